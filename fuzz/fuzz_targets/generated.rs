@@ -0,0 +1,31 @@
+//! `cargo +nightly fuzz run generated`: the parser accepts every program generated from the
+//! grammar and rejects every near miss. The input selects the generator's seed and limits.
+#![no_main]
+use cb_3::grammar::{Generator, GeneratorOptions, Grammar};
+use cb_3::C1Parser;
+use libfuzzer_sys::fuzz_target;
+use std::sync::OnceLock;
+
+static GRAMMAR: OnceLock<Grammar> = OnceLock::new();
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&[depth, repeat], seed)) = data.split_first_chunk::<2>() else {
+        return;
+    };
+    let mut seed_bytes = [0; 8];
+    for (index, byte) in seed.iter().enumerate() {
+        seed_bytes[index % 8] ^= byte;
+    }
+    let options = GeneratorOptions {
+        max_depth: 1 + usize::from(depth) % 32,
+        max_repeat: usize::from(repeat) % 6,
+        seed: u64::from_le_bytes(seed_bytes),
+        ..GeneratorOptions::default()
+    };
+    let mut generator = Generator::new(GRAMMAR.get_or_init(Grammar::c1), options);
+
+    let program = generator.program();
+    assert!(C1Parser::parse(&program).is_ok(), "rejected {}", program);
+    let invalid = generator.invalid_program();
+    assert!(C1Parser::parse(&invalid).is_err(), "accepted {}", invalid);
+});
@@ -0,0 +1,17 @@
+//! `cargo +nightly fuzz run lexer`: lexing arbitrary text never panics and loses nothing
+#![no_main]
+use cb_3::{tokenize, C1Lexer};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    let restored: String = C1Lexer::with_trivia(text).map(|token| token.text).collect();
+    assert_eq!(restored, text);
+
+    if let Ok(tokens) = tokenize(text) {
+        let replayed: Vec<_> = C1Lexer::from_tokens(text, &tokens).collect();
+        assert_eq!(replayed, tokens);
+    }
+});
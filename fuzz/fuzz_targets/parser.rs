@@ -0,0 +1,21 @@
+//! `cargo +nightly fuzz run parser`: parsing arbitrary text returns Ok or Err, never panics
+#![no_main]
+use cb_3::cst::Cst;
+use cb_3::{C1Parser, Dialect, ParserOptions};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    let result = C1Parser::parse_program(text);
+    let cst = Cst::parse(text);
+    assert_eq!(cst.to_string(), text);
+    assert_eq!(cst.error().is_some(), result.is_err());
+
+    let options = ParserOptions {
+        dialect: Dialect::C1,
+        ..ParserOptions::default()
+    };
+    let _ = C1Parser::with_options(text, options).program();
+});
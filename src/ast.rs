@@ -0,0 +1,254 @@
+/// Return type of a function definition
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Type {
+    Boolean,
+    Float,
+    Int,
+    Void,
+}
+
+/// A whole program, i.e. a list of function definitions and, in C1, declarations of global
+/// variables
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Program {
+    pub functions: Vec<FunctionDefinition>,
+    /// Always empty in C(-1)
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub globals: Vec<Declaration>,
+}
+
+/// functiondefinition ::= type <ID> "(" ( parameterlist )? ")" "{" statementlist "}"
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FunctionDefinition {
+    pub return_type: Type,
+    pub name: String,
+    /// Always empty in C(-1)
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub parameters: Vec<Parameter>,
+    pub body: Vec<Statement>,
+    /// Line of the function's name
+    pub line: usize,
+}
+
+/// One `type <ID>` of a parameterlist
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Parameter {
+    pub parameter_type: Type,
+    pub name: String,
+    pub line: usize,
+}
+
+/// declassignment ::= type <ID> ( "=" assignment )?
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Declaration {
+    pub variable_type: Type,
+    pub name: String,
+    pub value: Option<Expression>,
+    /// Line of the type
+    pub line: usize,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Statement {
+    pub kind: StatementKind,
+    /// Line of the first token of the statement
+    pub line: usize,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum StatementKind {
+    /// "{" statementlist "}"
+    Block(Vec<Statement>),
+    /// <KW_IF> "(" assignment ")" block ( <KW_ELSE> block )?
+    If {
+        condition: Expression,
+        body: Box<Statement>,
+        /// Always None in C(-1)
+        #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+        else_body: Option<Box<Statement>>,
+    },
+    /// <KW_WHILE> "(" assignment ")" block
+    While {
+        condition: Expression,
+        body: Box<Statement>,
+    },
+    /// <KW_DO> block <KW_WHILE> "(" assignment ")"
+    DoWhile {
+        body: Box<Statement>,
+        condition: Expression,
+    },
+    /// <KW_FOR> "(" ( statassignment | declassignment ) ";" expr ";" statassignment ")" block
+    For {
+        init: Box<Statement>,
+        condition: Expression,
+        step: Box<Statement>,
+        body: Box<Statement>,
+    },
+    /// <KW_RETURN> ( assignment )?
+    Return(Option<Expression>),
+    /// <KW_PRINTF> "(" ( assignment | <CONST_STRING> ) ")"
+    Printf(Expression),
+    /// declassignment
+    Declaration(Declaration),
+    /// <ID> "=" assignment
+    Assignment { target: String, value: Expression },
+    /// functioncall
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_call"))]
+    Call {
+        function: String,
+        arguments: Vec<Expression>,
+    },
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Expression {
+    pub kind: ExpressionKind,
+    /// Line of the first token of the expression
+    pub line: usize,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ExpressionKind {
+    /// <ID> "=" assignment, evaluates to the assigned value
+    Assign {
+        target: String,
+        value: Box<Expression>,
+    },
+    Binary {
+        operator: BinaryOperator,
+        lhs: Box<Expression>,
+        rhs: Box<Expression>,
+    },
+    /// The optional leading "-" of a simpexpr
+    Negate(Box<Expression>),
+    /// Integer constant that fits into an i64
+    Int(i64),
+    /// Integer constant that does not fit into an i64, with its digits. The grammar allows any
+    /// number of digits; the `integer-out-of-range` lint reports such constants and lowering
+    /// saturates them to i64::MAX.
+    IntOutOfRange(String),
+    Float(f64),
+    Bool(bool),
+    /// The text between the quotes of a string constant, only printed by printf
+    String(String),
+    /// <ID> "(" ( assignment ( "," assignment )* )? ")", the arguments are always empty in C(-1)
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_call"))]
+    Call {
+        function: String,
+        arguments: Vec<Expression>,
+    },
+    Variable(String),
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum BinaryOperator {
+    /// ==
+    Equal,
+    /// !=
+    NotEqual,
+    /// <
+    Less,
+    /// >
+    Greater,
+    /// <=
+    LessEqual,
+    /// >=
+    GreaterEqual,
+    /// +
+    Add,
+    /// -
+    Subtract,
+    /// ||
+    Or,
+    /// *
+    Multiply,
+    /// /
+    Divide,
+    /// &&
+    And,
+}
+
+impl Type {
+    /// Return the keyword of the type as it is written in the source
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            Type::Boolean => "bool",
+            Type::Float => "float",
+            Type::Int => "int",
+            Type::Void => "void",
+        }
+    }
+}
+
+impl BinaryOperator {
+    /// Return the operator as it is written in the source
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOperator::Equal => "==",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::Less => "<",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::LessEqual => "<=",
+            BinaryOperator::GreaterEqual => ">=",
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Or => "||",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::And => "&&",
+        }
+    }
+}
+
+impl Program {
+    /// Return the function definition with the given name, if there is one
+    pub fn function(&self, name: &str) -> Option<&FunctionDefinition> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+}
+
+impl Expression {
+    /// Create a binary expression located at the line of its left operand
+    pub fn binary(operator: BinaryOperator, lhs: Expression, rhs: Expression) -> Expression {
+        Expression {
+            line: lhs.line,
+            kind: ExpressionKind::Binary {
+                operator,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            },
+        }
+    }
+}
+
+/// Serialize a call without arguments as the name of the function, like C(-1) calls
+#[cfg(feature = "serde")]
+fn serialize_call<S: serde::Serializer>(
+    function: &String,
+    arguments: &Vec<Expression>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    use serde::ser::SerializeStruct;
+
+    if arguments.is_empty() {
+        return serializer.serialize_str(function);
+    }
+    let mut call = serializer.serialize_struct("Call", 2)?;
+    call.serialize_field("function", function)?;
+    call.serialize_field("arguments", arguments)?;
+    call.end()
+}
@@ -0,0 +1,15 @@
+//! Language server for C(-1) files. Speaks the Language Server Protocol on stdin and stdout.
+//! Built with the lsp feature only, e.g. `cargo run --features lsp --bin c1-lsp`.
+use std::io;
+use std::process;
+
+fn main() {
+    let stdin = io::stdin();
+    match cb_3::lsp::run(stdin.lock(), io::stdout()) {
+        Ok(code) => process::exit(code),
+        Err(error) => {
+            eprintln!("c1-lsp: {}", error);
+            process::exit(1);
+        }
+    }
+}
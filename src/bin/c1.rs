@@ -0,0 +1,177 @@
+//! Command line front end for the C(-1) parser.
+//!
+//! Reads a file, or stdin if the file is "-" or missing, and prints its tokens or syntax tree.
+//! The commands printing the syntax tree also take several files forming one program. The JSON
+//! output needs the serde feature, e.g. `cargo run --features serde --bin c1 -- json file.c1`.
+use cb_3::ast::Program;
+use cb_3::call_graph::CallGraph;
+use cb_3::diagnostic::Diagnostic;
+use cb_3::dot;
+use cb_3::grammar::{Analysis, Automaton, Grammar};
+use cb_3::sexp::Sexp;
+use cb_3::source::SourceMap;
+use cb_3::{C1Parser, Dialect, ParseError, ParserOptions};
+use std::io::{self, Read};
+use std::{env, fs, process};
+
+const USAGE: &str = "usage: c1 <command> [file]...
+
+options:
+    --c1       accept full C1 instead of C(-1) in check and the commands printing the
+               syntax tree
+    --color    colour error messages with ANSI escape sequences
+
+commands:
+    check      check the syntax of the files
+    tokens     print the tokens as JSON
+    json       print the syntax tree as JSON
+    sexp       print the syntax tree as S-expression
+    dot-parse  print the derivation tree as Graphviz graph
+    dot-ast    print the syntax tree as Graphviz graph
+    dot-calls  print the call graph as Graphviz graph
+    trace      print the rules entered and exited by the parser
+    ll1        print the FIRST and FOLLOW sets and LL(1) conflicts of an EBNF grammar, by
+               default of the C(-1) grammar
+    lalr       print the LR(0) item sets with LALR(1) lookaheads and the conflicts of an EBNF
+               grammar, by default of the C(-1) grammar";
+
+fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let dialect = match option(&mut args, "--c1") {
+        true => Dialect::C1,
+        false => Dialect::CMinus1,
+    };
+    let colour = option(&mut args, "--color");
+    let (command, paths) = match args.split_first() {
+        Some((command, [])) => (command.as_str(), &["-".to_string()][..]),
+        Some((command, paths)) => (command.as_str(), paths),
+        None => usage(),
+    };
+    if command == "ll1" || command == "lalr" {
+        let grammar = match args.as_slice() {
+            [_] => Grammar::c1(),
+            [_, path] => {
+                let text = read(path).unwrap_or_else(|error| fail(&format!("{}: {}", path, error)));
+                Grammar::parse(&text).unwrap_or_else(|error| fail(&error.to_string()))
+            }
+            _ => usage(),
+        };
+        if command == "ll1" {
+            print!("{}", Analysis::new(&grammar));
+        } else {
+            print!("{}", Automaton::new(&grammar));
+        }
+        return;
+    }
+    let parses = ["check", "json", "sexp", "dot-ast", "dot-calls"].contains(&command);
+    let reads_one = ["tokens", "dot-parse", "trace"].contains(&command);
+    if !(parses || reads_one && paths.len() == 1) {
+        usage();
+    }
+    let mut sources = SourceMap::new();
+    for path in paths {
+        match read(path) {
+            Ok(text) => sources.add(if path == "-" { "<stdin>" } else { path }, text),
+            Err(error) => fail(&format!("{}: {}", path, error)),
+        };
+    }
+    let parse = || {
+        let options = ParserOptions {
+            dialect,
+            ..ParserOptions::default()
+        };
+        let result = sources.parse_program(options);
+        result.unwrap_or_else(|error| report(&sources, &error, colour))
+    };
+    match command {
+        "check" => {
+            parse();
+        }
+        "json" => json(&parse()),
+        "sexp" => println!("{}", Sexp(&parse())),
+        "dot-ast" => print!("{}", dot::syntax_tree(&parse())),
+        "dot-calls" => print!("{}", dot::call_graph(&CallGraph::new(&parse()))),
+        "tokens" => tokens(&sources, colour),
+        "dot-parse" => print!("{}", dot::parse_tree(sources_text(&sources))),
+        "trace" => {
+            let text = sources_text(&sources);
+            let result =
+                C1Parser::parse_program_with_trace(text, &mut |entry| println!("{}", entry));
+            if let Err(error) = result {
+                report(&sources, &error, colour);
+            }
+        }
+        _ => usage(),
+    }
+}
+
+/// Return the text of the only file
+fn sources_text(sources: &SourceMap) -> &str {
+    sources.files().next().map_or("", |file| file.text())
+}
+
+/// Remove an option from the arguments and return whether it was given
+fn option(args: &mut Vec<String>, name: &str) -> bool {
+    let given = args.iter().any(|arg| arg == name);
+    args.retain(|arg| arg != name);
+    given
+}
+
+/// Print a syntax error with the source lines it occurred in and exit
+fn report(sources: &SourceMap, error: &ParseError, colour: bool) -> ! {
+    let diagnostic = Diagnostic::from_parse_error(error, sources);
+    eprint!("{}", diagnostic.render(sources, colour));
+    process::exit(1)
+}
+
+fn read(path: &str) -> io::Result<String> {
+    if path == "-" {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text)?;
+        Ok(text)
+    } else {
+        fs::read_to_string(path)
+    }
+}
+
+fn tokens(sources: &SourceMap, colour: bool) {
+    match cb_3::tokenize(sources_text(sources)) {
+        Ok(tokens) => print_json(&tokens),
+        Err(errors) => {
+            let rendered: Vec<String> = errors
+                .iter()
+                .map(|error| Diagnostic::error("unrecognized text", error.span.clone()))
+                .map(|diagnostic| diagnostic.render(sources, colour))
+                .collect();
+            eprint!("{}", rendered.join("\n"));
+            process::exit(1)
+        }
+    }
+}
+
+fn json(program: &Program) {
+    print_json(program)
+}
+
+#[cfg(feature = "serde")]
+fn print_json(value: &impl serde::Serialize) {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => println!("{}", json),
+        Err(error) => fail(&error.to_string()),
+    }
+}
+
+#[cfg(not(feature = "serde"))]
+fn print_json<T>(_: &T) {
+    fail("printing JSON needs the serde feature")
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2)
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("c1: {}", message);
+    process::exit(1)
+}
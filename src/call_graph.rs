@@ -0,0 +1,99 @@
+use crate::ast::{Expression, Program};
+use crate::visit::{walk_call, walk_function, Visitor};
+use std::collections::HashSet;
+
+/// Which function definition calls which functions
+#[derive(Debug, Clone)]
+pub struct CallGraph {
+    /// Defined functions in the order of their definition, each with the names of the functions
+    /// it calls in the order of the first call
+    functions: Vec<(String, Vec<String>)>,
+}
+
+impl CallGraph {
+    pub fn new(program: &Program) -> CallGraph {
+        let functions = program
+            .functions
+            .iter()
+            .map(|function| {
+                let mut callees = Callees(Vec::new());
+                walk_function(&mut callees, function);
+                (function.name.clone(), callees.0)
+            })
+            .collect();
+        CallGraph { functions }
+    }
+
+    /// Return the names of all defined functions in the order of their definition
+    pub fn functions(&self) -> impl Iterator<Item = &str> {
+        self.functions.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Return the functions called by the given function. Calls of undefined functions are
+    /// included.
+    pub fn callees(&self, function: &str) -> &[String] {
+        self.functions
+            .iter()
+            .find(|(name, _)| name == function)
+            .map(|(_, callees)| callees.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Return the defined functions calling the given function
+    pub fn callers(&self, function: &str) -> Vec<&str> {
+        self.functions
+            .iter()
+            .filter(|(_, callees)| callees.iter().any(|callee| callee == function))
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// Return the names of all functions reachable from `root` through calls, including `root`
+    pub fn reachable_from<'a>(&'a self, root: &'a str) -> HashSet<&'a str> {
+        let mut reachable = HashSet::new();
+        let mut worklist = vec![root];
+        while let Some(function) = worklist.pop() {
+            if reachable.insert(function) {
+                worklist.extend(self.callees(function).iter().map(String::as_str));
+            }
+        }
+        reachable
+    }
+}
+
+/// Collects the names of the called functions in the order of the first call
+struct Callees(Vec<String>);
+
+impl Visitor for Callees {
+    fn visit_call(&mut self, function: &str, arguments: &[Expression], _: usize) {
+        if !self.0.iter().any(|callee| callee == function) {
+            self.0.push(function.to_string());
+        }
+        walk_call(self, arguments);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::call_graph::CallGraph;
+    use crate::C1Parser;
+    use std::collections::HashSet;
+
+    #[test]
+    fn calls_of_example_program() {
+        let program = C1Parser::parse_program(include_str!("../tests/data/beispiel.c-1")).unwrap();
+        let graph = CallGraph::new(&program);
+        assert_eq!(
+            graph.functions().collect::<Vec<_>>(),
+            vec!["blub", "blah", "main"]
+        );
+        assert!(graph.callees("blub").is_empty());
+        assert_eq!(graph.callees("blah"), &["blub"]);
+        assert_eq!(graph.callees("main"), &["blub", "blah"]);
+        assert_eq!(graph.callers("blub"), vec!["blah", "main"]);
+        assert_eq!(
+            graph.reachable_from("blah"),
+            HashSet::from(["blah", "blub"])
+        );
+    }
+}
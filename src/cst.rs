@@ -0,0 +1,471 @@
+//! Lossless concrete syntax tree.
+//!
+//! Unlike the [syntax tree](crate::ast), the concrete syntax tree keeps every byte of the input:
+//! each grammar rule invoked by [`C1Parser`] becomes a [`Node`], each consumed token a
+//! [`SyntaxToken`], and the whitespace, line breaks and comments between tokens become trivia
+//! tokens. Printing the tree reproduces the input, even if it contains syntax errors:
+//! ```
+//! use cb_3::cst::Cst;
+//!
+//! let text = "int main() { // entry\n  return 0;\n}";
+//! let cst = Cst::parse(text);
+//! assert_eq!(cst.to_string(), text);
+//!
+//! let main = cst.functions().next().unwrap();
+//! assert_eq!(main.name().unwrap().text, "main");
+//! assert_eq!(main.node().comments().next().unwrap().text, "// entry\n");
+//! ```
+use crate::lexer::{C1Lexer, C1Token};
+use crate::parser::{C1Parser, ParseError, ParseEvent, Rule};
+use std::fmt;
+use std::ops::Range;
+
+/// A token or trivia with its text
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SyntaxToken {
+    pub token: C1Token,
+    pub text: String,
+    pub span: Range<usize>,
+}
+
+impl SyntaxToken {
+    /// Check whether the token is whitespace, a line break or a comment
+    pub fn is_trivia(&self) -> bool {
+        self.token.is_trivia()
+    }
+
+    pub fn is_comment(&self) -> bool {
+        matches!(self.token, C1Token::CComment | C1Token::CPPComment)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum NodeKind {
+    /// The tokens consumed while C1Parser parsed the rule
+    Rule(Rule),
+    /// The tokens after a syntax error
+    Error,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Element {
+    Node(Node),
+    Token(SyntaxToken),
+}
+
+/// An inner node of the concrete syntax tree
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub children: Vec<Element>,
+}
+
+impl Node {
+    /// Return the grammar rule of the node, None for the tokens after a syntax error
+    pub fn rule(&self) -> Option<Rule> {
+        match self.kind {
+            NodeKind::Rule(rule) => Some(rule),
+            NodeKind::Error => None,
+        }
+    }
+
+    /// Return the child nodes
+    pub fn nodes(&self) -> impl Iterator<Item = &Node> {
+        self.children.iter().filter_map(|child| match child {
+            Element::Node(node) => Some(node),
+            Element::Token(_) => None,
+        })
+    }
+
+    /// Return the first child node of the rule
+    pub fn child(&self, rule: Rule) -> Option<&Node> {
+        self.nodes().find(|node| node.rule() == Some(rule))
+    }
+
+    /// Return the child tokens that are no trivia
+    pub fn tokens(&self) -> impl Iterator<Item = &SyntaxToken> {
+        self.children.iter().filter_map(|child| match child {
+            Element::Token(token) if !token.is_trivia() => Some(token),
+            _ => None,
+        })
+    }
+
+    /// Return the first child token of the kind
+    pub fn token(&self, token: C1Token) -> Option<&SyntaxToken> {
+        self.tokens().find(|child| child.token == token)
+    }
+
+    /// Return all tokens and trivia below the node in the order of the text
+    pub fn leaves(&self) -> Vec<&SyntaxToken> {
+        let mut leaves = Vec::new();
+        self.collect_leaves(&mut leaves);
+        leaves
+    }
+
+    fn collect_leaves<'a>(&'a self, leaves: &mut Vec<&'a SyntaxToken>) {
+        for child in &self.children {
+            match child {
+                Element::Node(node) => node.collect_leaves(leaves),
+                Element::Token(token) => leaves.push(token),
+            }
+        }
+    }
+
+    /// Return all comments below the node
+    pub fn comments(&self) -> impl Iterator<Item = &SyntaxToken> {
+        self.leaves().into_iter().filter(|leaf| leaf.is_comment())
+    }
+
+    /// Return the byte range of the text below the node, None if the node is empty
+    pub fn span(&self) -> Option<Range<usize>> {
+        let leaves = self.leaves();
+        Some(leaves.first()?.span.start..leaves.last()?.span.end)
+    }
+}
+
+impl fmt::Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for child in &self.children {
+            match child {
+                Element::Node(node) => write!(f, "{}", node)?,
+                Element::Token(token) => f.write_str(&token.text)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The concrete syntax tree of a text
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Cst {
+    root: Node,
+    error: Option<ParseError>,
+}
+
+impl Cst {
+    /// Parse the text. The tree of an erroneous text contains the rules up to the syntax error,
+    /// followed by an error node with the remaining tokens.
+    pub fn parse(text: &str) -> Cst {
+        let mut builder = Builder {
+            text,
+            position: 0,
+            stack: Vec::new(),
+        };
+        let error = {
+            let mut listener = |event: ParseEvent| builder.event(event);
+            let mut parser = C1Parser::with_listener(text, &mut listener);
+            let error = parser.program().err();
+            parser.skip_rest();
+            error
+        };
+        Cst {
+            root: builder.finish(),
+            error,
+        }
+    }
+
+    /// Return the node of the program rule
+    pub fn root(&self) -> &Node {
+        &self.root
+    }
+
+    /// Return the syntax error, if any
+    pub fn error(&self) -> Option<&ParseError> {
+        self.error.as_ref()
+    }
+
+    /// Return the function definitions, the last one incomplete if there is a syntax error in it
+    pub fn functions(&self) -> impl Iterator<Item = FunctionNode<'_>> {
+        self.root.nodes().filter_map(FunctionNode::cast)
+    }
+}
+
+impl fmt::Display for Cst {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.root)
+    }
+}
+
+/// A node of the functiondefinition rule
+#[derive(Debug, Copy, Clone)]
+pub struct FunctionNode<'a>(&'a Node);
+
+impl<'a> FunctionNode<'a> {
+    pub fn cast(node: &'a Node) -> Option<FunctionNode<'a>> {
+        (node.rule() == Some(Rule::FunctionDefinition)).then_some(FunctionNode(node))
+    }
+
+    pub fn node(&self) -> &'a Node {
+        self.0
+    }
+
+    /// Return the type keyword
+    pub fn return_type(&self) -> Option<&'a SyntaxToken> {
+        self.0.child(Rule::Type)?.tokens().next()
+    }
+
+    pub fn name(&self) -> Option<&'a SyntaxToken> {
+        self.0.token(C1Token::Identifier)
+    }
+
+    /// Return the statements of the body
+    pub fn statements(&self) -> impl Iterator<Item = StatementNode<'a>> {
+        statements(self.0)
+    }
+}
+
+/// A node of the block rule, i.e. a statement or statements in braces
+#[derive(Debug, Copy, Clone)]
+pub struct StatementNode<'a>(&'a Node);
+
+impl<'a> StatementNode<'a> {
+    pub fn cast(node: &'a Node) -> Option<StatementNode<'a>> {
+        (node.rule() == Some(Rule::Block)).then_some(StatementNode(node))
+    }
+
+    pub fn node(&self) -> &'a Node {
+        self.0
+    }
+
+    /// Return the rule of the statement: Block for statements in braces, otherwise IfStatement,
+    /// ReturnStatement, Printf, StatAssignment or FunctionCall. Statement if the statement is
+    /// incomplete.
+    pub fn kind(&self) -> Rule {
+        match self.0.child(Rule::Statement) {
+            Some(statement) => statement
+                .nodes()
+                .next()
+                .and_then(Node::rule)
+                .unwrap_or(Rule::Statement),
+            None => Rule::Block,
+        }
+    }
+
+    /// Return the statements in braces, or the body of an if statement
+    pub fn statements(&self) -> impl Iterator<Item = StatementNode<'a>> {
+        let parent = match self.if_statement() {
+            Some(if_statement) => if_statement,
+            None => self.0,
+        };
+        statements(parent).chain(parent.nodes().filter_map(StatementNode::cast))
+    }
+
+    /// Return the assignment node of an if condition
+    pub fn condition(&self) -> Option<&'a Node> {
+        self.if_statement()?.child(Rule::Assignment)
+    }
+
+    fn if_statement(&self) -> Option<&'a Node> {
+        self.0.child(Rule::Statement)?.child(Rule::IfStatement)
+    }
+}
+
+/// Return the statements in the statement list below the node
+fn statements(node: &Node) -> impl Iterator<Item = StatementNode<'_>> {
+    node.child(Rule::StatementList)
+        .into_iter()
+        .flat_map(|list| list.nodes().filter_map(StatementNode::cast))
+}
+
+/// Builds the tree from the events of C1Parser
+struct Builder<'t> {
+    text: &'t str,
+    /// End of the text that is already part of the tree
+    position: usize,
+    /// The nodes of the rules currently parsed. The program node stays at the bottom.
+    stack: Vec<Node>,
+}
+
+impl<'t> Builder<'t> {
+    fn event(&mut self, event: ParseEvent) {
+        match event {
+            ParseEvent::Enter { rule, token } => {
+                let start = token.map_or(self.text.len(), |token| token.span.start);
+                // Trivia before a rule belongs to the enclosing rule, so that it ends up before
+                // the outermost rule starting at the next token
+                if !self.stack.is_empty() {
+                    self.trivia(start);
+                }
+                self.stack.push(Node {
+                    kind: NodeKind::Rule(rule),
+                    children: Vec::new(),
+                });
+                self.trivia(start);
+            }
+            ParseEvent::Token(token) => {
+                self.trivia(token.span.start);
+                self.position = token.span.end;
+                self.push(Element::Token(SyntaxToken {
+                    token: token.token,
+                    text: token.text.to_string(),
+                    span: token.span,
+                }));
+            }
+            ParseEvent::Exit { rule, error, .. } => {
+                if self.stack.len() > 1 {
+                    let node = self.stack.pop().unwrap();
+                    self.push(Element::Node(node));
+                } else if rule == Rule::Program && error.is_some() {
+                    self.stack.push(Node {
+                        kind: NodeKind::Error,
+                        children: Vec::new(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Add the trivia up to `end` to the current node
+    fn trivia(&mut self, end: usize) {
+        let start = self.position;
+        let mut lexer = C1Lexer::with_trivia(&self.text[start..end]);
+        while let (Some(token), Some(text), Some(span)) = (
+            lexer.current_token(),
+            lexer.current_text(),
+            lexer.current_span(),
+        ) {
+            self.push(Element::Token(SyntaxToken {
+                token,
+                text: text.to_string(),
+                span: start + span.start..start + span.end,
+            }));
+            lexer.eat();
+        }
+        self.position = end;
+    }
+
+    fn push(&mut self, element: Element) {
+        self.stack
+            .last_mut()
+            .expect("the program rule is entered first")
+            .children
+            .push(element);
+    }
+
+    fn finish(mut self) -> Node {
+        if self.stack.len() > 1 {
+            let error = self.stack.pop().unwrap();
+            if !error.children.is_empty() {
+                self.push(Element::Node(error));
+            }
+        }
+        self.trivia(self.text.len());
+        self.stack.pop().expect("the program rule is entered first")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cst::{Cst, Element, NodeKind};
+    use crate::{C1Token, Rule};
+
+    const EXAMPLE: &str = include_str!("../tests/data/beispiel.c-1");
+
+    /// Check that the tree prints the text and its leaves cover the text without gaps
+    fn assert_lossless(text: &str) {
+        let cst = Cst::parse(text);
+        assert_eq!(cst.to_string(), text);
+        let mut position = 0;
+        for leaf in cst.root().leaves() {
+            assert_eq!(
+                leaf.span.start, position,
+                "gap before {:?} in {:?}",
+                leaf, text
+            );
+            assert_eq!(&text[leaf.span.clone()], leaf.text);
+            position = leaf.span.end;
+        }
+        assert_eq!(position, text.len());
+    }
+
+    #[test]
+    fn every_byte_is_kept() {
+        for text in [
+            "",
+            " \t\n",
+            "/* only a comment */",
+            "// no line break",
+            "int",
+            "void f() { x = ; } // after the error\n",
+            "void f() {}\r\n\r\n  /* between */ int g() { return /*inner*/ 1; }\n",
+            "void f() { printf(\"string\"); } /* unterminated",
+            "ä void",
+            EXAMPLE,
+        ] {
+            assert_lossless(text);
+        }
+        // Deleting parts of the example gives all kinds of syntax errors
+        for start in (0..EXAMPLE.len()).step_by(7) {
+            let mut text = EXAMPLE.to_string();
+            text.replace_range(start..(start + 5).min(EXAMPLE.len()), "");
+            assert_lossless(&text);
+        }
+    }
+
+    #[test]
+    fn trivia_is_attached_to_the_enclosing_rule() {
+        let cst = Cst::parse("// header\nint blub() {\n  /* c */ x = 1;\n}\n");
+        let root = cst.root();
+        assert_eq!(root.rule(), Some(Rule::Program));
+        let Element::Token(header) = &root.children[0] else {
+            panic!("expected trivia first");
+        };
+        assert_eq!(header.token, C1Token::CPPComment);
+
+        let function = cst.functions().next().unwrap();
+        assert_eq!(function.return_type().unwrap().text, "int");
+        assert_eq!(function.name().unwrap().text, "blub");
+        let statements: Vec<_> = function.statements().collect();
+        assert_eq!(statements.len(), 1);
+        assert_eq!(statements[0].kind(), Rule::StatAssignment);
+        assert_eq!(statements[0].node().to_string(), "x = 1;");
+        // Trivia goes before the outermost rule starting at the next token, which is the
+        // statement list here
+        assert!(function
+            .node()
+            .children
+            .iter()
+            .any(|child| matches!(child, Element::Token(token) if token.text == "/* c */")));
+        assert_eq!(statements[0].node().comments().count(), 0);
+    }
+
+    #[test]
+    fn nested_statements() {
+        let cst = Cst::parse(EXAMPLE);
+        let blah = cst.functions().nth(1).unwrap();
+        let kinds: Vec<_> = blah
+            .statements()
+            .map(|statement| statement.kind())
+            .collect();
+        use Rule::*;
+        assert_eq!(
+            kinds,
+            vec![StatAssignment, StatAssignment, IfStatement, ReturnStatement]
+        );
+        let outer_if = blah.statements().nth(2).unwrap();
+        assert_eq!(outer_if.condition().unwrap().to_string(), "a < blub()");
+        let body: Vec<_> = outer_if.statements().collect();
+        assert_eq!(body.len(), 1);
+        assert_eq!(body[0].kind(), Block);
+        let inner_if = body[0].statements().next().unwrap();
+        assert_eq!(inner_if.kind(), IfStatement);
+        assert_eq!(
+            inner_if.statements().next().unwrap().node().to_string(),
+            "{\n\t\t\tprintf(blub() + blub());\n\t\t}"
+        );
+    }
+
+    #[test]
+    fn tokens_after_a_syntax_error() {
+        let cst = Cst::parse("void f() {}\nvoid g() { x = ; }\nint h() {}");
+        assert_eq!(cst.error().unwrap().text.as_deref(), Some(";"));
+        assert_eq!(cst.functions().count(), 2);
+        let error = cst.root().nodes().last().unwrap();
+        assert_eq!(error.kind, NodeKind::Error);
+        assert_eq!(error.to_string(), "; }\nint h() {}");
+        // The rules entered before the error keep what they consumed
+        let partial = cst.root().nodes().nth(1).unwrap();
+        assert_eq!(partial.rule(), Some(Rule::FunctionDefinition));
+        assert_eq!(partial.to_string(), "void g() { x = ");
+    }
+}
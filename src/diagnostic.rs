@@ -0,0 +1,307 @@
+//! Diagnostics rendered for terminals, showing the source lines they point at.
+//!
+//! ```
+//! use cb_3::diagnostic::Diagnostic;
+//! use cb_3::source::SourceMap;
+//!
+//! let mut sources = SourceMap::new();
+//! sources.add("main.c1", "void main() {\n  printf(1);\n");
+//! let error = sources.parse_program(Default::default()).unwrap_err();
+//! let diagnostic = Diagnostic::from_parse_error(&error, &sources);
+//! assert_eq!(
+//!     diagnostic.render(&sources, false),
+//!     "\
+//! error: unexpected token
+//!  --> main.c1:3:1
+//!   |
+//! 1 | void main() {
+//!   |             - this `{` was never closed
+//! 2 |   printf(1);
+//! 3 |
+//!   | ^ the file ends here
+//! "
+//! );
+//! ```
+use crate::parser::ParseError;
+use crate::source::{Location, SourceMap};
+use crate::{C1Lexer, C1Token};
+use std::fmt;
+use std::ops::Range;
+
+/// Tabs in source lines are shown as this many spaces, so that the markers under them line up
+const TAB_WIDTH: usize = 4;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A byte range of a [`SourceMap`] with a message shown under it
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Label {
+    pub span: Range<usize>,
+    /// May be empty to underline the span only
+    pub message: String,
+}
+
+/// A message about a place in the source, with related places
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    /// Where the problem is, underlined with carets
+    pub primary: Label,
+    /// Related places, underlined with dashes
+    pub secondary: Vec<Label>,
+}
+
+impl Diagnostic {
+    pub fn error(message: impl Into<String>, span: Range<usize>) -> Diagnostic {
+        Diagnostic::new(Severity::Error, message.into(), span)
+    }
+
+    pub fn warning(message: impl Into<String>, span: Range<usize>) -> Diagnostic {
+        Diagnostic::new(Severity::Warning, message.into(), span)
+    }
+
+    fn new(severity: Severity, message: String, span: Range<usize>) -> Diagnostic {
+        Diagnostic {
+            severity,
+            message,
+            primary: Label {
+                span,
+                message: String::new(),
+            },
+            secondary: Vec::new(),
+        }
+    }
+
+    /// Show a message under the carets
+    pub fn with_label(mut self, message: impl Into<String>) -> Diagnostic {
+        self.primary.message = message.into();
+        self
+    }
+
+    /// Point at a related place
+    pub fn with_secondary(mut self, span: Range<usize>, message: impl Into<String>) -> Diagnostic {
+        self.secondary.push(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    /// Describe a syntax error of a program parsed by [`SourceMap::parse_program`]. If the error
+    /// is at the end of the file or at a bracket closing a different one, the innermost bracket
+    /// still open is pointed at.
+    pub fn from_parse_error(error: &ParseError, sources: &SourceMap) -> Diagnostic {
+        let mut diagnostic = Diagnostic::error(error.reason.as_str(), error.span.clone());
+        if error.text.is_none() {
+            diagnostic = diagnostic.with_label("the file ends here");
+        }
+        let file = match sources.file_at(error.span.start) {
+            Some(file) => file,
+            None => return diagnostic,
+        };
+        let start = file.span().start;
+        let mut open = Vec::new();
+        for token in C1Lexer::new(file.text()) {
+            if start + token.span.start >= error.span.start {
+                break;
+            }
+            match token.kind {
+                C1Token::LeftParenthesis | C1Token::LeftBrace => open.push(token),
+                C1Token::RightParenthesis | C1Token::RightBrace => {
+                    open.pop();
+                }
+                _ => {}
+            }
+        }
+        let closing = match open.last().map(|token| token.kind) {
+            Some(C1Token::LeftParenthesis) => ")",
+            Some(_) => "}",
+            None => return diagnostic,
+        };
+        let bracket = open.pop().unwrap();
+        let span = start + bracket.span.start..start + bracket.span.end;
+        match error.text.as_deref() {
+            None => {
+                diagnostic.with_secondary(span, format!("this `{}` was never closed", bracket.text))
+            }
+            Some(text) if text != closing && (text == ")" || text == "}") => {
+                diagnostic.with_secondary(span, format!("this `{}` is still open", bracket.text))
+            }
+            Some(_) => diagnostic,
+        }
+    }
+
+    /// Render the diagnostic with the lines of all labels, optionally coloured with ANSI escape
+    /// sequences. Labels in other files than the primary label follow under their own location.
+    pub fn render(&self, sources: &SourceMap, colour: bool) -> String {
+        let style = Style(colour);
+        let mut labels: Vec<(Location, &Label, bool)> = Vec::new();
+        for (label, primary) in std::iter::once((&self.primary, true))
+            .chain(self.secondary.iter().map(|label| (label, false)))
+        {
+            if let Some(location) = sources.locate(label.span.start) {
+                labels.push((location, label, primary));
+            }
+        }
+        let width = labels
+            .iter()
+            .map(|(location, ..)| location.line.to_string().len())
+            .max()
+            .unwrap_or(1);
+
+        let mut output = format!(
+            "{}{}\n",
+            style.severity(self.severity),
+            style.bold(&format!(": {}", self.message))
+        );
+        // Files in the order of their first label, the primary one first
+        let mut files = Vec::new();
+        for (location, ..) in &labels {
+            if !files.contains(&location.file) {
+                files.push(location.file);
+            }
+        }
+        for (index, file) in files.into_iter().enumerate() {
+            let mut in_file: Vec<_> = labels
+                .iter()
+                .filter(|(location, ..)| location.file == file)
+                .collect();
+            let arrow = if index == 0 { "-->" } else { ":::" };
+            output += &format!("{:width$}{} {}\n", "", style.gutter(arrow), in_file[0].0);
+            output += &format!("{:width$} {}\n", "", style.gutter("|"));
+            in_file.sort_by_key(|(location, _, primary)| (location.line, !primary));
+            let mut previous: Option<usize> = None;
+            for (location, label, primary) in in_file {
+                let line = location.line;
+                if previous != Some(line) {
+                    match previous {
+                        Some(previous) if line == previous + 2 => {
+                            output += &source_line(sources, location, line - 1, width, style)
+                        }
+                        Some(previous) if line > previous + 2 => output += "...\n",
+                        _ => {}
+                    }
+                    output += &source_line(sources, location, line, width, style);
+                }
+                previous = Some(line);
+                let severity = primary.then_some(self.severity);
+                output += &marker_line(sources, location, label, severity, width, style);
+            }
+        }
+        output
+    }
+}
+
+/// Render a line of the file of `location`
+fn source_line(
+    sources: &SourceMap,
+    location: &Location,
+    line: usize,
+    width: usize,
+    style: Style,
+) -> String {
+    let number = style.gutter(&format!("{:>width$} |", line));
+    match expand_tabs(line_text(sources, location, line)) {
+        text if text.is_empty() => format!("{}\n", number),
+        text => format!("{} {}\n", number, text),
+    }
+}
+
+/// Render the carets of a primary label, which has the severity of the diagnostic, or the dashes
+/// of a secondary label, and its message
+fn marker_line(
+    sources: &SourceMap,
+    location: &Location,
+    label: &Label,
+    severity: Option<Severity>,
+    width: usize,
+    style: Style,
+) -> String {
+    let text = line_text(sources, location, location.line);
+    let before: String = text.chars().take(location.column - 1).collect();
+    let underlined: String = text
+        .chars()
+        .skip(location.column - 1)
+        .take(label.span.len())
+        .scan(label.span.len(), |left, char| {
+            // Count bytes, a span may end within the line
+            *left = left.checked_sub(char.len_utf8())?;
+            Some(char)
+        })
+        .collect();
+    let marker = if severity.is_some() { "^" } else { "-" };
+    let mut markers = marker.repeat(expand_tabs(&underlined).chars().count().max(1));
+    if !label.message.is_empty() {
+        markers = format!("{} {}", markers, label.message);
+    }
+    let markers = match severity {
+        Some(severity) => style.coloured(severity, &markers),
+        None => style.gutter(&markers),
+    };
+    format!(
+        "{:width$} {} {}{}\n",
+        "",
+        style.gutter("|"),
+        " ".repeat(expand_tabs(&before).chars().count()),
+        markers
+    )
+}
+
+/// Return the text of a line, counted from 1, of the file of `location`
+fn line_text<'a>(sources: &'a SourceMap, location: &Location, line: usize) -> &'a str {
+    let line = location.file.lines().start + line - 1;
+    sources
+        .line_span(line)
+        .and_then(|span| sources.slice(span))
+        .unwrap_or("")
+}
+
+fn expand_tabs(text: &str) -> String {
+    text.replace('\t', &" ".repeat(TAB_WIDTH))
+}
+
+/// Wraps text in ANSI escape sequences if colours are enabled
+#[derive(Copy, Clone)]
+struct Style(bool);
+
+impl Style {
+    fn paint(self, code: &str, text: &str) -> String {
+        match self.0 {
+            true => format!("\x1b[{}m{}\x1b[0m", code, text),
+            false => text.to_string(),
+        }
+    }
+
+    fn bold(self, text: &str) -> String {
+        self.paint("1", text)
+    }
+
+    fn gutter(self, text: &str) -> String {
+        self.paint("1;34", text)
+    }
+
+    fn coloured(self, severity: Severity, text: &str) -> String {
+        match severity {
+            Severity::Error => self.paint("1;31", text),
+            Severity::Warning => self.paint("1;33", text),
+        }
+    }
+
+    fn severity(self, severity: Severity) -> String {
+        self.coloured(severity, &severity.to_string())
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
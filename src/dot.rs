@@ -0,0 +1,324 @@
+//! Graphviz export of parse trees, syntax trees and call graphs.
+//!
+//! Each function returns a graph in the DOT language, which `dot -Tsvg` renders:
+//! ```
+//! use cb_3::dot;
+//!
+//! let graph = dot::parse_tree("void main() {}");
+//! assert!(graph.starts_with("digraph parse_tree {"));
+//! assert!(graph.contains("n1 [label=\"functiondefinition\"];"));
+//! assert!(graph.contains("n3 [label=\"void\", shape=box];"));
+//! ```
+use crate::ast::{
+    Declaration, Expression, ExpressionKind, FunctionDefinition, Program, Statement, StatementKind,
+};
+use crate::call_graph::CallGraph;
+use crate::cst::{Cst, Element, Node, NodeKind};
+use crate::visit::{
+    walk_declaration, walk_expression, walk_function, walk_program, walk_statement, Visitor,
+};
+use std::fmt::Write;
+
+/// Return the derivation tree of the text: one node per rule invoked by
+/// [`C1Parser`](crate::C1Parser) and the consumed tokens as leaves. After a syntax error, the
+/// remaining tokens hang below an error node.
+pub fn parse_tree(text: &str) -> String {
+    let mut graph = Graph::new("parse_tree");
+    parse_tree_node(&mut graph, Cst::parse(text).root());
+    graph.finish()
+}
+
+fn parse_tree_node(graph: &mut Graph, node: &Node) -> usize {
+    let id = match node.kind {
+        NodeKind::Rule(rule) => graph.node(rule.name(), ""),
+        NodeKind::Error => graph.node("error", ", color=red"),
+    };
+    for child in &node.children {
+        let child = match child {
+            Element::Node(node) => parse_tree_node(graph, node),
+            Element::Token(token) if !token.is_trivia() => graph.node(&token.text, ", shape=box"),
+            Element::Token(_) => continue,
+        };
+        graph.edge(id, child);
+    }
+    id
+}
+
+/// Return the abstract syntax tree of the program
+pub fn syntax_tree(program: &Program) -> String {
+    let mut builder = SyntaxTree {
+        graph: Graph::new("syntax_tree"),
+        parent: 0,
+    };
+    builder.visit_program(program);
+    builder.graph.finish()
+}
+
+struct SyntaxTree {
+    graph: Graph,
+    /// The node the next visited node is attached to
+    parent: usize,
+}
+
+impl SyntaxTree {
+    /// Add a node below the current parent and visit the children below it
+    fn add(&mut self, label: &str, walk: impl FnOnce(&mut Self)) {
+        let id = self.graph.node(label, "");
+        if id != 0 {
+            self.graph.edge(self.parent, id);
+        }
+        let parent = std::mem::replace(&mut self.parent, id);
+        walk(self);
+        self.parent = parent;
+    }
+}
+
+impl Visitor for SyntaxTree {
+    fn visit_program(&mut self, program: &Program) {
+        self.add("program", |builder| walk_program(builder, program));
+    }
+
+    fn visit_function(&mut self, function: &FunctionDefinition) {
+        let parameters: Vec<_> = function
+            .parameters
+            .iter()
+            .map(|parameter| format!("{} {}", parameter.parameter_type.keyword(), parameter.name))
+            .collect();
+        let label = format!(
+            "{} {}({})",
+            function.return_type.keyword(),
+            function.name,
+            parameters.join(", ")
+        );
+        self.add(&label, |builder| walk_function(builder, function));
+    }
+
+    fn visit_statement(&mut self, statement: &Statement) {
+        let label = match &statement.kind {
+            StatementKind::Block(_) => "block".to_string(),
+            StatementKind::If { .. } => "if".to_string(),
+            StatementKind::While { .. } => "while".to_string(),
+            StatementKind::DoWhile { .. } => "do while".to_string(),
+            StatementKind::For { .. } => "for".to_string(),
+            StatementKind::Return(_) => "return".to_string(),
+            StatementKind::Printf(_) => "printf".to_string(),
+            // Drawn by visit_declaration
+            StatementKind::Declaration(_) => return walk_statement(self, statement),
+            StatementKind::Assignment { target, .. } => format!("{} =", target),
+            StatementKind::Call { function, .. } => format!("{}()", function),
+        };
+        self.add(&label, |builder| walk_statement(builder, statement));
+    }
+
+    fn visit_declaration(&mut self, declaration: &Declaration) {
+        let mut label = format!(
+            "{} {}",
+            declaration.variable_type.keyword(),
+            declaration.name
+        );
+        if declaration.value.is_some() {
+            label.push_str(" =");
+        }
+        self.add(&label, |builder| walk_declaration(builder, declaration));
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        let label = match &expression.kind {
+            ExpressionKind::Assign { target, .. } => format!("{} =", target),
+            ExpressionKind::Binary { operator, .. } => operator.symbol().to_string(),
+            ExpressionKind::Negate(_) => "-".to_string(),
+            ExpressionKind::Int(value) => value.to_string(),
+            ExpressionKind::IntOutOfRange(digits) => digits.clone(),
+            ExpressionKind::Float(value) => format!("{:?}", value),
+            ExpressionKind::Bool(value) => value.to_string(),
+            ExpressionKind::String(value) => format!("{:?}", value),
+            ExpressionKind::Call { function, .. } => format!("{}()", function),
+            ExpressionKind::Variable(name) => name.clone(),
+        };
+        self.add(&label, |builder| walk_expression(builder, expression));
+    }
+}
+
+/// Return the call graph. Called functions without definition are drawn dashed.
+pub fn call_graph(graph: &CallGraph) -> String {
+    let mut dot = Graph::new("call_graph");
+    let mut ids: Vec<(&str, usize)> = graph
+        .functions()
+        .map(|function| (function, dot.node(function, "")))
+        .collect();
+    for (caller, caller_id) in ids.clone() {
+        for callee in graph.callees(caller) {
+            let callee_id = match ids.iter().find(|(name, _)| name == callee) {
+                Some((_, id)) => *id,
+                None => {
+                    let id = dot.node(callee, ", style=dashed");
+                    ids.push((callee, id));
+                    id
+                }
+            };
+            dot.edge(caller_id, callee_id);
+        }
+    }
+    dot.finish()
+}
+
+/// A directed graph in the DOT language with numbered nodes
+struct Graph {
+    text: String,
+    nodes: usize,
+}
+
+impl Graph {
+    fn new(name: &str) -> Graph {
+        Graph {
+            text: format!("digraph {} {{\n    ordering=out;\n", name),
+            nodes: 0,
+        }
+    }
+
+    /// Add a node with the label and further attributes, each preceded by a comma
+    fn node(&mut self, label: &str, attributes: &str) -> usize {
+        let id = self.nodes;
+        self.nodes += 1;
+        let _ = writeln!(
+            self.text,
+            "    n{} [label=\"{}\"{}];",
+            id,
+            escape(label),
+            attributes
+        );
+        id
+    }
+
+    fn edge(&mut self, from: usize, to: usize) {
+        let _ = writeln!(self.text, "    n{} -> n{};", from, to);
+    }
+
+    fn finish(mut self) -> String {
+        self.text.push_str("}\n");
+        self.text
+    }
+}
+
+fn escape(label: &str) -> String {
+    let mut escaped = String::new();
+    for c in label.chars() {
+        match c {
+            '"' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::call_graph::CallGraph;
+    use crate::dot;
+    use crate::C1Parser;
+
+    #[test]
+    fn parse_tree_with_syntax_error() {
+        assert_eq!(
+            dot::parse_tree("int f() { x = 1 }"),
+            "digraph parse_tree {
+    ordering=out;
+    n0 [label=\"program\"];
+    n1 [label=\"functiondefinition\"];
+    n2 [label=\"type\"];
+    n3 [label=\"int\", shape=box];
+    n2 -> n3;
+    n1 -> n2;
+    n4 [label=\"f\", shape=box];
+    n1 -> n4;
+    n5 [label=\"(\", shape=box];
+    n1 -> n5;
+    n6 [label=\")\", shape=box];
+    n1 -> n6;
+    n7 [label=\"{\", shape=box];
+    n1 -> n7;
+    n8 [label=\"statementlist\"];
+    n9 [label=\"block\"];
+    n10 [label=\"statement\"];
+    n11 [label=\"statassignment\"];
+    n12 [label=\"x\", shape=box];
+    n11 -> n12;
+    n13 [label=\"=\", shape=box];
+    n11 -> n13;
+    n14 [label=\"assignment\"];
+    n15 [label=\"expr\"];
+    n16 [label=\"simpexpr\"];
+    n17 [label=\"term\"];
+    n18 [label=\"factor\"];
+    n19 [label=\"1\", shape=box];
+    n18 -> n19;
+    n17 -> n18;
+    n16 -> n17;
+    n15 -> n16;
+    n14 -> n15;
+    n11 -> n14;
+    n10 -> n11;
+    n9 -> n10;
+    n8 -> n9;
+    n1 -> n8;
+    n0 -> n1;
+    n20 [label=\"error\", color=red];
+    n21 [label=\"}\", shape=box];
+    n20 -> n21;
+    n0 -> n20;
+}
+"
+        );
+    }
+
+    #[test]
+    fn syntax_tree_and_call_graph() {
+        let program =
+            C1Parser::parse_program("void f() { if (a) g(); printf(-b + 1.0); }").unwrap();
+        let graph = dot::syntax_tree(&program);
+        let lines: Vec<&str> = graph
+            .lines()
+            .skip(2)
+            .filter(|line| !line.contains("->"))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "    n0 [label=\"program\"];",
+                "    n1 [label=\"void f()\"];",
+                "    n2 [label=\"if\"];",
+                "    n3 [label=\"a\"];",
+                "    n4 [label=\"g()\"];",
+                "    n5 [label=\"printf\"];",
+                "    n6 [label=\"+\"];",
+                "    n7 [label=\"-\"];",
+                "    n8 [label=\"b\"];",
+                "    n9 [label=\"1.0\"];",
+                "}",
+            ]
+        );
+        assert!(graph.contains("    n2 -> n3;\n"));
+        assert!(graph.contains("    n7 -> n8;\n"));
+        assert!(graph.contains("    n6 -> n9;\n"));
+
+        let program = C1Parser::parse_program("void a() { b(); c(); }\nvoid b() { a(); }").unwrap();
+        assert_eq!(
+            dot::call_graph(&CallGraph::new(&program)),
+            "digraph call_graph {
+    ordering=out;
+    n0 [label=\"a\"];
+    n1 [label=\"b\"];
+    n0 -> n1;
+    n2 [label=\"c\", style=dashed];
+    n0 -> n2;
+    n1 -> n0;
+}
+"
+        );
+    }
+}
@@ -0,0 +1,409 @@
+use crate::grammar::{Ebnf, Grammar, Terminal, TERMINALS};
+use std::fmt;
+
+/// The nullable, FIRST and FOLLOW sets of the rules of a grammar, which tell a predictive parser
+/// which production to choose by looking at the next token
+/// ```
+/// use cb_3::grammar::{Analysis, Grammar, Terminal};
+/// use cb_3::C1Token;
+///
+/// let grammar = Grammar::c1();
+/// let analysis = Analysis::new(&grammar);
+/// assert!(analysis.nullable("statementlist"));
+/// assert_eq!(
+///     analysis.first("type"),
+///     [C1Token::KwBoolean, C1Token::KwFloat, C1Token::KwInt, C1Token::KwVoid].map(Terminal::Token)
+/// );
+/// assert_eq!(analysis.follow("returnstatement"), [Terminal::Token(C1Token::Semicolon)]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Analysis<'g> {
+    grammar: &'g Grammar,
+    /// The sets of each production, in the order of the productions
+    nullable: Vec<bool>,
+    first: Vec<Vec<Terminal>>,
+    follow: Vec<Vec<Terminal>>,
+}
+
+/// A decision a parser cannot make by looking at one token
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Conflict {
+    /// The rule in which the decision is made
+    pub rule: String,
+    /// The tokens that do not decide
+    pub terminals: Vec<Terminal>,
+    pub kind: ConflictKind,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ConflictKind {
+    /// Both alternatives of a choice may start with the terminals
+    Alternatives(Ebnf, Ebnf),
+    /// The optional or repeated part may start with the terminals, which may also follow it
+    Follow(Ebnf),
+}
+
+impl<'g> Analysis<'g> {
+    pub fn new(grammar: &'g Grammar) -> Analysis<'g> {
+        let count = grammar.productions().len();
+        let mut analysis = Analysis {
+            grammar,
+            nullable: vec![false; count],
+            first: vec![Vec::new(); count],
+            follow: vec![Vec::new(); count],
+        };
+        // Both sets only grow, so iterating to the fixed point terminates
+        loop {
+            let mut changed = false;
+            for (index, production) in grammar.productions().iter().enumerate() {
+                let nullable = analysis.nullable_of(&production.body);
+                let first = analysis.first_of(&production.body);
+                changed |= nullable != analysis.nullable[index];
+                changed |= union(&mut analysis.first[index], &first);
+                analysis.nullable[index] = nullable;
+            }
+            if !changed {
+                break;
+            }
+        }
+        analysis.follow[0].push(Terminal::Eof);
+        loop {
+            let mut additions = Vec::new();
+            for (index, production) in grammar.productions().iter().enumerate() {
+                analysis.walk(
+                    &production.body,
+                    &analysis.follow[index],
+                    &mut |part, follow| {
+                        if let Ebnf::Rule(name) = part {
+                            additions.push((analysis.index(name), follow.to_vec()));
+                        }
+                    },
+                );
+            }
+            let mut changed = false;
+            for (index, follow) in additions {
+                changed |= union(&mut analysis.follow[index], &follow);
+            }
+            if !changed {
+                break;
+            }
+        }
+        analysis
+    }
+
+    /// Check whether the rule derives the empty word
+    pub fn nullable(&self, rule: &str) -> bool {
+        self.nullable[self.index(rule)]
+    }
+
+    /// Return the terminals that start the words of the rule
+    pub fn first(&self, rule: &str) -> &[Terminal] {
+        &self.first[self.index(rule)]
+    }
+
+    /// Return the terminals that may follow the rule in words of the start rule
+    pub fn follow(&self, rule: &str) -> &[Terminal] {
+        &self.follow[self.index(rule)]
+    }
+
+    /// Check whether the body derives the empty word
+    pub fn nullable_of(&self, body: &Ebnf) -> bool {
+        match body {
+            Ebnf::Terminal(_) => false,
+            Ebnf::Rule(name) => self.nullable(name),
+            Ebnf::Sequence(parts) => parts.iter().all(|part| self.nullable_of(part)),
+            Ebnf::Choice(alternatives) => alternatives
+                .iter()
+                .any(|alternative| self.nullable_of(alternative)),
+            Ebnf::Optional(_) | Ebnf::Repeat(_) => true,
+        }
+    }
+
+    /// Return the terminals that start the words of the body
+    pub fn first_of(&self, body: &Ebnf) -> Vec<Terminal> {
+        let mut first = Vec::new();
+        match body {
+            Ebnf::Terminal(terminal) => first.push(*terminal),
+            Ebnf::Rule(name) => first.extend_from_slice(self.first(name)),
+            Ebnf::Sequence(parts) => {
+                for part in parts {
+                    union(&mut first, &self.first_of(part));
+                    if !self.nullable_of(part) {
+                        break;
+                    }
+                }
+            }
+            Ebnf::Choice(alternatives) => {
+                for alternative in alternatives {
+                    union(&mut first, &self.first_of(alternative));
+                }
+            }
+            Ebnf::Optional(body) | Ebnf::Repeat(body) => first = self.first_of(body),
+        }
+        first
+    }
+
+    /// Return the decisions of all rules that one token of lookahead cannot make, in the order
+    /// of the productions
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let mut conflicts = Vec::new();
+        for (index, production) in self.grammar.productions().iter().enumerate() {
+            let mut conflict = |terminals: Vec<Terminal>, kind| {
+                if !terminals.is_empty() {
+                    conflicts.push(Conflict {
+                        rule: production.name.clone(),
+                        terminals,
+                        kind,
+                    });
+                }
+            };
+            self.walk(
+                &production.body,
+                &self.follow[index],
+                &mut |part, follow| {
+                    match part {
+                        Ebnf::Choice(alternatives) => {
+                            // A nullable alternative is also chosen by the terminals following it
+                            let starts: Vec<Vec<Terminal>> = alternatives
+                                .iter()
+                                .map(|alternative| {
+                                    let mut first = self.first_of(alternative);
+                                    if self.nullable_of(alternative) {
+                                        union(&mut first, follow);
+                                    }
+                                    first
+                                })
+                                .collect();
+                            for i in 0..alternatives.len() {
+                                for j in i + 1..alternatives.len() {
+                                    conflict(
+                                        intersection(&starts[i], &starts[j]),
+                                        ConflictKind::Alternatives(
+                                            alternatives[i].clone(),
+                                            alternatives[j].clone(),
+                                        ),
+                                    );
+                                }
+                            }
+                        }
+                        Ebnf::Optional(body) | Ebnf::Repeat(body) => conflict(
+                            intersection(&self.first_of(body), follow),
+                            ConflictKind::Follow(part.clone()),
+                        ),
+                        _ => {}
+                    }
+                },
+            );
+        }
+        conflicts
+    }
+
+    /// Call visit for the body and each of its parts with the terminals that may follow it,
+    /// given the terminals that may follow the body
+    fn walk<'b>(
+        &self,
+        body: &'b Ebnf,
+        follow: &[Terminal],
+        visit: &mut dyn FnMut(&'b Ebnf, &[Terminal]),
+    ) {
+        visit(body, follow);
+        match body {
+            Ebnf::Terminal(_) | Ebnf::Rule(_) => {}
+            Ebnf::Sequence(parts) => {
+                // Going backwards, each part is followed by the start of the parts after it
+                let mut after = follow.to_vec();
+                for part in parts.iter().rev() {
+                    self.walk(part, &after, visit);
+                    let first = self.first_of(part);
+                    if !self.nullable_of(part) {
+                        after.clear();
+                    }
+                    union(&mut after, &first);
+                }
+            }
+            Ebnf::Choice(alternatives) => {
+                for alternative in alternatives {
+                    self.walk(alternative, follow, visit);
+                }
+            }
+            Ebnf::Optional(part) => self.walk(part, follow, visit),
+            Ebnf::Repeat(part) => {
+                let mut after = self.first_of(part);
+                union(&mut after, follow);
+                self.walk(part, &after, visit);
+            }
+        }
+    }
+
+    fn index(&self, rule: &str) -> usize {
+        self.grammar
+            .productions()
+            .iter()
+            .position(|production| production.name == rule)
+            .unwrap_or_else(|| panic!("rule '{}' is not defined", rule))
+    }
+}
+
+/// Add the terminals to the set, keeping it in the order of the terminal table, and return
+/// whether the set grew
+fn union(set: &mut Vec<Terminal>, terminals: &[Terminal]) -> bool {
+    let length = set.len();
+    for terminal in terminals {
+        if !set.contains(terminal) {
+            set.push(*terminal);
+        }
+    }
+    set.sort_by_key(|terminal| order(*terminal));
+    set.len() != length
+}
+
+fn intersection(a: &[Terminal], b: &[Terminal]) -> Vec<Terminal> {
+    a.iter()
+        .copied()
+        .filter(|terminal| b.contains(terminal))
+        .collect()
+}
+
+/// Sort the tokens in the order of the terminal table, and the end of the text last
+fn order(terminal: Terminal) -> usize {
+    match terminal {
+        Terminal::Token(token) => TERMINALS
+            .iter()
+            .position(|(_, known)| *known == token)
+            .unwrap_or(TERMINALS.len()),
+        Terminal::Eof => TERMINALS.len() + 1,
+    }
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            ConflictKind::Alternatives(a, b) => {
+                write!(f, "{}: '{}' and '{}' both start with ", self.rule, a, b)?
+            }
+            ConflictKind::Follow(part) => write!(
+                f,
+                "{}: '{}' may start with and be followed by ",
+                self.rule, part
+            )?,
+        }
+        write_terminals(f, &self.terminals)
+    }
+}
+
+/// Print the tables, one line per rule, followed by the conflicts
+impl fmt::Display for Analysis<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let productions = self.grammar.productions();
+        let width = productions
+            .iter()
+            .map(|production| production.name.len())
+            .max()
+            .unwrap_or(0);
+        for (title, sets) in [("FIRST", &self.first), ("FOLLOW", &self.follow)] {
+            writeln!(f, "{}", title)?;
+            for (index, production) in productions.iter().enumerate() {
+                write!(f, "    {:width$}  ", production.name, width = width)?;
+                write_terminals(f, &sets[index])?;
+                if title == "FIRST" && self.nullable[index] {
+                    f.write_str(" (nullable)")?;
+                }
+                writeln!(f)?;
+            }
+        }
+        writeln!(f, "LL(1) conflicts")?;
+        let conflicts = self.conflicts();
+        if conflicts.is_empty() {
+            writeln!(f, "    none")?;
+        }
+        for conflict in conflicts {
+            writeln!(f, "    {}", conflict)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_terminals(f: &mut fmt::Formatter<'_>, terminals: &[Terminal]) -> fmt::Result {
+    for (index, terminal) in terminals.iter().enumerate() {
+        if index > 0 {
+            f.write_str(" ")?;
+        }
+        write!(f, "{}", terminal)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grammar::{Analysis, Conflict, ConflictKind, Ebnf, Grammar, Terminal};
+    use crate::C1Token::*;
+
+    #[test]
+    fn c1_conflicts() {
+        let grammar = Grammar::c1();
+        let analysis = Analysis::new(&grammar);
+        let conflicts: Vec<String> = analysis
+            .conflicts()
+            .iter()
+            .map(Conflict::to_string)
+            .collect();
+        assert_eq!(
+            conflicts,
+            [
+                "statement: 'statassignment \";\"' and 'functioncall \";\"' both start with <ID>",
+                "assignment: '<ID> \"=\" assignment' and 'expr' both start with <ID>",
+                "factor: 'functioncall' and '<ID>' both start with <ID>",
+            ]
+        );
+        assert_eq!(
+            analysis.first("statement"),
+            [Identifier, KwIf, KwPrintf, KwReturn].map(Terminal::Token)
+        );
+        assert_eq!(
+            analysis.follow("simpexpr"),
+            [
+                Equal,
+                NotEqual,
+                Less,
+                Greater,
+                LessEqual,
+                GreaterEqual,
+                Semicolon,
+                RightParenthesis
+            ]
+            .map(Terminal::Token)
+        );
+        assert_eq!(analysis.follow("program"), [Terminal::Eof]);
+        assert!(!analysis.nullable("program"));
+        assert!(analysis.nullable_of(&Ebnf::Rule("statementlist".to_string())));
+    }
+
+    #[test]
+    fn follow_conflicts() {
+        let grammar = Grammar::parse("list ::= item* <ID> item ::= <ID> | ( \";\" )?").unwrap();
+        let analysis = Analysis::new(&grammar);
+        assert!(analysis.nullable("item"));
+        assert_eq!(
+            analysis.first("item"),
+            [Terminal::Token(Identifier), Terminal::Token(Semicolon)]
+        );
+        assert_eq!(
+            analysis.follow("item"),
+            [Terminal::Token(Identifier), Terminal::Token(Semicolon)]
+        );
+        let conflicts = analysis.conflicts();
+        assert_eq!(
+            conflicts[0],
+            Conflict {
+                rule: "list".to_string(),
+                terminals: vec![Terminal::Token(Identifier)],
+                kind: ConflictKind::Follow(Ebnf::Repeat(Box::new(Ebnf::Rule("item".to_string())))),
+            }
+        );
+        assert_eq!(
+            conflicts[1].to_string(),
+            "item: '<ID>' and '( \";\" )?' both start with <ID>"
+        );
+        assert_eq!(conflicts.len(), 3);
+    }
+}
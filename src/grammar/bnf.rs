@@ -0,0 +1,224 @@
+use crate::grammar::{Ebnf, Grammar, Terminal};
+use crate::lexer::{C1Lexer, C1Token};
+use crate::ParseError;
+use std::collections::HashSet;
+use std::fmt;
+
+/// A terminal or a nonterminal of the grammar with the EBNF operators expanded
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub(super) enum Symbol {
+    Terminal(Terminal),
+    Nonterminal(usize),
+}
+
+/// A rule of the grammar, or a choice, optional or repeated part inside a rule
+#[derive(Debug, Clone)]
+pub(super) struct Nonterminal {
+    /// The name of the rule, or for a part the name of its rule and a number, e.g. `simpexpr#1`
+    pub(super) name: String,
+    /// The production the nonterminal belongs to
+    pub(super) rule: String,
+    /// The EBNF it was made of, to report conflicts
+    pub(super) origin: Ebnf,
+    pub(super) alternatives: Vec<Vec<Symbol>>,
+}
+
+/// The grammar in BNF, which the table-driven parsers work on
+#[derive(Debug, Clone)]
+pub(super) struct Bnf {
+    /// The productions of the grammar first, in their order, then the parts inside them
+    pub(super) nonterminals: Vec<Nonterminal>,
+}
+
+/// The first two terminals of a word, fewer if the word is shorter
+pub(super) type Prefix = Vec<Terminal>;
+
+impl Bnf {
+    pub(super) fn new(grammar: &Grammar) -> Bnf {
+        let mut compiler = Compiler {
+            grammar,
+            nonterminals: Vec::new(),
+        };
+        for production in grammar.productions() {
+            compiler.nonterminals.push(Nonterminal {
+                name: production.name.clone(),
+                rule: production.name.clone(),
+                origin: production.body.clone(),
+                alternatives: Vec::new(),
+            });
+        }
+        for (index, production) in grammar.productions().iter().enumerate() {
+            compiler.nonterminals[index].alternatives = match &production.body {
+                Ebnf::Choice(alternatives) => alternatives
+                    .iter()
+                    .map(|alternative| compiler.sequence(&production.name, alternative))
+                    .collect(),
+                body => vec![compiler.sequence(&production.name, body)],
+            };
+        }
+        Bnf {
+            nonterminals: compiler.nonterminals,
+        }
+    }
+
+    /// Return the prefixes of length two of the words of each nonterminal
+    pub(super) fn first(&self) -> Vec<HashSet<Prefix>> {
+        let mut first = vec![HashSet::new(); self.nonterminals.len()];
+        loop {
+            let mut changed = false;
+            for (index, nonterminal) in self.nonterminals.iter().enumerate() {
+                for symbols in &nonterminal.alternatives {
+                    for prefix in first_of(symbols, &first) {
+                        changed |= first[index].insert(prefix);
+                    }
+                }
+            }
+            if !changed {
+                return first;
+            }
+        }
+    }
+
+    /// Return something that prints the symbol
+    pub(super) fn display(&self, symbol: Symbol) -> impl fmt::Display + '_ {
+        SymbolName(self, symbol)
+    }
+}
+
+struct SymbolName<'b>(&'b Bnf, Symbol);
+
+impl fmt::Display for SymbolName<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.1 {
+            Symbol::Terminal(terminal) => write!(f, "{}", terminal),
+            Symbol::Nonterminal(index) => f.write_str(&self.0.nonterminals[index].name),
+        }
+    }
+}
+
+/// Turns the bodies of the productions into sequences of symbols
+struct Compiler<'g> {
+    grammar: &'g Grammar,
+    nonterminals: Vec<Nonterminal>,
+}
+
+impl Compiler<'_> {
+    fn sequence(&mut self, rule: &str, body: &Ebnf) -> Vec<Symbol> {
+        match body {
+            Ebnf::Sequence(parts) => parts
+                .iter()
+                .flat_map(|part| self.sequence(rule, part))
+                .collect(),
+            Ebnf::Terminal(terminal) => vec![Symbol::Terminal(*terminal)],
+            Ebnf::Rule(name) => {
+                let index = self
+                    .grammar
+                    .productions()
+                    .iter()
+                    .position(|production| production.name == *name)
+                    .unwrap();
+                vec![Symbol::Nonterminal(index)]
+            }
+            Ebnf::Choice(alternatives) => {
+                let index = self.add(rule, body);
+                self.nonterminals[index].alternatives = alternatives
+                    .iter()
+                    .map(|alternative| self.sequence(rule, alternative))
+                    .collect();
+                vec![Symbol::Nonterminal(index)]
+            }
+            // `( x )?` becomes `n ::= x | ε`
+            Ebnf::Optional(part) => {
+                let index = self.add(rule, body);
+                let symbols = self.sequence(rule, part);
+                self.nonterminals[index].alternatives = vec![symbols, Vec::new()];
+                vec![Symbol::Nonterminal(index)]
+            }
+            // `( x )*` becomes `n ::= x n | ε`
+            Ebnf::Repeat(part) => {
+                let index = self.add(rule, body);
+                let mut symbols = self.sequence(rule, part);
+                symbols.push(Symbol::Nonterminal(index));
+                self.nonterminals[index].alternatives = vec![symbols, Vec::new()];
+                vec![Symbol::Nonterminal(index)]
+            }
+        }
+    }
+
+    /// Add a nonterminal for a part of a rule, its alternatives are filled in later
+    fn add(&mut self, rule: &str, origin: &Ebnf) -> usize {
+        let number = self
+            .nonterminals
+            .iter()
+            .filter(|nonterminal| nonterminal.rule == rule && nonterminal.name != rule)
+            .count();
+        self.nonterminals.push(Nonterminal {
+            name: format!("{}#{}", rule, number),
+            rule: rule.to_string(),
+            origin: origin.clone(),
+            alternatives: Vec::new(),
+        });
+        self.nonterminals.len() - 1
+    }
+}
+
+/// Return the prefixes of the words of the symbols
+pub(super) fn first_of(symbols: &[Symbol], first: &[HashSet<Prefix>]) -> HashSet<Prefix> {
+    let mut prefixes = HashSet::from([Vec::new()]);
+    for symbol in symbols {
+        if prefixes.iter().all(|prefix| prefix.len() == 2) {
+            break;
+        }
+        prefixes = match symbol {
+            Symbol::Terminal(terminal) => concat(&prefixes, &HashSet::from([vec![*terminal]])),
+            Symbol::Nonterminal(index) => concat(&prefixes, &first[*index]),
+        };
+    }
+    prefixes
+}
+
+/// Return the prefixes of the concatenations of the words
+pub(super) fn concat(a: &HashSet<Prefix>, b: &HashSet<Prefix>) -> HashSet<Prefix> {
+    let mut prefixes = HashSet::new();
+    for x in a {
+        if x.len() == 2 {
+            prefixes.insert(x.clone());
+            continue;
+        }
+        for y in b {
+            let mut prefix = x.clone();
+            prefix.extend(y);
+            prefix.truncate(2);
+            prefixes.insert(prefix);
+        }
+    }
+    prefixes
+}
+
+pub(super) fn terminal(token: Option<C1Token>) -> Terminal {
+    token.map_or(Terminal::Eof, Terminal::Token)
+}
+
+/// Return an error at the current token, or at the next one if that did not fit
+pub(super) fn unexpected(lexer: &C1Lexer, next: bool) -> ParseError {
+    let end = lexer.source().len();
+    let (line, text, span) = if next {
+        (
+            lexer.peek_line_number(),
+            lexer.peek_text(),
+            lexer.peek_span(),
+        )
+    } else {
+        (
+            lexer.current_line_number(),
+            lexer.current_text(),
+            lexer.current_span(),
+        )
+    };
+    ParseError {
+        reason: "unexpected token".to_string(),
+        line,
+        text: text.map(str::to_string),
+        span: span.unwrap_or(end..end),
+    }
+}
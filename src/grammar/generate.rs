@@ -0,0 +1,302 @@
+use crate::grammar::{Ebnf, Grammar, Terminal};
+use crate::lexer::C1Token;
+
+/// Identifiers to choose from, none of them a keyword
+const IDENTIFIERS: &[&str] = &["a", "b", "x", "y1", "blub", "main", "f", "g2", "value"];
+
+/// Tokens that never occur in programs of C(-1), nor of any grammar that does not use them
+const FOREIGN: &[C1Token] = &[
+    C1Token::KwWhile,
+    C1Token::KwElse,
+    C1Token::KwDo,
+    C1Token::KwFor,
+    C1Token::Comma,
+    C1Token::ConstString,
+];
+
+#[derive(Debug, Copy, Clone)]
+pub struct GeneratorOptions {
+    /// How many rules may be nested at most. Deeper derivations are cut short by choosing the
+    /// shortest alternatives, so the depth may be exceeded if the grammar has no shorter way.
+    pub max_depth: usize,
+    /// How often a repetition is expanded at most
+    pub max_repeat: usize,
+    /// Start value of the random number generator, equal seeds generate equal programs
+    pub seed: u64,
+}
+
+impl Default for GeneratorOptions {
+    fn default() -> Self {
+        GeneratorOptions {
+            max_depth: 12,
+            max_repeat: 3,
+            seed: 1,
+        }
+    }
+}
+
+/// Generates random programs from a grammar, e.g. to test a parser with inputs nobody wrote
+/// by hand
+/// ```
+/// use cb_3::grammar::{Generator, GeneratorOptions, Grammar};
+/// use cb_3::C1Parser;
+///
+/// let grammar = Grammar::c1();
+/// let mut generator = Generator::new(&grammar, GeneratorOptions::default());
+/// for _ in 0..10 {
+///     assert!(C1Parser::parse(&generator.program()).is_ok());
+///     assert!(C1Parser::parse(&generator.invalid_program()).is_err());
+/// }
+/// ```
+pub struct Generator<'g> {
+    grammar: &'g Grammar,
+    options: GeneratorOptions,
+    /// Minimal derivation depth of each production
+    heights: Vec<usize>,
+    random: u64,
+}
+
+impl<'g> Generator<'g> {
+    pub fn new(grammar: &'g Grammar, options: GeneratorOptions) -> Generator<'g> {
+        let mut generator = Generator {
+            grammar,
+            options,
+            heights: vec![usize::MAX; grammar.productions().len()],
+            // Xorshift never leaves zero
+            random: options.seed.max(1),
+        };
+        // Iterate to the fixed point, each round finds the heights of at least one more rule
+        loop {
+            let heights: Vec<usize> = grammar
+                .productions()
+                .iter()
+                .map(|production| generator.height(&production.body).saturating_add(1))
+                .collect();
+            if heights == generator.heights {
+                break;
+            }
+            generator.heights = heights;
+        }
+        generator
+    }
+
+    /// Generate the text of a random word of the grammar
+    pub fn program(&mut self) -> String {
+        let tokens = self.tokens();
+        self.render(&tokens)
+    }
+
+    /// Generate the text of a random program the grammar does not derive: a word of the grammar
+    /// in which a parenthesis or brace was removed or added, or a token was replaced by a token
+    /// the grammar never uses. The first relies on every production deriving balanced
+    /// parentheses and braces, as those of C(-1) do.
+    pub fn invalid_program(&mut self) -> String {
+        let mut tokens = self.tokens();
+        let terminals = self.grammar.terminals();
+        let foreign: Vec<C1Token> = FOREIGN
+            .iter()
+            .copied()
+            .filter(|token| !terminals.contains(&Terminal::Token(*token)))
+            .collect();
+        let brackets: Vec<usize> = (0..tokens.len())
+            .filter(|&index| is_bracket(tokens[index]))
+            .collect();
+        match self.below(3) {
+            0 if !brackets.is_empty() => {
+                let index = brackets[self.below(brackets.len())];
+                tokens.remove(index);
+            }
+            1 if !foreign.is_empty() && !tokens.is_empty() => {
+                let index = self.below(tokens.len());
+                tokens[index] = foreign[self.below(foreign.len())];
+            }
+            _ => {
+                let index = self.below(tokens.len() + 1);
+                let bracket = [
+                    C1Token::LeftParenthesis,
+                    C1Token::RightParenthesis,
+                    C1Token::LeftBrace,
+                    C1Token::RightBrace,
+                ][self.below(4)];
+                tokens.insert(index, bracket);
+            }
+        }
+        self.render(&tokens)
+    }
+
+    /// Derive a random word of the start rule
+    fn tokens(&mut self) -> Vec<C1Token> {
+        let mut tokens = Vec::new();
+        let start = Ebnf::Rule(self.grammar.start().to_string());
+        self.derive(&start, self.options.max_depth, &mut tokens);
+        tokens
+    }
+
+    /// Return the minimal number of nested rules needed to derive a word of the body
+    fn height(&self, body: &Ebnf) -> usize {
+        match body {
+            Ebnf::Terminal(_) | Ebnf::Optional(_) | Ebnf::Repeat(_) => 0,
+            Ebnf::Rule(name) => self.heights[self.index(name)],
+            Ebnf::Sequence(parts) => parts
+                .iter()
+                .map(|part| self.height(part))
+                .max()
+                .unwrap_or(0),
+            Ebnf::Choice(alternatives) => alternatives
+                .iter()
+                .map(|alternative| self.height(alternative))
+                .min()
+                .unwrap_or(usize::MAX),
+        }
+    }
+
+    fn index(&self, name: &str) -> usize {
+        self.grammar
+            .productions()
+            .iter()
+            .position(|production| production.name == name)
+            .unwrap()
+    }
+
+    /// Append a random word of the body to the tokens, using at most `depth` nested rules if
+    /// possible
+    fn derive(&mut self, body: &Ebnf, depth: usize, tokens: &mut Vec<C1Token>) {
+        match body {
+            Ebnf::Terminal(Terminal::Token(token)) => tokens.push(*token),
+            Ebnf::Terminal(Terminal::Eof) => {}
+            Ebnf::Rule(name) => {
+                let body = &self.grammar.productions()[self.index(name)].body;
+                self.derive(body, depth.saturating_sub(1), tokens);
+            }
+            Ebnf::Sequence(parts) => {
+                for part in parts {
+                    self.derive(part, depth, tokens);
+                }
+            }
+            Ebnf::Choice(alternatives) => {
+                let fitting: Vec<&Ebnf> = alternatives
+                    .iter()
+                    .filter(|alternative| self.height(alternative) <= depth)
+                    .collect();
+                let alternative = if fitting.is_empty() {
+                    alternatives
+                        .iter()
+                        .min_by_key(|alternative| self.height(alternative))
+                        .unwrap()
+                } else {
+                    fitting[self.below(fitting.len())]
+                };
+                self.derive(alternative, depth, tokens);
+            }
+            Ebnf::Optional(body) => {
+                if self.height(body) <= depth && self.below(2) == 0 {
+                    self.derive(body, depth, tokens);
+                }
+            }
+            Ebnf::Repeat(body) => {
+                if self.height(body) <= depth {
+                    for _ in 0..self.below(self.options.max_repeat + 1) {
+                        self.derive(body, depth, tokens);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Write the tokens with random texts for identifiers and constants
+    fn render(&mut self, tokens: &[C1Token]) -> String {
+        let mut text = String::new();
+        for &token in tokens {
+            let piece = match token {
+                C1Token::Identifier => IDENTIFIERS[self.below(IDENTIFIERS.len())].to_string(),
+                C1Token::ConstInt => self.below(1000).to_string(),
+                C1Token::ConstFloat => format!("{}.{}", self.below(100), self.below(100)),
+                C1Token::ConstBoolean => ["true", "false"][self.below(2)].to_string(),
+                C1Token::ConstString => "\"text\"".to_string(),
+                C1Token::KwBoolean => "bool".to_string(),
+                C1Token::KwDo => "do".to_string(),
+                C1Token::KwElse => "else".to_string(),
+                C1Token::KwFloat => "float".to_string(),
+                C1Token::KwFor => "for".to_string(),
+                C1Token::KwIf => "if".to_string(),
+                C1Token::KwInt => "int".to_string(),
+                C1Token::KwPrintf => "printf".to_string(),
+                C1Token::KwReturn => "return".to_string(),
+                C1Token::KwVoid => "void".to_string(),
+                C1Token::KwWhile => "while".to_string(),
+                // The spelling of the other terminals is their text in quotes
+                _ => Terminal::Token(token)
+                    .spelling()
+                    .trim_matches('"')
+                    .to_string(),
+            };
+            if !text.is_empty() {
+                text.push(if self.below(8) == 0 { '\n' } else { ' ' });
+            }
+            text.push_str(&piece);
+        }
+        text
+    }
+
+    /// Return a pseudo-random number below the bound
+    fn below(&mut self, bound: usize) -> usize {
+        self.random ^= self.random << 13;
+        self.random ^= self.random >> 7;
+        self.random ^= self.random << 17;
+        (self.random % bound as u64) as usize
+    }
+}
+
+fn is_bracket(token: C1Token) -> bool {
+    matches!(
+        token,
+        C1Token::LeftParenthesis
+            | C1Token::RightParenthesis
+            | C1Token::LeftBrace
+            | C1Token::RightBrace
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grammar::{Generator, GeneratorOptions, Grammar};
+
+    #[test]
+    fn equal_seeds_generate_equal_programs() {
+        let grammar = Grammar::c1();
+        let options = GeneratorOptions {
+            seed: 42,
+            ..GeneratorOptions::default()
+        };
+        let programs = |options| {
+            let mut generator = Generator::new(&grammar, options);
+            (0..5).map(|_| generator.program()).collect::<Vec<_>>()
+        };
+        assert_eq!(programs(options), programs(options));
+        assert_ne!(
+            programs(options),
+            programs(GeneratorOptions {
+                seed: 43,
+                ..options
+            })
+        );
+    }
+
+    #[test]
+    fn depth_and_repetitions_are_limited() {
+        let grammar = Grammar::parse("list ::= ( \"(\" list \")\" )* <ID>").unwrap();
+        let mut generator = Generator::new(
+            &grammar,
+            GeneratorOptions {
+                max_depth: 3,
+                max_repeat: 2,
+                seed: 7,
+            },
+        );
+        for _ in 0..100 {
+            let program = generator.program();
+            assert!(program.matches('(').count() <= 2 + 2 * 2, "{}", program);
+            assert!(!program.contains("( ( ("), "{}", program);
+        }
+    }
+}
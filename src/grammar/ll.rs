@@ -0,0 +1,220 @@
+use crate::grammar::bnf::{
+    concat, first_of, terminal, unexpected, Bnf, Nonterminal, Prefix, Symbol,
+};
+use crate::grammar::{Conflict, ConflictKind, Ebnf, Grammar, Terminal};
+use crate::lexer::C1Lexer;
+use crate::ParseResult;
+use std::collections::{HashMap, HashSet};
+
+/// Which alternative to expand for the current token
+#[derive(Debug, Clone)]
+enum Entry {
+    Alternative(usize),
+    /// The next token decides
+    Next(HashMap<Terminal, usize>),
+}
+
+/// A predictive parser driven by a parse table computed from a grammar. It keeps the rules to
+/// be parsed on an explicit stack, so unlike [`C1Parser`](crate::C1Parser) it needs no limit on
+/// the nesting. Where one token does not decide between alternatives, the next one is looked
+/// at as well.
+/// ```
+/// use cb_3::grammar::{Grammar, LlParser};
+/// use cb_3::C1Parser;
+///
+/// let parser = LlParser::new(&Grammar::c1()).unwrap();
+/// for text in ["void f() { x = g(); }", "void f() { x = g; }", "void f() { x; }"] {
+///     assert_eq!(parser.parse(text), C1Parser::parse(text));
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct LlParser {
+    bnf: Bnf,
+    /// For each nonterminal, the entry for each terminal that may start it
+    table: Vec<HashMap<Terminal, Entry>>,
+}
+
+impl LlParser {
+    /// Compute the parse table of the grammar. Fails with the decisions that two tokens of
+    /// lookahead cannot make.
+    pub fn new(grammar: &Grammar) -> Result<LlParser, Vec<Conflict>> {
+        let mut parser = LlParser {
+            bnf: Bnf::new(grammar),
+            table: Vec::new(),
+        };
+        let conflicts = parser.fill_table();
+        if conflicts.is_empty() {
+            Ok(parser)
+        } else {
+            Err(conflicts)
+        }
+    }
+
+    /// Check the syntax of the text
+    pub fn parse(&self, text: &str) -> ParseResult {
+        let mut lexer = C1Lexer::new(text);
+        let mut stack = vec![Symbol::Nonterminal(0)];
+        while let Some(symbol) = stack.pop() {
+            let current = terminal(lexer.current_token());
+            match symbol {
+                Symbol::Terminal(expected) if expected == current => {
+                    if current != Terminal::Eof {
+                        lexer.eat();
+                    }
+                }
+                Symbol::Terminal(_) => return Err(unexpected(&lexer, false)),
+                Symbol::Nonterminal(nonterminal) => {
+                    let alternative = match self.table[nonterminal].get(&current) {
+                        Some(Entry::Alternative(alternative)) => *alternative,
+                        Some(Entry::Next(next)) => match next.get(&terminal(lexer.peek_token())) {
+                            Some(alternative) => *alternative,
+                            None => return Err(unexpected(&lexer, true)),
+                        },
+                        None => return Err(unexpected(&lexer, false)),
+                    };
+                    let symbols = &self.bnf.nonterminals[nonterminal].alternatives[alternative];
+                    stack.extend(symbols.iter().rev());
+                }
+            }
+        }
+        if lexer.current_token().is_some() {
+            return Err(unexpected(&lexer, false));
+        }
+        Ok(())
+    }
+
+    /// Compute the entries of all nonterminals and return the conflicts
+    fn fill_table(&mut self) -> Vec<Conflict> {
+        let first = self.bnf.first();
+        let follow = self.follow(&first);
+        let mut conflicts = Vec::new();
+        for (index, nonterminal) in self.bnf.nonterminals.iter().enumerate() {
+            // The prefixes the words of each alternative may start with, followed by anything
+            // that may follow the nonterminal, split into the first terminal and the second.
+            // Only after the end of the text there is no second.
+            let mut starts: HashMap<Terminal, Vec<(Option<Terminal>, usize)>> = HashMap::new();
+            for (alternative, symbols) in nonterminal.alternatives.iter().enumerate() {
+                for prefix in concat(&first_of(symbols, &first), &follow[index]) {
+                    starts
+                        .entry(prefix[0])
+                        .or_default()
+                        .push((prefix.get(1).copied(), alternative));
+                }
+            }
+            let mut starts: Vec<_> = starts.into_iter().collect();
+            starts.sort_by_key(|(terminal, _)| terminal.spelling());
+            let mut entries = HashMap::new();
+            for (terminal, mut options) in starts {
+                options.sort_by_key(|&(_, alternative)| alternative);
+                options.dedup();
+                let lowest = options[0].1;
+                let highest = options[options.len() - 1].1;
+                if lowest == highest {
+                    entries.insert(terminal, Entry::Alternative(lowest));
+                    continue;
+                }
+                let mut next = HashMap::new();
+                let mut conflict = None;
+                for &(second, alternative) in &options {
+                    match second.and_then(|second| next.insert(second, alternative)) {
+                        Some(other) if other != alternative => {
+                            conflict = Some((other, alternative))
+                        }
+                        Some(_) => {}
+                        None if second.is_none() => conflict = Some((lowest, highest)),
+                        None => {}
+                    }
+                }
+                match conflict {
+                    Some((a, b)) => conflicts.push(nonterminal.conflict(terminal, a, b)),
+                    None => {
+                        entries.insert(terminal, Entry::Next(next));
+                    }
+                }
+            }
+            self.table.push(entries);
+        }
+        conflicts
+    }
+
+    /// Return the prefixes of length two of what may follow each nonterminal. Only the end of
+    /// the text is not followed by anything.
+    fn follow(&self, first: &[HashSet<Prefix>]) -> Vec<HashSet<Prefix>> {
+        let mut follow = vec![HashSet::new(); self.bnf.nonterminals.len()];
+        follow[0].insert(vec![Terminal::Eof]);
+        loop {
+            let mut changed = false;
+            for (index, nonterminal) in self.bnf.nonterminals.iter().enumerate() {
+                for symbols in &nonterminal.alternatives {
+                    for (position, symbol) in symbols.iter().enumerate() {
+                        let Symbol::Nonterminal(inner) = *symbol else {
+                            continue;
+                        };
+                        let rest = first_of(&symbols[position + 1..], first);
+                        for prefix in concat(&rest, &follow[index]) {
+                            changed |= follow[inner].insert(prefix);
+                        }
+                    }
+                }
+            }
+            if !changed {
+                return follow;
+            }
+        }
+    }
+}
+
+impl Nonterminal {
+    fn conflict(&self, terminal: Terminal, a: usize, b: usize) -> Conflict {
+        let kind = match &self.origin {
+            Ebnf::Choice(alternatives) => {
+                ConflictKind::Alternatives(alternatives[a].clone(), alternatives[b].clone())
+            }
+            origin => ConflictKind::Follow(origin.clone()),
+        };
+        Conflict {
+            rule: self.rule.clone(),
+            terminals: vec![terminal],
+            kind,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grammar::{ConflictKind, Ebnf, Grammar, LlParser, Terminal};
+    use crate::C1Token;
+
+    #[test]
+    fn two_tokens_decide() {
+        let parser = LlParser::new(&Grammar::c1()).unwrap();
+        assert_eq!(parser.parse("void f() { g(); x = (y); }"), Ok(()));
+        let error = parser.parse("void f() {\n  x y;\n}").unwrap_err();
+        assert_eq!((error.line, error.text.as_deref()), (Some(2), Some("y")));
+        let error = parser.parse("void f() {}}").unwrap_err();
+        assert_eq!(error.span, 11..12);
+        let error = parser.parse("void f() {").unwrap_err();
+        assert_eq!((error.line, error.text), (None, None));
+    }
+
+    #[test]
+    fn conflicts() {
+        let grammar = Grammar::parse("list ::= ( <ID> \"(\" )? <ID> \"(\" \")\"").unwrap();
+        let conflicts = LlParser::new(&grammar).unwrap_err();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(
+            conflicts[0].terminals,
+            [Terminal::Token(C1Token::Identifier)]
+        );
+        assert!(matches!(
+            conflicts[0].kind,
+            ConflictKind::Follow(Ebnf::Optional(_))
+        ));
+
+        // Decided by the second token
+        let grammar = Grammar::parse("list ::= ( <ID> \",\" )* <ID> \";\"").unwrap();
+        let parser = LlParser::new(&grammar).unwrap();
+        assert_eq!(parser.parse("a, b, c;"), Ok(()));
+        assert!(parser.parse("a, b,;").is_err());
+    }
+}
@@ -0,0 +1,562 @@
+use crate::grammar::bnf::{first_of, terminal, unexpected, Bnf, Prefix, Symbol};
+use crate::grammar::{Ebnf, Grammar, Terminal};
+use crate::lexer::C1Lexer;
+use crate::ParseResult;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// A production with the position of the parser in it: the index of the production and the
+/// number of symbols before the dot
+type Item = (usize, usize);
+
+/// The LR(0) item sets of a grammar with the LALR(1) lookaheads of their items, from which a
+/// bottom-up parser is built. The EBNF operators are expanded into BNF first: a choice or a
+/// repetition inside a rule becomes an extra nonterminal, named after the rule, e.g.
+/// `simpexpr#1` for the repeated part of `simpexpr`. A production with an optional part becomes
+/// two productions, one with and one without the part, so that the parser needs not decide
+/// whether the part is empty before seeing what follows it.
+/// ```
+/// use cb_3::grammar::{Automaton, Grammar};
+///
+/// let automaton = Automaton::new(&Grammar::parse("list ::= <ID> ( \",\" <ID> )*").unwrap());
+/// assert_eq!(automaton.states(), 7);
+/// assert!(automaton.conflicts().is_empty());
+/// assert!(automaton.to_string().starts_with(
+///     "state 0
+///     list' ::= . list  [<EOF>]
+///     list ::= . <ID> list#0  [<EOF>]
+///     on list go to 1
+///     on <ID> go to 2
+/// "
+/// ));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Automaton {
+    bnf: Bnf,
+    /// The productions as nonterminal and right-hand side. The first production, `start' ::=
+    /// start`, is added to accept after the start rule.
+    productions: Vec<(usize, Vec<Symbol>)>,
+    states: Vec<State>,
+}
+
+#[derive(Debug, Clone)]
+struct State {
+    /// The items the state is reached with, sorted
+    kernel: Vec<Item>,
+    /// The kernel and the items it predicts, with their lookaheads
+    items: Vec<(Item, Vec<Terminal>)>,
+    /// The state reached by shifting or going to each symbol
+    transitions: Vec<(Symbol, usize)>,
+}
+
+/// What the parser does for the current token
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum Action {
+    Shift(usize),
+    /// Reduce by the production
+    Reduce(usize),
+    Accept,
+}
+
+struct Tables {
+    actions: Vec<HashMap<Terminal, Action>>,
+    /// The state to go to after reducing to a nonterminal
+    gotos: Vec<HashMap<usize, usize>>,
+    conflicts: Vec<LrConflict>,
+}
+
+/// A state in which the next token does not decide what to do
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LrConflict {
+    pub state: usize,
+    pub terminal: Terminal,
+    pub kind: LrConflictKind,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum LrConflictKind {
+    /// The token may be shifted or the production reduced
+    ShiftReduce(String),
+    /// Either production may be reduced
+    ReduceReduce(String, String),
+}
+
+impl Automaton {
+    pub fn new(grammar: &Grammar) -> Automaton {
+        let bnf = Bnf::new(grammar);
+        let start = bnf.nonterminals.len();
+        let mut productions = vec![(start, vec![Symbol::Nonterminal(0)])];
+        for (index, nonterminal) in bnf.nonterminals.iter().enumerate() {
+            for symbols in &nonterminal.alternatives {
+                for symbols in expand_optionals(&bnf, symbols) {
+                    productions.push((index, symbols));
+                }
+            }
+        }
+        let mut automaton = Automaton {
+            bnf,
+            productions,
+            states: Vec::new(),
+        };
+        automaton.item_sets();
+        automaton.lookaheads();
+        automaton
+    }
+
+    /// Return the number of states
+    pub fn states(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Return the shift/reduce and reduce/reduce conflicts, in the order of the states
+    pub fn conflicts(&self) -> Vec<LrConflict> {
+        self.table().conflicts
+    }
+
+    /// Build the LR(0) item sets, each state with the items reached by the same symbols
+    fn item_sets(&mut self) {
+        self.states.push(State {
+            kernel: vec![(0, 0)],
+            items: Vec::new(),
+            transitions: Vec::new(),
+        });
+        let mut index = 0;
+        while index < self.states.len() {
+            let items = self.closure(&self.states[index].kernel);
+            let mut transitions = Vec::new();
+            for &item in &items {
+                let Some(symbol) = self.next(item) else {
+                    continue;
+                };
+                if transitions.iter().any(|&(known, _)| known == symbol) {
+                    continue;
+                }
+                let mut kernel: Vec<Item> = items
+                    .iter()
+                    .filter(|&&item| self.next(item) == Some(symbol))
+                    .map(|&(production, dot)| (production, dot + 1))
+                    .collect();
+                kernel.sort();
+                let target = match self.states.iter().position(|state| state.kernel == kernel) {
+                    Some(target) => target,
+                    None => {
+                        self.states.push(State {
+                            kernel,
+                            items: Vec::new(),
+                            transitions: Vec::new(),
+                        });
+                        self.states.len() - 1
+                    }
+                };
+                transitions.push((symbol, target));
+            }
+            let state = &mut self.states[index];
+            state.items = items.into_iter().map(|item| (item, Vec::new())).collect();
+            state.transitions = transitions;
+            index += 1;
+        }
+    }
+
+    /// Return the kernel and the items predicted by it, in the order of prediction
+    fn closure(&self, kernel: &[Item]) -> Vec<Item> {
+        let mut items = kernel.to_vec();
+        let mut index = 0;
+        while index < items.len() {
+            if let Some(Symbol::Nonterminal(nonterminal)) = self.next(items[index]) {
+                for (production, _) in self.productions_of(nonterminal) {
+                    if !items.contains(&(production, 0)) {
+                        items.push((production, 0));
+                    }
+                }
+            }
+            index += 1;
+        }
+        items
+    }
+
+    /// Propagate the lookaheads through the item sets until nothing changes. States with the
+    /// same items but different lookaheads are not told apart, which makes them LALR(1).
+    fn lookaheads(&mut self) {
+        let first = self.bnf.first();
+        self.states[0].items[0].1.push(Terminal::Eof);
+        loop {
+            let mut changed = false;
+            for index in 0..self.states.len() {
+                changed |= self.predict(index, &first);
+                for item_index in 0..self.states[index].items.len() {
+                    let (item, lookaheads) = self.states[index].items[item_index].clone();
+                    let Some(symbol) = self.next(item) else {
+                        continue;
+                    };
+                    let target = self.transition(index, symbol);
+                    let advanced = (item.0, item.1 + 1);
+                    let target = &mut self.states[target];
+                    let entry = target
+                        .items
+                        .iter_mut()
+                        .find(|(item, _)| *item == advanced)
+                        .unwrap();
+                    changed |= union(&mut entry.1, &lookaheads);
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    /// Pass the lookaheads of the items of the state on to the items they predict
+    fn predict(&mut self, index: usize, first: &[HashSet<Prefix>]) -> bool {
+        let mut changed = false;
+        loop {
+            let mut grown = false;
+            for item_index in 0..self.states[index].items.len() {
+                let ((production, dot), lookaheads) = self.states[index].items[item_index].clone();
+                let Some(Symbol::Nonterminal(nonterminal)) = self.next((production, dot)) else {
+                    continue;
+                };
+                let mut follow = Vec::new();
+                for prefix in first_of(&self.productions[production].1[dot + 1..], first) {
+                    match prefix.first() {
+                        Some(terminal) => union(&mut follow, &[*terminal]),
+                        None => union(&mut follow, &lookaheads),
+                    };
+                }
+                let predicted: Vec<usize> = self
+                    .productions_of(nonterminal)
+                    .map(|(production, _)| production)
+                    .collect();
+                for (item, lookaheads) in &mut self.states[index].items {
+                    if item.1 == 0 && predicted.contains(&item.0) {
+                        grown |= union(lookaheads, &follow);
+                    }
+                }
+            }
+            if !grown {
+                return changed;
+            }
+            changed = true;
+        }
+    }
+
+    /// Return the actions and gotos of each state, and the conflicts between actions. Of
+    /// conflicting actions, shifting and the earlier production win.
+    fn table(&self) -> Tables {
+        let mut actions = Vec::new();
+        let mut gotos = Vec::new();
+        let mut conflicts = Vec::new();
+        for (index, state) in self.states.iter().enumerate() {
+            let mut state_actions: HashMap<Terminal, Action> = HashMap::new();
+            let mut state_gotos = HashMap::new();
+            for &(symbol, target) in &state.transitions {
+                match symbol {
+                    Symbol::Terminal(terminal) => {
+                        state_actions.insert(terminal, Action::Shift(target));
+                    }
+                    Symbol::Nonterminal(nonterminal) => {
+                        state_gotos.insert(nonterminal, target);
+                    }
+                }
+            }
+            for ((production, dot), lookaheads) in &state.items {
+                if *dot < self.productions[*production].1.len() {
+                    continue;
+                }
+                for &terminal in lookaheads {
+                    let action = if *production == 0 {
+                        Action::Accept
+                    } else {
+                        Action::Reduce(*production)
+                    };
+                    let kind = match state_actions.get(&terminal) {
+                        None => {
+                            state_actions.insert(terminal, action);
+                            continue;
+                        }
+                        Some(Action::Shift(_)) => {
+                            LrConflictKind::ShiftReduce(self.production(*production))
+                        }
+                        Some(Action::Reduce(other)) => LrConflictKind::ReduceReduce(
+                            self.production(*other),
+                            self.production(*production),
+                        ),
+                        Some(Action::Accept) => LrConflictKind::ReduceReduce(
+                            self.production(0),
+                            self.production(*production),
+                        ),
+                    };
+                    conflicts.push(LrConflict {
+                        state: index,
+                        terminal,
+                        kind,
+                    });
+                }
+            }
+            actions.push(state_actions);
+            gotos.push(state_gotos);
+        }
+        Tables {
+            actions,
+            gotos,
+            conflicts,
+        }
+    }
+
+    /// Return the symbol after the dot, None at the end of the production
+    fn next(&self, (production, dot): Item) -> Option<Symbol> {
+        self.productions[production].1.get(dot).copied()
+    }
+
+    fn transition(&self, state: usize, symbol: Symbol) -> usize {
+        self.states[state]
+            .transitions
+            .iter()
+            .find(|(known, _)| *known == symbol)
+            .map(|(_, target)| *target)
+            .unwrap()
+    }
+
+    fn productions_of(
+        &self,
+        nonterminal: usize,
+    ) -> impl Iterator<Item = (usize, &(usize, Vec<Symbol>))> {
+        self.productions
+            .iter()
+            .enumerate()
+            .filter(move |(_, (left, _))| *left == nonterminal)
+    }
+
+    fn name(&self, nonterminal: usize) -> String {
+        match self.bnf.nonterminals.get(nonterminal) {
+            Some(nonterminal) => nonterminal.name.clone(),
+            None => format!("{}'", self.bnf.nonterminals[0].name),
+        }
+    }
+
+    /// Return the production as text, with a dot at the position if there is one
+    fn item(&self, production: usize, dot: Option<usize>) -> String {
+        let (left, symbols) = &self.productions[production];
+        let mut text = format!("{} ::=", self.name(*left));
+        for (index, symbol) in symbols.iter().enumerate() {
+            if dot == Some(index) {
+                text.push_str(" .");
+            }
+            text.push_str(&format!(" {}", self.bnf.display(*symbol)));
+        }
+        if dot == Some(symbols.len()) {
+            text.push_str(" .");
+        }
+        text
+    }
+
+    fn production(&self, production: usize) -> String {
+        self.item(production, None)
+    }
+}
+
+/// Prints each state with its items, their lookaheads and the transitions, followed by the
+/// conflicts
+impl fmt::Display for Automaton {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, state) in self.states.iter().enumerate() {
+            writeln!(f, "state {}", index)?;
+            for ((production, dot), lookaheads) in &state.items {
+                write!(f, "    {}  [", self.item(*production, Some(*dot)))?;
+                for (index, terminal) in lookaheads.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(" ")?;
+                    }
+                    write!(f, "{}", terminal)?;
+                }
+                writeln!(f, "]")?;
+            }
+            for (symbol, target) in &state.transitions {
+                writeln!(f, "    on {} go to {}", self.bnf.display(*symbol), target)?;
+            }
+        }
+        writeln!(f, "LALR(1) conflicts")?;
+        let conflicts = self.conflicts();
+        if conflicts.is_empty() {
+            writeln!(f, "    none")?;
+        }
+        for conflict in conflicts {
+            writeln!(f, "    {}", conflict)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for LrConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            LrConflictKind::ShiftReduce(production) => write!(
+                f,
+                "state {}: shift {} or reduce {}",
+                self.state, self.terminal, production
+            ),
+            LrConflictKind::ReduceReduce(a, b) => write!(
+                f,
+                "state {}: on {} reduce {} or {}",
+                self.state, self.terminal, a, b
+            ),
+        }
+    }
+}
+
+/// A bottom-up parser driven by the LALR(1) tables of a grammar. Like
+/// [`LlParser`](crate::grammar::LlParser), it keeps its state on an explicit stack.
+/// ```
+/// use cb_3::grammar::{Grammar, LrParser};
+/// use cb_3::C1Parser;
+///
+/// let parser = LrParser::new(&Grammar::c1()).unwrap();
+/// for text in ["void f() { x = g(); }", "void f() { x = g; }", "void f() { x; }"] {
+///     assert_eq!(parser.parse(text), C1Parser::parse(text));
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct LrParser {
+    /// The nonterminal and length of each production
+    productions: Vec<(usize, usize)>,
+    actions: Vec<HashMap<Terminal, Action>>,
+    gotos: Vec<HashMap<usize, usize>>,
+}
+
+impl LrParser {
+    /// Compute the parse tables of the grammar. Fails with the conflicts if the grammar is not
+    /// LALR(1).
+    pub fn new(grammar: &Grammar) -> Result<LrParser, Vec<LrConflict>> {
+        let automaton = Automaton::new(grammar);
+        let Tables {
+            actions,
+            gotos,
+            conflicts,
+        } = automaton.table();
+        if !conflicts.is_empty() {
+            return Err(conflicts);
+        }
+        Ok(LrParser {
+            productions: automaton
+                .productions
+                .iter()
+                .map(|(left, symbols)| (*left, symbols.len()))
+                .collect(),
+            actions,
+            gotos,
+        })
+    }
+
+    /// Check the syntax of the text
+    pub fn parse(&self, text: &str) -> ParseResult {
+        let mut lexer = C1Lexer::new(text);
+        let mut stack = vec![0];
+        loop {
+            let state = stack[stack.len() - 1];
+            let current = terminal(lexer.current_token());
+            match self.actions[state].get(&current) {
+                Some(Action::Shift(target)) => {
+                    stack.push(*target);
+                    if current != Terminal::Eof {
+                        lexer.eat();
+                    }
+                }
+                Some(Action::Reduce(production)) => {
+                    let (left, length) = self.productions[*production];
+                    stack.truncate(stack.len() - length);
+                    let state = stack[stack.len() - 1];
+                    stack.push(self.gotos[state][&left]);
+                }
+                Some(Action::Accept) => return Ok(()),
+                None => return Err(unexpected(&lexer, false)),
+            }
+        }
+    }
+}
+
+/// Return the symbols with and without each optional part
+fn expand_optionals(bnf: &Bnf, symbols: &[Symbol]) -> Vec<Vec<Symbol>> {
+    let mut variants = vec![Vec::new()];
+    for &symbol in symbols {
+        match symbol {
+            Symbol::Nonterminal(index)
+                if matches!(bnf.nonterminals[index].origin, Ebnf::Optional(_)) =>
+            {
+                let parts = expand_optionals(bnf, &bnf.nonterminals[index].alternatives[0]);
+                variants = variants
+                    .into_iter()
+                    .flat_map(|variant| {
+                        let with = parts.iter().map({
+                            let variant = variant.clone();
+                            move |part| [variant.clone(), part.clone()].concat()
+                        });
+                        with.chain([variant])
+                    })
+                    .collect();
+            }
+            _ => variants.iter_mut().for_each(|variant| variant.push(symbol)),
+        }
+    }
+    variants
+}
+
+/// Add the terminals missing in the set and return whether the set grew
+fn union(set: &mut Vec<Terminal>, terminals: &[Terminal]) -> bool {
+    let length = set.len();
+    for terminal in terminals {
+        if !set.contains(terminal) {
+            set.push(*terminal);
+        }
+    }
+    set.len() != length
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grammar::{Automaton, Grammar, LlParser, LrConflictKind, LrParser, Terminal};
+    use crate::C1Token;
+
+    #[test]
+    fn c1_tables() {
+        let grammar = Grammar::c1();
+        let automaton = Automaton::new(&grammar);
+        assert!(automaton.conflicts().is_empty());
+        let parser = LrParser::new(&grammar).unwrap();
+        assert_eq!(parser.parse("void f() { g(); x = (y); }"), Ok(()));
+        let error = parser.parse("void f() {\n  x y;\n}").unwrap_err();
+        assert_eq!((error.line, error.text.as_deref()), (Some(2), Some("y")));
+        let error = parser.parse("void f() {").unwrap_err();
+        assert_eq!((error.line, error.text), (None, None));
+    }
+
+    #[test]
+    fn conflicts() {
+        // The dangling else
+        let grammar = Grammar::parse(
+            "statement ::= <KW_IF> \"(\" <ID> \")\" statement ( <KW_ELSE> statement )? | \";\"",
+        )
+        .unwrap();
+        let conflicts = LrParser::new(&grammar).unwrap_err();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].terminal, Terminal::Token(C1Token::KwElse));
+        assert_eq!(
+            conflicts[0].kind,
+            LrConflictKind::ShiftReduce(
+                "statement ::= <KW_IF> \"(\" <ID> \")\" statement".to_string()
+            )
+        );
+
+        let grammar = Grammar::parse("s ::= a <ID> | b <ID>  a ::= \";\"  b ::= \";\"").unwrap();
+        let conflicts = Automaton::new(&grammar).conflicts();
+        assert_eq!(
+            conflicts[0].to_string(),
+            "state 4: on <ID> reduce a ::= \";\" or b ::= \";\""
+        );
+
+        // Left recursion is no problem bottom-up
+        let grammar = Grammar::parse("sum ::= sum \"+\" <ID> | <ID>").unwrap();
+        assert!(LlParser::new(&grammar).is_err());
+        let parser = LrParser::new(&grammar).unwrap();
+        assert_eq!(parser.parse("a + b + c"), Ok(()));
+        assert_eq!(parser.parse("a + + c").unwrap_err().span, 4..5);
+    }
+}
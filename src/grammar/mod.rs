@@ -0,0 +1,520 @@
+//! The C(-1) grammar as data, read from the EBNF notation of `c-1-syntax.ebnf`.
+//!
+//! A grammar is a list of productions `name ::= body`, the first of which is the start rule. A
+//! body combines terminals and references to other rules with `|`, grouping parentheses and the
+//! postfix operators `?`, `*` and `+`. Terminals are either written literally, e.g. `"("`, or name
+//! a token class in angle brackets, e.g. `<ID>`:
+//! ```
+//! use cb_3::grammar::{Ebnf, Grammar, Terminal};
+//! use cb_3::C1Token;
+//!
+//! let grammar = Grammar::parse("call ::= <ID> \"(\" \")\" ( \";\" )?").unwrap();
+//! let body = &grammar.production("call").unwrap().body;
+//! assert_eq!(
+//!     body,
+//!     &Ebnf::Sequence(vec![
+//!         Ebnf::Terminal(Terminal::Token(C1Token::Identifier)),
+//!         Ebnf::Terminal(Terminal::Token(C1Token::LeftParenthesis)),
+//!         Ebnf::Terminal(Terminal::Token(C1Token::RightParenthesis)),
+//!         Ebnf::Optional(Box::new(Ebnf::Terminal(Terminal::Token(C1Token::Semicolon)))),
+//!     ])
+//! );
+//! assert_eq!(body.to_string(), "<ID> \"(\" \")\" ( \";\" )?");
+//! ```
+use crate::lexer::C1Token;
+use std::fmt;
+
+mod analysis;
+mod bnf;
+mod generate;
+mod ll;
+mod lr;
+
+pub use analysis::{Analysis, Conflict, ConflictKind};
+pub use generate::{Generator, GeneratorOptions};
+pub use ll::LlParser;
+pub use lr::{Automaton, LrConflict, LrConflictKind, LrParser};
+
+/// The grammar of C(-1) that [`C1Parser`](crate::C1Parser) implements
+pub const C1_GRAMMAR: &str = include_str!("../../c-1-syntax.ebnf");
+
+/// The spelling of each terminal in the EBNF notation
+const TERMINALS: &[(&str, C1Token)] = &[
+    ("<ID>", C1Token::Identifier),
+    ("<CONST_INT>", C1Token::ConstInt),
+    ("<CONST_FLOAT>", C1Token::ConstFloat),
+    ("<CONST_BOOLEAN>", C1Token::ConstBoolean),
+    ("<CONST_STRING>", C1Token::ConstString),
+    ("<KW_BOOLEAN>", C1Token::KwBoolean),
+    ("<KW_DO>", C1Token::KwDo),
+    ("<KW_ELSE>", C1Token::KwElse),
+    ("<KW_FLOAT>", C1Token::KwFloat),
+    ("<KW_FOR>", C1Token::KwFor),
+    ("<KW_IF>", C1Token::KwIf),
+    ("<KW_INT>", C1Token::KwInt),
+    ("<KW_PRINTF>", C1Token::KwPrintf),
+    ("<KW_RETURN>", C1Token::KwReturn),
+    ("<KW_VOID>", C1Token::KwVoid),
+    ("<KW_WHILE>", C1Token::KwWhile),
+    ("\"+\"", C1Token::Plus),
+    ("\"-\"", C1Token::Minus),
+    ("\"*\"", C1Token::Asterisk),
+    ("\"/\"", C1Token::Slash),
+    ("\"=\"", C1Token::Assign),
+    ("\"==\"", C1Token::Equal),
+    ("\"!=\"", C1Token::NotEqual),
+    ("\"<\"", C1Token::Less),
+    ("\">\"", C1Token::Greater),
+    ("\"<=\"", C1Token::LessEqual),
+    ("\">=\"", C1Token::GreaterEqual),
+    ("\"&&\"", C1Token::And),
+    ("\"||\"", C1Token::Or),
+    ("\",\"", C1Token::Comma),
+    ("\";\"", C1Token::Semicolon),
+    ("\"(\"", C1Token::LeftParenthesis),
+    ("\")\"", C1Token::RightParenthesis),
+    ("\"{\"", C1Token::LeftBrace),
+    ("\"}\"", C1Token::RightBrace),
+];
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum Terminal {
+    Token(C1Token),
+    /// `<EOF>`, the end of the text
+    Eof,
+}
+
+/// The body of a production
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Ebnf {
+    Terminal(Terminal),
+    /// Reference to the production with the name
+    Rule(String),
+    /// The parts one after the other. The empty sequence derives the empty word.
+    Sequence(Vec<Ebnf>),
+    /// One of the alternatives
+    Choice(Vec<Ebnf>),
+    /// `( ... )?`
+    Optional(Box<Ebnf>),
+    /// `( ... )*`; `( ... )+` is read as a sequence of the body and its repetition
+    Repeat(Box<Ebnf>),
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Production {
+    pub name: String,
+    pub body: Ebnf,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Grammar {
+    productions: Vec<Production>,
+}
+
+/// A problem in the EBNF text
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct GrammarError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for GrammarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "grammar error at line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for GrammarError {}
+
+impl Grammar {
+    /// Read a grammar from EBNF. Every referenced rule must be defined exactly once.
+    pub fn parse(text: &str) -> Result<Grammar, GrammarError> {
+        let mut reader = Reader {
+            tokens: scan(text)?,
+            position: 0,
+        };
+        let mut productions: Vec<Production> = Vec::new();
+        while let Some(&(line, ref token)) = reader.tokens.get(reader.position) {
+            let EbnfToken::Name(name) = token else {
+                return Err(reader.error("expected the name of a rule"));
+            };
+            if productions
+                .iter()
+                .any(|production| production.name == *name)
+            {
+                return Err(GrammarError {
+                    line,
+                    message: format!("rule '{}' is defined twice", name),
+                });
+            }
+            let name = name.clone();
+            reader.position += 1;
+            reader.expect(EbnfToken::Define, "'::='")?;
+            let body = reader.choice()?;
+            productions.push(Production { name, body });
+        }
+        let grammar = Grammar { productions };
+        for production in &grammar.productions {
+            let mut undefined = None;
+            production.body.for_each_rule(&mut |name| {
+                if undefined.is_none() && grammar.production(name).is_none() {
+                    undefined = Some(name.to_string());
+                }
+            });
+            if let Some(name) = undefined {
+                let line = reader
+                    .tokens
+                    .iter()
+                    .find(|(_, token)| *token == EbnfToken::Name(name.clone()))
+                    .map_or(1, |(line, _)| *line);
+                return Err(GrammarError {
+                    line,
+                    message: format!("rule '{}' is not defined", name),
+                });
+            }
+        }
+        if grammar.productions.is_empty() {
+            return Err(GrammarError {
+                line: 1,
+                message: "the grammar has no rules".to_string(),
+            });
+        }
+        Ok(grammar)
+    }
+
+    /// Return the grammar of C(-1)
+    pub fn c1() -> Grammar {
+        Grammar::parse(C1_GRAMMAR).expect("c-1-syntax.ebnf is a valid grammar")
+    }
+
+    /// Return the productions in the order of their definition
+    pub fn productions(&self) -> &[Production] {
+        &self.productions
+    }
+
+    pub fn production(&self, name: &str) -> Option<&Production> {
+        self.productions
+            .iter()
+            .find(|production| production.name == name)
+    }
+
+    /// Return the name of the start rule, which is defined first
+    pub fn start(&self) -> &str {
+        &self.productions[0].name
+    }
+
+    /// Return all terminals used by the grammar, each once in the order of first use
+    pub fn terminals(&self) -> Vec<Terminal> {
+        let mut terminals = Vec::new();
+        for production in &self.productions {
+            production.body.for_each_terminal(&mut |terminal| {
+                if !terminals.contains(&terminal) {
+                    terminals.push(terminal);
+                }
+            });
+        }
+        terminals
+    }
+}
+
+impl Ebnf {
+    /// Call f for every rule referenced in the body
+    pub fn for_each_rule<'a>(&'a self, f: &mut dyn FnMut(&'a str)) {
+        match self {
+            Ebnf::Terminal(_) => {}
+            Ebnf::Rule(name) => f(name),
+            Ebnf::Sequence(parts) | Ebnf::Choice(parts) => {
+                parts.iter().for_each(|part| part.for_each_rule(f))
+            }
+            Ebnf::Optional(body) | Ebnf::Repeat(body) => body.for_each_rule(f),
+        }
+    }
+
+    /// Call f for every terminal in the body
+    pub fn for_each_terminal(&self, f: &mut dyn FnMut(Terminal)) {
+        match self {
+            Ebnf::Terminal(terminal) => f(*terminal),
+            Ebnf::Rule(_) => {}
+            Ebnf::Sequence(parts) | Ebnf::Choice(parts) => {
+                parts.iter().for_each(|part| part.for_each_terminal(f))
+            }
+            Ebnf::Optional(body) | Ebnf::Repeat(body) => body.for_each_terminal(f),
+        }
+    }
+}
+
+impl Terminal {
+    /// Return the spelling of the terminal in EBNF, e.g. `"("` or `<ID>`
+    pub fn spelling(self) -> &'static str {
+        match self {
+            Terminal::Token(token) => TERMINALS
+                .iter()
+                .find(|(_, known)| *known == token)
+                .map_or("<?>", |(spelling, _)| spelling),
+            Terminal::Eof => "<EOF>",
+        }
+    }
+}
+
+impl fmt::Display for Terminal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.spelling())
+    }
+}
+
+impl fmt::Display for Ebnf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ebnf::Terminal(terminal) => write!(f, "{}", terminal),
+            Ebnf::Rule(name) => f.write_str(name),
+            Ebnf::Sequence(parts) => {
+                for (index, part) in parts.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(" ")?;
+                    }
+                    match part {
+                        Ebnf::Choice(_) => write!(f, "( {} )", part)?,
+                        _ => write!(f, "{}", part)?,
+                    }
+                }
+                Ok(())
+            }
+            Ebnf::Choice(alternatives) => {
+                for (index, alternative) in alternatives.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(" | ")?;
+                    }
+                    write!(f, "{}", alternative)?;
+                }
+                Ok(())
+            }
+            Ebnf::Optional(body) => write!(f, "( {} )?", body),
+            Ebnf::Repeat(body) => write!(f, "( {} )*", body),
+        }
+    }
+}
+
+impl fmt::Display for Grammar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for production in &self.productions {
+            writeln!(f, "{} ::= {}", production.name, production.body)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum EbnfToken {
+    Name(String),
+    Terminal(Terminal),
+    /// `::=`
+    Define,
+    Bar,
+    Open,
+    Close,
+    Question,
+    Star,
+    Plus,
+}
+
+fn scan(text: &str) -> Result<Vec<(usize, EbnfToken)>, GrammarError> {
+    let mut tokens = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let error = |message: String| GrammarError {
+            line: index + 1,
+            message,
+        };
+        let mut rest = line.trim_start();
+        while !rest.is_empty() {
+            let (token, length) = match rest.as_bytes()[0] {
+                b'|' => (EbnfToken::Bar, 1),
+                b'(' => (EbnfToken::Open, 1),
+                b')' => (EbnfToken::Close, 1),
+                b'?' => (EbnfToken::Question, 1),
+                b'*' => (EbnfToken::Star, 1),
+                b'+' => (EbnfToken::Plus, 1),
+                b':' if rest.starts_with("::=") => (EbnfToken::Define, 3),
+                quote @ (b'"' | b'<') => {
+                    let close = if quote == b'"' { '"' } else { '>' };
+                    let length = match rest[1..].find(close) {
+                        Some(end) => end + 2,
+                        None => return Err(error(format!("unterminated terminal {}", rest))),
+                    };
+                    let spelling = &rest[..length];
+                    let terminal = if spelling == "<EOF>" {
+                        Terminal::Eof
+                    } else {
+                        match TERMINALS.iter().find(|(known, _)| *known == spelling) {
+                            Some((_, token)) => Terminal::Token(*token),
+                            None => return Err(error(format!("unknown terminal {}", spelling))),
+                        }
+                    };
+                    (EbnfToken::Terminal(terminal), length)
+                }
+                c if c.is_ascii_alphabetic() || c == b'_' => {
+                    let length = rest
+                        .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                        .unwrap_or(rest.len());
+                    (EbnfToken::Name(rest[..length].to_string()), length)
+                }
+                _ => {
+                    let c = rest.chars().next().unwrap();
+                    return Err(error(format!("unexpected character '{}'", c)));
+                }
+            };
+            tokens.push((index + 1, token));
+            rest = rest[length..].trim_start();
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent over the tokens of an EBNF text
+struct Reader {
+    tokens: Vec<(usize, EbnfToken)>,
+    position: usize,
+}
+
+impl Reader {
+    fn current(&self) -> Option<&EbnfToken> {
+        self.tokens.get(self.position).map(|(_, token)| token)
+    }
+
+    fn error(&self, message: &str) -> GrammarError {
+        match self.tokens.get(self.position) {
+            Some((line, token)) => GrammarError {
+                line: *line,
+                message: format!("{}, found {:?}", message, token),
+            },
+            None => GrammarError {
+                line: self.tokens.last().map_or(1, |(line, _)| *line),
+                message: format!("{}, found the end of the grammar", message),
+            },
+        }
+    }
+
+    fn expect(&mut self, token: EbnfToken, description: &str) -> Result<(), GrammarError> {
+        if self.current() == Some(&token) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected {}", description)))
+        }
+    }
+
+    /// Check whether the current token starts the next production
+    fn at_definition(&self) -> bool {
+        matches!(self.current(), Some(EbnfToken::Name(_)))
+            && matches!(
+                self.tokens.get(self.position + 1),
+                Some((_, EbnfToken::Define))
+            )
+    }
+
+    fn choice(&mut self) -> Result<Ebnf, GrammarError> {
+        let mut alternatives = vec![self.sequence()?];
+        while self.current() == Some(&EbnfToken::Bar) {
+            self.position += 1;
+            alternatives.push(self.sequence()?);
+        }
+        Ok(if alternatives.len() == 1 {
+            alternatives.pop().unwrap()
+        } else {
+            Ebnf::Choice(alternatives)
+        })
+    }
+
+    fn sequence(&mut self) -> Result<Ebnf, GrammarError> {
+        let mut parts = Vec::new();
+        while !self.at_definition() {
+            let part = match self.current() {
+                Some(EbnfToken::Name(name)) => Ebnf::Rule(name.clone()),
+                Some(EbnfToken::Terminal(terminal)) => Ebnf::Terminal(*terminal),
+                Some(EbnfToken::Open) => {
+                    self.position += 1;
+                    let body = self.choice()?;
+                    if self.current() != Some(&EbnfToken::Close) {
+                        return Err(self.error("expected ')'"));
+                    }
+                    body
+                }
+                _ => break,
+            };
+            self.position += 1;
+            let part = match self.current() {
+                Some(EbnfToken::Question) => Ebnf::Optional(Box::new(part)),
+                Some(EbnfToken::Star) => Ebnf::Repeat(Box::new(part)),
+                Some(EbnfToken::Plus) => {
+                    Ebnf::Sequence(vec![part.clone(), Ebnf::Repeat(Box::new(part))])
+                }
+                _ => {
+                    parts.push(part);
+                    continue;
+                }
+            };
+            self.position += 1;
+            parts.push(part);
+        }
+        Ok(if parts.len() == 1 {
+            parts.pop().unwrap()
+        } else {
+            Ebnf::Sequence(parts)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grammar::{Ebnf, Grammar, GrammarError, Terminal};
+    use crate::C1Token;
+
+    #[test]
+    fn c1_grammar() {
+        let grammar = Grammar::c1();
+        assert_eq!(grammar.start(), "program");
+        assert_eq!(grammar.productions().len(), 16);
+        assert_eq!(
+            grammar.production("program").unwrap().body,
+            Ebnf::Sequence(vec![
+                Ebnf::Repeat(Box::new(Ebnf::Rule("functiondefinition".to_string()))),
+                Ebnf::Terminal(Terminal::Eof),
+            ])
+        );
+        assert_eq!(
+            grammar.production("statement").unwrap().body.to_string(),
+            "ifstatement | returnstatement \";\" | printf \";\" | statassignment \";\" \
+             | functioncall \";\""
+        );
+        assert!(!grammar
+            .terminals()
+            .contains(&Terminal::Token(C1Token::KwWhile)));
+
+        // The printed grammar reads back to the same grammar
+        assert_eq!(Grammar::parse(&grammar.to_string()), Ok(grammar));
+    }
+
+    #[test]
+    fn errors() {
+        let error = |text: &str| Grammar::parse(text).unwrap_err();
+        assert_eq!(
+            error("a ::= b\n\nb ::= \"(\" c"),
+            GrammarError {
+                line: 3,
+                message: "rule 'c' is not defined".to_string()
+            }
+        );
+        assert_eq!(error("a ::= <WHILE>").message, "unknown terminal <WHILE>");
+        assert_eq!(error("a ::= ( \"(\"").line, 1);
+        assert_eq!(
+            error("a ::= <ID>\na ::= <ID>").message,
+            "rule 'a' is defined twice"
+        );
+        assert_eq!(error("").message, "the grammar has no rules");
+        assert_eq!(
+            Grammar::parse("a ::= ( <ID> )+").unwrap().productions()[0]
+                .body
+                .to_string(),
+            "<ID> ( <ID> )*"
+        );
+    }
+}
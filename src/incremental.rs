@@ -0,0 +1,469 @@
+use crate::ast::{Declaration, Expression, FunctionDefinition, Program, Statement};
+use crate::lexer::{C1Lexer, C1Token, Token};
+use crate::parser::{C1Parser, ParseError};
+use crate::visit::{
+    walk_declaration_mut, walk_expression_mut, walk_function_mut, walk_statement_mut, VisitorMut,
+};
+use std::ops::Range;
+
+/// How many bytes after its end the logos lexer may read to decide where a token ends, e.g. "e+x"
+/// after "1" in "1e+x". Slashes followed by "*" and error tokens may read arbitrarily far.
+const LOOKAHEAD: usize = 3;
+
+/// A parse of a text that is kept up to date while the text is edited, e.g. in an editor.
+///
+/// An edit only lexes the text around the edited range again and only parses the function
+/// definitions again that contain changed tokens. Tokens and function definitions before and after
+/// the edit are reused with their lines and byte ranges shifted. The result is always identical
+/// to parsing the whole text with [`C1Parser::parse_program`].
+///
+/// ```
+/// use cb_3::incremental::IncrementalParse;
+///
+/// let mut parse = IncrementalParse::new("void a() {}\nvoid b() {}");
+/// parse.edit(10..10, "return;");
+/// assert_eq!(parse.text(), "void a() {return;}\nvoid b() {}");
+/// assert_eq!(parse.result().unwrap().functions[0].body.len(), 1);
+/// assert_eq!(parse.reparsed_functions(), 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct IncrementalParse {
+    text: String,
+    tokens: Vec<Token>,
+    program: Program,
+    /// Token index range of each function definition in `program`
+    ranges: Vec<Range<usize>>,
+    /// The syntax error that stopped parsing after the last function definition
+    error: Option<ParseError>,
+    /// Number of function definitions parsed by the last edit
+    reparsed: usize,
+}
+
+impl IncrementalParse {
+    /// Parse the whole text
+    pub fn new(text: &str) -> IncrementalParse {
+        let mut parse = IncrementalParse {
+            text: String::new(),
+            tokens: Vec::new(),
+            program: Program::default(),
+            ranges: Vec::new(),
+            error: None,
+            reparsed: 0,
+        };
+        parse.edit(0..0, text);
+        parse
+    }
+
+    /// Return the current text
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Return the syntax tree of the current text, or its first syntax error
+    pub fn result(&self) -> Result<&Program, &ParseError> {
+        match &self.error {
+            Some(error) => Err(error),
+            None => Ok(&self.program),
+        }
+    }
+
+    /// Return how many function definitions were parsed, successfully or not, by the last edit
+    pub fn reparsed_functions(&self) -> usize {
+        self.reparsed
+    }
+
+    /// Replace the byte range of the text with `replacement` and update the parse
+    ///
+    /// # Panics
+    /// If the range is out of bounds or does not lie on char boundaries
+    pub fn edit(&mut self, range: Range<usize>, replacement: &str) {
+        self.text.replace_range(range.clone(), replacement);
+        let delta = replacement.len() as isize - range.len() as isize;
+        let mut tokens = std::mem::take(&mut self.tokens);
+
+        // Tokens that end far enough before the edit are lexed the same way in the new text
+        let prefix = tokens
+            .iter()
+            .take_while(|token| is_stable(&self.text, token, range.start))
+            .count();
+        // Token index where the old parse stopped at a syntax error
+        let old_error_token = self
+            .error
+            .as_ref()
+            .map(|error| tokens.partition_point(|token| token.span.start < error.span.start));
+        let mut old_suffix = tokens.split_off(prefix);
+        let (restart, line) = match tokens.last() {
+            Some(token) => (
+                token.span.end,
+                token.line + token.text.matches('\n').count(),
+            ),
+            None => (0, 1),
+        };
+        let line_start = self.text[..restart]
+            .rfind('\n')
+            .map_or(0, |index| index + 1);
+        let column = self.text[line_start..restart].chars().count() + 1;
+
+        // Lex until a token starts behind the edit where a token started before. From there on
+        // the lexer sees the same text as before, so the old tokens can be shifted.
+        let mut suffix = None;
+        for token in C1Lexer::resume(&self.text, restart, line, column) {
+            if token.span.start >= range.start + replacement.len() {
+                let old_start = shift(token.span.start, -delta);
+                if let Ok(index) =
+                    old_suffix.binary_search_by_key(&old_start, |token| token.span.start)
+                {
+                    let old = &old_suffix[index];
+                    let shifted = Suffix {
+                        start: tokens.len(),
+                        old_start: prefix + index,
+                        line_delta: token.line as isize - old.line as isize,
+                        first_line: old.line,
+                        column_delta: token.column as isize - old.column as isize,
+                        delta,
+                    };
+                    tokens.extend(old_suffix.drain(index..).map(|mut token| {
+                        shifted.shift_token(&mut token);
+                        token
+                    }));
+                    suffix = Some(shifted);
+                    break;
+                }
+            }
+            tokens.push(token);
+        }
+
+        // Function definitions that end before the edit are parsed the same way in the new text.
+        // So is the syntax error after them, as long as neither the erroneous token nor the token
+        // after it changed.
+        let mut functions = std::mem::take(&mut self.program.functions);
+        let mut ranges = std::mem::take(&mut self.ranges);
+        let old_error = self.error.take();
+        let reused = ranges
+            .iter()
+            .take_while(|range| range.end <= prefix)
+            .count();
+        let mut old_functions = functions.split_off(reused);
+        let old_ranges = ranges.split_off(reused);
+        let error_is_stable =
+            old_ranges.is_empty() && old_error_token.is_some_and(|index| index + 1 < prefix);
+        if error_is_stable {
+            self.tokens = tokens;
+            self.program.functions = functions;
+            self.ranges = ranges;
+            self.error = old_error;
+            self.reparsed = 0;
+            return;
+        }
+
+        let mut position = ranges.last().map_or(0, |range| range.end);
+        // Token index where the old parse stopped at a syntax error
+        let old_error_start = old_ranges.last().map_or(position, |range| range.end);
+        let mut parser =
+            C1Parser::from_lexer(C1Lexer::from_tokens(&self.text, &tokens[position..]));
+        let mut reparsed = 0;
+        let error = loop {
+            // Function definitions that start behind the edit where one started before are parsed
+            // the same way, too
+            if let Some(suffix) = &suffix {
+                if let Some(old_position) = suffix.old_index(position) {
+                    let old_boundary = old_ranges
+                        .iter()
+                        .position(|range| range.start == old_position);
+                    if let Some(first) = old_boundary {
+                        for mut function in old_functions.drain(first..) {
+                            if suffix.line_delta != 0 {
+                                LineShift(suffix.line_delta).visit_function_mut(&mut function);
+                            }
+                            functions.push(function);
+                        }
+                        ranges.extend(old_ranges[first..].iter().map(|range| {
+                            suffix.new_index(range.start)..suffix.new_index(range.end)
+                        }));
+                        break old_error.map(|error| suffix.shift_error(error));
+                    }
+                    if old_error.is_some() && old_position == old_error_start {
+                        break old_error.map(|error| suffix.shift_error(error));
+                    }
+                }
+            }
+            if parser.current_token().is_none() {
+                break None;
+            }
+            reparsed += 1;
+            match parser.function_definition() {
+                Ok(function) => {
+                    let end = match parser.current_span() {
+                        Some(span) => tokens.partition_point(|token| token.span.start < span.start),
+                        None => tokens.len(),
+                    };
+                    functions.push(function);
+                    ranges.push(position..end);
+                    position = end;
+                }
+                Err(error) => break Some(error),
+            }
+        };
+
+        self.tokens = tokens;
+        self.program.functions = functions;
+        self.ranges = ranges;
+        self.error = error;
+        self.reparsed = reparsed;
+    }
+}
+
+/// Where the tokens lexed before the edit continue in the new token list
+struct Suffix {
+    /// Index of the first old token in the new token list
+    start: usize,
+    /// Index of the first old token in the old token list
+    old_start: usize,
+    line_delta: isize,
+    /// Old line of the first old token, whose columns shift along with it
+    first_line: usize,
+    column_delta: isize,
+    /// Difference between the new and the old byte offsets
+    delta: isize,
+}
+
+impl Suffix {
+    fn old_index(&self, index: usize) -> Option<usize> {
+        (index >= self.start).then(|| index - self.start + self.old_start)
+    }
+
+    fn new_index(&self, old_index: usize) -> usize {
+        old_index - self.old_start + self.start
+    }
+
+    fn shift_token(&self, token: &mut Token) {
+        if token.line == self.first_line {
+            token.column = shift(token.column, self.column_delta);
+        }
+        token.line = shift(token.line, self.line_delta);
+        token.span = shift(token.span.start, self.delta)..shift(token.span.end, self.delta);
+    }
+
+    fn shift_error(&self, error: ParseError) -> ParseError {
+        ParseError {
+            line: error.line.map(|line| shift(line, self.line_delta)),
+            span: shift(error.span.start, self.delta)..shift(error.span.end, self.delta),
+            ..error
+        }
+    }
+}
+
+/// Check whether the token is lexed the same way if the text from `edit_start` on changes
+fn is_stable(text: &str, token: &Token, edit_start: usize) -> bool {
+    token.span.end + LOOKAHEAD <= edit_start
+        && match token.kind {
+            C1Token::Error => false,
+            C1Token::Slash => !text[token.span.end..].starts_with('*'),
+            _ => true,
+        }
+}
+
+fn shift(value: usize, delta: isize) -> usize {
+    (value as isize + delta) as usize
+}
+
+/// Moves the nodes of a syntax tree by a number of lines
+pub(crate) struct LineShift(pub(crate) isize);
+
+impl VisitorMut for LineShift {
+    fn visit_function_mut(&mut self, function: &mut FunctionDefinition) {
+        function.line = shift(function.line, self.0);
+        for parameter in &mut function.parameters {
+            parameter.line = shift(parameter.line, self.0);
+        }
+        walk_function_mut(self, function);
+    }
+
+    fn visit_statement_mut(&mut self, statement: &mut Statement) {
+        statement.line = shift(statement.line, self.0);
+        walk_statement_mut(self, statement);
+    }
+
+    fn visit_declaration_mut(&mut self, declaration: &mut Declaration) {
+        declaration.line = shift(declaration.line, self.0);
+        walk_declaration_mut(self, declaration);
+    }
+
+    fn visit_expression_mut(&mut self, expression: &mut Expression) {
+        expression.line = shift(expression.line, self.0);
+        walk_expression_mut(self, expression);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::incremental::IncrementalParse;
+    use crate::C1Parser;
+
+    const EXAMPLE: &str = include_str!("../tests/data/beispiel.c-1");
+
+    fn assert_same_as_full_parse(parse: &IncrementalParse) {
+        let full = IncrementalParse::new(parse.text());
+        assert_eq!(parse.tokens, full.tokens, "tokens of {:?}", parse.text());
+        assert_eq!(parse.ranges, full.ranges, "ranges of {:?}", parse.text());
+        assert_eq!(
+            parse.result().map_err(Clone::clone),
+            C1Parser::parse_program(parse.text())
+                .as_ref()
+                .map_err(Clone::clone),
+            "result of {:?}",
+            parse.text()
+        );
+    }
+
+    #[test]
+    fn edits_inside_one_function_reuse_the_others() {
+        let mut parse = IncrementalParse::new(EXAMPLE);
+        assert_eq!(parse.reparsed_functions(), 3);
+
+        // "blub3 = 42;" becomes "blub3 = 4;\n\n" in the first function
+        let offset = EXAMPLE.find("42").unwrap();
+        parse.edit(offset + 1..offset + 3, ";\n\n");
+        assert_eq!(parse.reparsed_functions(), 1);
+        assert_same_as_full_parse(&parse);
+        assert_eq!(parse.result().unwrap().functions[2].line, 23);
+
+        // Renaming main only parses main again
+        let offset = parse.text().find("main").unwrap();
+        parse.edit(offset..offset + 4, "start");
+        assert_eq!(parse.reparsed_functions(), 1);
+        assert_same_as_full_parse(&parse);
+    }
+
+    #[test]
+    fn syntax_errors_are_kept_or_shifted() {
+        let mut parse = IncrementalParse::new("void a() {}\nvoid b() { x = ; }\nvoid c() {}");
+        let error = parse.result().unwrap_err().clone();
+        assert_eq!(error.line, Some(2));
+
+        // The error after the edit is shifted without parsing anything
+        parse.edit(0..0, "\n\n");
+        assert_eq!(parse.reparsed_functions(), 0);
+        assert_eq!(parse.result().unwrap_err().line, Some(4));
+        assert_eq!(
+            parse.result().unwrap_err().span,
+            error.span.start + 2..error.span.end + 2
+        );
+        assert_same_as_full_parse(&parse);
+
+        // The error before the edit is kept without parsing anything
+        let end = parse.text().len();
+        parse.edit(end - 6..end - 5, "d");
+        assert_eq!(parse.reparsed_functions(), 0);
+        assert_same_as_full_parse(&parse);
+
+        let start = parse.text().find("x = ;").unwrap();
+        parse.edit(start..start + 5, "x = 1;");
+        assert!(parse.result().is_ok());
+        assert_same_as_full_parse(&parse);
+    }
+
+    #[test]
+    fn tokens_depending_on_later_text_are_lexed_again() {
+        // Lexing "1" depends on the "e+" after it, "/" on the "*" after it
+        let mut parse = IncrementalParse::new("int f() { return 1e+ 2; }");
+        parse.edit(20..21, "");
+        assert_same_as_full_parse(&parse);
+
+        let mut parse = IncrementalParse::new("int f() { return 1 /* 2; } int g() {}");
+        let offset = parse.text().find("int g").unwrap();
+        parse.edit(offset..offset, "*/");
+        assert_same_as_full_parse(&parse);
+    }
+
+    #[test]
+    fn functions_are_reparsed_at_the_depth_of_a_full_parse() {
+        let function = |parentheses| {
+            format!(
+                "void f() {{ x = {}1{}; }}",
+                "(".repeat(parentheses),
+                ")".repeat(parentheses)
+            )
+        };
+        let deepest = (0..)
+            .take_while(|&parentheses| C1Parser::parse_program(&function(parentheses)).is_ok())
+            .last()
+            .unwrap();
+        // The function at the limit, one parenthesis deeper and back again
+        let mut parse = IncrementalParse::new(&format!("void a() {{}}\n{}", function(deepest)));
+        assert!(parse.result().is_ok());
+        let offset = parse.text().find('1').unwrap();
+        parse.edit(offset..offset + 1, "(1)");
+        assert_eq!(parse.reparsed_functions(), 1);
+        assert_eq!(parse.result().unwrap_err().reason, "nesting too deep");
+        assert_same_as_full_parse(&parse);
+        parse.edit(offset..offset + 3, "1");
+        assert_eq!(parse.reparsed_functions(), 1);
+        assert!(parse.result().is_ok());
+        assert_same_as_full_parse(&parse);
+    }
+
+    /// Xorshift generator, so that failures can be reproduced
+    struct Random(u64);
+
+    impl Random {
+        fn below(&mut self, bound: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % bound as u64) as usize
+        }
+    }
+
+    #[test]
+    fn random_edits_give_the_same_result_as_a_full_parse() {
+        const FRAGMENTS: &[&str] = &[
+            "",
+            " ",
+            "\n",
+            "{",
+            "}",
+            "(",
+            ")",
+            ";",
+            "=",
+            "<",
+            "-",
+            "/",
+            "*",
+            "/*",
+            "*/",
+            "//",
+            "\"",
+            "1",
+            ".5",
+            "e+",
+            "x",
+            "if",
+            "int",
+            "return x;",
+            "int f() {}",
+            "void g() { h(); }",
+            "printf(1.5 * x);",
+            "ä",
+        ];
+        for seed in 1..=20u64 {
+            let mut random = Random(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+            let mut parse = IncrementalParse::new(EXAMPLE);
+            for _ in 0..200 {
+                let text = parse.text();
+                let mut start = random.below(text.len() + 1);
+                while !text.is_char_boundary(start) {
+                    start -= 1;
+                }
+                let mut end = (start + random.below(8)).min(text.len());
+                while !text.is_char_boundary(end) {
+                    end += 1;
+                }
+                let replacement = FRAGMENTS[random.below(FRAGMENTS.len())];
+                parse.edit(start..end, replacement);
+                assert_same_as_full_parse(&parse);
+            }
+        }
+    }
+}
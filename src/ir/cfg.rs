@@ -0,0 +1,121 @@
+use crate::ir::{BlockId, Function};
+
+/// Control-flow graph of a lowered function.
+///
+/// The graph is a snapshot: it has to be rebuilt after the terminators of the function change.
+#[derive(Debug, Clone)]
+pub struct Cfg {
+    successors: Vec<Vec<BlockId>>,
+    predecessors: Vec<Vec<BlockId>>,
+}
+
+impl Cfg {
+    pub fn new(function: &Function) -> Cfg {
+        let successors: Vec<Vec<BlockId>> = function
+            .blocks
+            .iter()
+            .map(|block| block.terminator.successors())
+            .collect();
+        let mut predecessors = vec![Vec::new(); successors.len()];
+        for (index, targets) in successors.iter().enumerate() {
+            for target in targets {
+                predecessors[target.0].push(BlockId(index));
+            }
+        }
+        Cfg {
+            successors,
+            predecessors,
+        }
+    }
+
+    /// Number of blocks in the graph, including unreachable ones
+    pub fn len(&self) -> usize {
+        self.successors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.successors.is_empty()
+    }
+
+    /// Iterate over all blocks in label order
+    pub fn blocks(&self) -> impl Iterator<Item = BlockId> {
+        (0..self.len()).map(BlockId)
+    }
+
+    pub fn successors(&self, block: BlockId) -> &[BlockId] {
+        &self.successors[block.0]
+    }
+
+    pub fn predecessors(&self, block: BlockId) -> &[BlockId] {
+        &self.predecessors[block.0]
+    }
+
+    /// Return the blocks reachable from the entry block in reverse postorder, i.e. every block
+    /// appears before its successors unless the edge between them is a back edge
+    pub fn reverse_postorder(&self) -> Vec<BlockId> {
+        let mut visited = vec![false; self.len()];
+        let mut postorder = Vec::new();
+        // Explicit stack of (block, index of the next successor to visit)
+        let mut stack = vec![(Function::ENTRY, 0)];
+        visited[Function::ENTRY.0] = true;
+        while let Some((block, next)) = stack.pop() {
+            if let Some(&successor) = self.successors(block).get(next) {
+                stack.push((block, next + 1));
+                if !visited[successor.0] {
+                    visited[successor.0] = true;
+                    stack.push((successor, 0));
+                }
+            } else {
+                postorder.push(block);
+            }
+        }
+        postorder.reverse();
+        postorder
+    }
+
+    /// Return for each block whether it can be reached from the entry block
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.len()];
+        for block in self.reverse_postorder() {
+            reachable[block.0] = true;
+        }
+        reachable
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ir::{lower_program, BlockId, Cfg};
+    use crate::C1Parser;
+
+    fn cfg(text: &str) -> Cfg {
+        let module = lower_program(&C1Parser::parse_program(text).unwrap());
+        Cfg::new(&module.functions[0])
+    }
+
+    #[test]
+    fn if_statement_edges() {
+        let cfg = cfg("void main() { if (a) { if (b) c(); } d(); }");
+        // bb0 -> bb1 (then a), bb2 (join a); bb1 -> bb3 (then b), bb4 (join b); bb3 -> bb4 -> bb2
+        assert_eq!(cfg.len(), 5);
+        assert_eq!(cfg.successors(BlockId(0)), &[BlockId(1), BlockId(2)]);
+        assert_eq!(cfg.successors(BlockId(1)), &[BlockId(3), BlockId(4)]);
+        assert_eq!(cfg.successors(BlockId(3)), &[BlockId(4)]);
+        assert_eq!(cfg.successors(BlockId(4)), &[BlockId(2)]);
+        assert_eq!(cfg.successors(BlockId(2)), &[]);
+        assert_eq!(cfg.predecessors(BlockId(2)), &[BlockId(0), BlockId(4)]);
+        assert_eq!(cfg.predecessors(BlockId(4)), &[BlockId(1), BlockId(3)]);
+        assert_eq!(cfg.predecessors(BlockId(0)), &[]);
+    }
+
+    #[test]
+    fn reverse_postorder_and_reachability() {
+        let cfg = cfg("int f() { if (a) return 1; return 2; b = 1; }");
+        assert_eq!(
+            cfg.reverse_postorder(),
+            vec![BlockId(0), BlockId(2), BlockId(1)]
+        );
+        assert_eq!(cfg.reachable(), vec![true, true, true, false]);
+        assert!(cfg.predecessors(BlockId(3)).is_empty());
+    }
+}
@@ -0,0 +1,180 @@
+use crate::ir::{BlockId, Cfg, Function};
+
+/// Dominator tree of a control-flow graph, computed with the iterative algorithm of Cooper,
+/// Harvey and Kennedy ("A Simple, Fast Dominance Algorithm").
+///
+/// Blocks that are unreachable from the entry block are not part of the tree.
+#[derive(Debug, Clone)]
+pub struct DominatorTree {
+    immediate_dominators: Vec<Option<BlockId>>,
+    children: Vec<Vec<BlockId>>,
+    /// Position of each reachable block in reverse postorder
+    order: Vec<Option<usize>>,
+}
+
+impl DominatorTree {
+    pub fn new(cfg: &Cfg) -> DominatorTree {
+        let reverse_postorder = cfg.reverse_postorder();
+        let mut order = vec![None; cfg.len()];
+        for (index, block) in reverse_postorder.iter().enumerate() {
+            order[block.0] = Some(index);
+        }
+
+        let mut immediate_dominators = vec![None; cfg.len()];
+        immediate_dominators[Function::ENTRY.0] = Some(Function::ENTRY);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &block in reverse_postorder.iter().skip(1) {
+                let mut new_idom = None;
+                for &predecessor in cfg.predecessors(block) {
+                    if immediate_dominators[predecessor.0].is_none() {
+                        continue;
+                    }
+                    new_idom = Some(match new_idom {
+                        None => predecessor,
+                        Some(current) => {
+                            intersect(&immediate_dominators, &order, predecessor, current)
+                        }
+                    });
+                }
+                if new_idom.is_some() && immediate_dominators[block.0] != new_idom {
+                    immediate_dominators[block.0] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+        // The entry block has no immediate dominator, the algorithm just uses it as a sentinel
+        immediate_dominators[Function::ENTRY.0] = None;
+
+        let mut children = vec![Vec::new(); cfg.len()];
+        for &block in &reverse_postorder {
+            if let Some(idom) = immediate_dominators[block.0] {
+                children[idom.0].push(block);
+            }
+        }
+        DominatorTree {
+            immediate_dominators,
+            children,
+            order,
+        }
+    }
+
+    /// Return the immediate dominator of the block. The entry block and unreachable blocks have
+    /// none.
+    pub fn immediate_dominator(&self, block: BlockId) -> Option<BlockId> {
+        self.immediate_dominators[block.0]
+    }
+
+    /// Return the blocks immediately dominated by the given block
+    pub fn children(&self, block: BlockId) -> &[BlockId] {
+        &self.children[block.0]
+    }
+
+    pub fn is_reachable(&self, block: BlockId) -> bool {
+        self.order[block.0].is_some()
+    }
+
+    /// Check whether every path from the entry block to `b` passes through `a`. Every reachable
+    /// block dominates itself.
+    pub fn dominates(&self, a: BlockId, b: BlockId) -> bool {
+        if !self.is_reachable(a) || !self.is_reachable(b) {
+            return false;
+        }
+        let mut current = Some(b);
+        while let Some(block) = current {
+            if block == a {
+                return true;
+            }
+            current = self.immediate_dominator(block);
+        }
+        false
+    }
+
+    /// Compute the dominance frontier of every block: the blocks where the dominance of a block
+    /// ends, i.e. which it does not strictly dominate but one of whose predecessors it dominates
+    pub fn dominance_frontiers(&self, cfg: &Cfg) -> Vec<Vec<BlockId>> {
+        let mut frontiers: Vec<Vec<BlockId>> = vec![Vec::new(); cfg.len()];
+        for block in cfg.blocks() {
+            if !self.is_reachable(block) || cfg.predecessors(block).len() < 2 {
+                continue;
+            }
+            let idom = self.immediate_dominator(block);
+            for &predecessor in cfg.predecessors(block) {
+                let mut runner = Some(predecessor);
+                while let Some(current) = runner {
+                    if Some(current) == idom || !self.is_reachable(current) {
+                        break;
+                    }
+                    if !frontiers[current.0].contains(&block) {
+                        frontiers[current.0].push(block);
+                    }
+                    runner = self.immediate_dominator(current);
+                }
+            }
+        }
+        for frontier in &mut frontiers {
+            frontier.sort();
+        }
+        frontiers
+    }
+}
+
+fn intersect(
+    immediate_dominators: &[Option<BlockId>],
+    order: &[Option<usize>],
+    mut a: BlockId,
+    mut b: BlockId,
+) -> BlockId {
+    while a != b {
+        while order[a.0] > order[b.0] {
+            a = immediate_dominators[a.0].expect("processed blocks have a dominator");
+        }
+        while order[b.0] > order[a.0] {
+            b = immediate_dominators[b.0].expect("processed blocks have a dominator");
+        }
+    }
+    a
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ir::dominators::DominatorTree;
+    use crate::ir::{lower_program, BlockId, Cfg};
+    use crate::C1Parser;
+
+    #[test]
+    fn nested_ifs() {
+        // bb0 -> bb1, bb2; bb1 -> bb3, bb4; bb3 -> bb4; bb4 -> bb2; bb5 is unreachable
+        let module = lower_program(
+            &C1Parser::parse_program(
+                "void main() { if (a) { if (b) c(); d(); } e(); return; f(); }",
+            )
+            .unwrap(),
+        );
+        let cfg = Cfg::new(&module.functions[0]);
+        let tree = DominatorTree::new(&cfg);
+
+        assert_eq!(tree.immediate_dominator(BlockId(0)), None);
+        assert_eq!(tree.immediate_dominator(BlockId(1)), Some(BlockId(0)));
+        assert_eq!(tree.immediate_dominator(BlockId(2)), Some(BlockId(0)));
+        assert_eq!(tree.immediate_dominator(BlockId(3)), Some(BlockId(1)));
+        assert_eq!(tree.immediate_dominator(BlockId(4)), Some(BlockId(1)));
+        assert_eq!(tree.immediate_dominator(BlockId(5)), None);
+        assert_eq!(tree.children(BlockId(1)), &[BlockId(3), BlockId(4)]);
+
+        assert!(tree.dominates(BlockId(0), BlockId(3)));
+        assert!(tree.dominates(BlockId(1), BlockId(1)));
+        assert!(!tree.dominates(BlockId(1), BlockId(2)));
+        assert!(!tree.dominates(BlockId(3), BlockId(4)));
+        assert!(!tree.dominates(BlockId(0), BlockId(5)));
+
+        let frontiers = tree.dominance_frontiers(&cfg);
+        assert_eq!(frontiers[0], vec![]);
+        assert_eq!(frontiers[1], vec![BlockId(2)]);
+        assert_eq!(frontiers[2], vec![]);
+        assert_eq!(frontiers[3], vec![BlockId(4)]);
+        assert_eq!(frontiers[4], vec![BlockId(2)]);
+        assert_eq!(frontiers[5], vec![]);
+    }
+}
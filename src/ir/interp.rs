@@ -0,0 +1,337 @@
+use crate::ast::BinaryOperator;
+use crate::ir::{BlockId, Constant, Function, Instruction, Module, Operand, Place, Terminator};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Maximum number of nested calls before execution is aborted
+const MAX_CALL_DEPTH: usize = 200;
+
+/// Runtime value of the interpreter
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+}
+
+/// Observable result of running a function: everything it printed and the value it returned
+#[derive(Debug, PartialEq, Clone)]
+pub struct Execution {
+    pub output: Vec<Value>,
+    pub return_value: Option<Value>,
+}
+
+/// Run the named function of the module and record its observable behaviour.
+///
+/// The interpreter is meant for testing transformations of the IR, so its semantics are simple:
+/// variables that are read before being assigned hold `0`, arithmetic on integers wraps, mixing
+/// integers and floats yields floats and booleans count as `0`/`1` in arithmetic. Conditions,
+/// `&&` and `||` treat every non-zero value as true; the IR has already evaluated both operands
+/// of `&&` and `||`. Strings, which C1 only prints, count as `0`.
+/// The function is run without arguments.
+pub fn run(module: &Module, function: &str) -> Result<Execution, String> {
+    let mut interpreter = Interpreter {
+        module,
+        output: Vec::new(),
+        depth: 0,
+    };
+    let return_value = interpreter.call(function, Vec::new())?;
+    Ok(Execution {
+        output: interpreter.output,
+        return_value,
+    })
+}
+
+struct Interpreter<'a> {
+    module: &'a Module,
+    output: Vec<Value>,
+    depth: usize,
+}
+
+impl<'a> Interpreter<'a> {
+    fn call(&mut self, name: &str, arguments: Vec<Value>) -> Result<Option<Value>, String> {
+        let function = self
+            .module
+            .function(name)
+            .ok_or_else(|| format!("call of undefined function '{}'", name))?;
+        if function.parameters.len() != arguments.len() {
+            return Err(format!(
+                "'{}' takes {} arguments but got {}",
+                name,
+                function.parameters.len(),
+                arguments.len()
+            ));
+        }
+        if self.depth == MAX_CALL_DEPTH {
+            return Err(format!("maximum call depth exceeded in '{}'", name));
+        }
+        self.depth += 1;
+        let result = self.execute(function, arguments);
+        self.depth -= 1;
+        result
+    }
+
+    fn execute(
+        &mut self,
+        function: &Function,
+        arguments: Vec<Value>,
+    ) -> Result<Option<Value>, String> {
+        let mut frame: HashMap<Place, Value> = function
+            .parameters
+            .iter()
+            .map(|parameter| Place::Variable(parameter.clone()))
+            .zip(arguments)
+            .collect();
+        let mut previous: Option<BlockId> = None;
+        let mut current = Function::ENTRY;
+        loop {
+            let block = function.block(current);
+            // All phis of a block read their arguments before any of them is written
+            let mut phi_values = Vec::new();
+            for instruction in &block.instructions {
+                if let Instruction::Phi { dest, arguments } = instruction {
+                    let argument = arguments
+                        .iter()
+                        .find(|(predecessor, _)| Some(*predecessor) == previous)
+                        .map(|(_, argument)| argument)
+                        .ok_or_else(|| {
+                            format!("phi for {} in {} has no argument", dest, current)
+                        })?;
+                    phi_values.push((dest.clone(), read(&frame, argument)));
+                }
+            }
+            frame.extend(phi_values);
+
+            for instruction in &block.instructions {
+                match instruction {
+                    Instruction::Phi { .. } => {}
+                    Instruction::Copy { dest, src } => {
+                        let value = read(&frame, src);
+                        frame.insert(dest.clone(), value);
+                    }
+                    Instruction::Negate { dest, operand } => {
+                        let value = negate(read(&frame, operand));
+                        frame.insert(dest.clone(), value);
+                    }
+                    Instruction::Binary {
+                        dest,
+                        operator,
+                        lhs,
+                        rhs,
+                    } => {
+                        let value = binary(*operator, read(&frame, lhs), read(&frame, rhs))?;
+                        frame.insert(dest.clone(), value);
+                    }
+                    Instruction::Call {
+                        dest,
+                        function,
+                        arguments,
+                    } => {
+                        let arguments = arguments
+                            .iter()
+                            .map(|argument| read(&frame, argument))
+                            .collect();
+                        let value = self.call(function, arguments)?;
+                        if let Some(dest) = dest {
+                            // Using the result of a function without return value yields 0
+                            frame.insert(dest.clone(), value.unwrap_or(Value::Int(0)));
+                        }
+                    }
+                    Instruction::Print(value) => {
+                        let value = read(&frame, value);
+                        self.output.push(value);
+                    }
+                }
+            }
+
+            previous = Some(current);
+            current = match &block.terminator {
+                Terminator::Jump(target) => *target,
+                Terminator::Branch {
+                    condition,
+                    if_true,
+                    if_false,
+                } => {
+                    if read(&frame, condition).is_true() {
+                        *if_true
+                    } else {
+                        *if_false
+                    }
+                }
+                Terminator::Return(value) => {
+                    return Ok(value.as_ref().map(|value| read(&frame, value)));
+                }
+            };
+        }
+    }
+}
+
+fn read(frame: &HashMap<Place, Value>, operand: &Operand) -> Value {
+    match operand {
+        Operand::Constant(Constant::Int(value)) => Value::Int(*value),
+        Operand::Constant(Constant::Float(value)) => Value::Float(*value),
+        Operand::Constant(Constant::Bool(value)) => Value::Bool(*value),
+        Operand::Constant(Constant::String(value)) => Value::String(value.clone()),
+        Operand::Place(place) => frame.get(place).cloned().unwrap_or(Value::Int(0)),
+    }
+}
+
+fn negate(value: Value) -> Value {
+    match value {
+        Value::Float(value) => Value::Float(-value),
+        value => Value::Int(value.as_int().wrapping_neg()),
+    }
+}
+
+fn binary(operator: BinaryOperator, lhs: Value, rhs: Value) -> Result<Value, String> {
+    use BinaryOperator::*;
+
+    if let And | Or = operator {
+        let result = match operator {
+            And => lhs.is_true() && rhs.is_true(),
+            _ => lhs.is_true() || rhs.is_true(),
+        };
+        return Ok(Value::Bool(result));
+    }
+    if let (Value::Float(_), _) | (_, Value::Float(_)) = (&lhs, &rhs) {
+        let (lhs, rhs) = (lhs.as_float(), rhs.as_float());
+        return Ok(match operator {
+            Add => Value::Float(lhs + rhs),
+            Subtract => Value::Float(lhs - rhs),
+            Multiply => Value::Float(lhs * rhs),
+            Divide => Value::Float(lhs / rhs),
+            _ => Value::Bool(compare(operator, lhs, rhs)),
+        });
+    }
+    let (lhs, rhs) = (lhs.as_int(), rhs.as_int());
+    Ok(match operator {
+        Add => Value::Int(lhs.wrapping_add(rhs)),
+        Subtract => Value::Int(lhs.wrapping_sub(rhs)),
+        Multiply => Value::Int(lhs.wrapping_mul(rhs)),
+        Divide if rhs == 0 => return Err("division by zero".to_string()),
+        Divide => Value::Int(lhs.wrapping_div(rhs)),
+        _ => Value::Bool(compare(operator, lhs, rhs)),
+    })
+}
+
+fn compare<T: PartialOrd>(operator: BinaryOperator, lhs: T, rhs: T) -> bool {
+    match operator {
+        BinaryOperator::Equal => lhs == rhs,
+        BinaryOperator::NotEqual => lhs != rhs,
+        BinaryOperator::Less => lhs < rhs,
+        BinaryOperator::Greater => lhs > rhs,
+        BinaryOperator::LessEqual => lhs <= rhs,
+        BinaryOperator::GreaterEqual => lhs >= rhs,
+        _ => unreachable!("{:?} is not a comparison", operator),
+    }
+}
+
+impl Value {
+    fn is_true(&self) -> bool {
+        match self {
+            Value::Int(value) => *value != 0,
+            Value::Float(value) => *value != 0.0,
+            Value::Bool(value) => *value,
+            Value::String(_) => false,
+        }
+    }
+
+    fn as_int(&self) -> i64 {
+        match self {
+            Value::Int(value) => *value,
+            Value::Float(value) => *value as i64,
+            Value::Bool(value) => *value as i64,
+            Value::String(_) => 0,
+        }
+    }
+
+    fn as_float(&self) -> f64 {
+        match self {
+            Value::Int(value) => *value as f64,
+            Value::Float(value) => *value,
+            Value::Bool(value) => *value as i64 as f64,
+            Value::String(_) => 0.0,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(value) => write!(f, "{}", value),
+            Value::Float(value) => write!(f, "{:?}", value),
+            Value::Bool(value) => write!(f, "{}", value),
+            Value::String(value) => f.write_str(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ir::interp::{run, Value};
+    use crate::ir::{into_ssa, lower_program};
+    use crate::{C1Parser, Dialect, ParserOptions};
+
+    #[test]
+    #[allow(clippy::approx_constant)]
+    fn runs_example_program() {
+        let program = C1Parser::parse_program(include_str!("../../tests/data/beispiel.c-1"));
+        let module = lower_program(&program.unwrap());
+        let execution = run(&module, "main").unwrap();
+        // main prints a + b, blub() and blah(); the printf in blah() is never reached
+        assert_eq!(
+            execution.output,
+            vec![Value::Int(3), Value::Int(17), Value::Float(3.14159)]
+        );
+        assert_eq!(execution.return_value, None);
+    }
+
+    #[test]
+    fn runs_c1_program() {
+        let options = ParserOptions {
+            dialect: Dialect::C1,
+            ..ParserOptions::default()
+        };
+        let text = include_str!("../../tests/data/beispiel.c1");
+        let program = C1Parser::with_options(text, options).program().unwrap();
+        let mut module = lower_program(&program);
+        let expected = vec![
+            Value::String("fib:".to_string()),
+            Value::Int(55),
+            Value::Int(34),
+            Value::Int(21),
+            Value::Int(5050),
+            Value::Int(0),
+        ];
+        assert_eq!(run(&module, "main").unwrap().output, expected);
+        for function in &mut module.functions {
+            into_ssa(function);
+        }
+        assert_eq!(run(&module, "main").unwrap().output, expected);
+        assert_eq!(
+            run(&module, "fib"),
+            Err("'fib' takes 1 arguments but got 0".to_string())
+        );
+    }
+
+    #[test]
+    fn runtime_errors() {
+        let module = lower_program(
+            &C1Parser::parse_program("int f() { return f(); } int g() { return 1 / 0; }").unwrap(),
+        );
+        assert!(run(&module, "f").unwrap_err().contains("call depth"));
+        assert_eq!(run(&module, "g"), Err("division by zero".to_string()));
+        assert!(run(&module, "h").is_err());
+    }
+
+    #[test]
+    fn logical_operators_do_not_short_circuit() {
+        let text =
+            "int g() { printf(1); return 1; } bool f() { return false && g() || true || g(); }";
+        let module = lower_program(&C1Parser::parse_program(text).unwrap());
+        let execution = run(&module, "f").unwrap();
+        assert_eq!(execution.output, vec![Value::Int(1), Value::Int(1)]);
+        assert_eq!(execution.return_value, Some(Value::Bool(true)));
+    }
+}
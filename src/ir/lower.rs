@@ -0,0 +1,463 @@
+use crate::ast::{
+    Expression, ExpressionKind, FunctionDefinition, Program, Statement, StatementKind,
+};
+use crate::ir::{
+    BasicBlock, BlockId, Constant, Function, Instruction, Module, Operand, Place, Temp, Terminator,
+};
+
+/// Lower every function definition of the program. The IR has no global variables: their
+/// declarations are dropped, so each function sees them as variables of its own.
+pub fn lower_program(program: &Program) -> Module {
+    Module {
+        functions: program.functions.iter().map(lower_function).collect(),
+    }
+}
+
+/// Lower a single function definition into basic blocks.
+///
+/// Statements following a `return` are placed in a fresh block without predecessors, so they are
+/// kept in the IR but unreachable in the control-flow graph. Falling off the end of a function
+/// returns without a value. `&&` and `||` evaluate both of their operands, unlike in C; see
+/// [`crate::ir`].
+pub fn lower_function(function: &FunctionDefinition) -> Function {
+    let mut builder = Builder::default();
+    builder.current = Some(builder.new_block());
+    for statement in &function.body {
+        builder.statement(statement);
+    }
+    if builder.current.is_some() {
+        builder.terminate(Terminator::Return(None));
+    }
+    Function {
+        name: function.name.clone(),
+        return_type: function.return_type,
+        parameters: function
+            .parameters
+            .iter()
+            .map(|parameter| parameter.name.clone())
+            .collect(),
+        blocks: builder
+            .blocks
+            .into_iter()
+            .enumerate()
+            .map(|(index, (instructions, terminator))| BasicBlock {
+                id: BlockId(index),
+                instructions,
+                terminator: terminator.expect("every block is terminated"),
+            })
+            .collect(),
+    }
+}
+
+#[derive(Default)]
+struct Builder {
+    blocks: Vec<(Vec<Instruction>, Option<Terminator>)>,
+    /// Block that receives new instructions, None directly after a terminator
+    current: Option<BlockId>,
+    next_temp: usize,
+}
+
+impl Builder {
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push((Vec::new(), None));
+        BlockId(self.blocks.len() - 1)
+    }
+
+    fn new_temp(&mut self) -> Place {
+        self.next_temp += 1;
+        Place::Temp(Temp(self.next_temp - 1))
+    }
+
+    /// Return the block receiving instructions, opening a new (unreachable) one after a terminator
+    fn current_block(&mut self) -> BlockId {
+        match self.current {
+            Some(block) => block,
+            None => {
+                let block = self.new_block();
+                self.current = Some(block);
+                block
+            }
+        }
+    }
+
+    fn emit(&mut self, instruction: Instruction) {
+        let block = self.current_block();
+        self.blocks[block.0].0.push(instruction);
+    }
+
+    fn terminate(&mut self, terminator: Terminator) {
+        let block = self.current_block();
+        self.blocks[block.0].1 = Some(terminator);
+        self.current = None;
+    }
+
+    fn switch_to(&mut self, block: BlockId) {
+        self.current = Some(block);
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match &statement.kind {
+            StatementKind::Block(statements) => {
+                for statement in statements {
+                    self.statement(statement);
+                }
+            }
+            StatementKind::If {
+                condition,
+                body,
+                else_body,
+            } => {
+                let condition = self.expression(condition);
+                let then_block = self.new_block();
+                let else_block = else_body.as_ref().map(|_| self.new_block());
+                let join_block = self.new_block();
+                self.terminate(Terminator::Branch {
+                    condition,
+                    if_true: then_block,
+                    if_false: else_block.unwrap_or(join_block),
+                });
+                self.switch_to(then_block);
+                self.statement(body);
+                self.jump(join_block);
+                if let (Some(else_block), Some(else_body)) = (else_block, else_body) {
+                    self.switch_to(else_block);
+                    self.statement(else_body);
+                    self.jump(join_block);
+                }
+                self.switch_to(join_block);
+            }
+            StatementKind::While { condition, body } => self.emit_loop(condition, body, None),
+            StatementKind::DoWhile { body, condition } => {
+                let body_block = self.new_block();
+                self.jump(body_block);
+                self.switch_to(body_block);
+                self.statement(body);
+                let condition = self.expression(condition);
+                let exit_block = self.new_block();
+                self.terminate(Terminator::Branch {
+                    condition,
+                    if_true: body_block,
+                    if_false: exit_block,
+                });
+                self.switch_to(exit_block);
+            }
+            StatementKind::For {
+                init,
+                condition,
+                step,
+                body,
+            } => {
+                self.statement(init);
+                self.emit_loop(condition, body, Some(step));
+            }
+            StatementKind::Return(value) => {
+                let value = value.as_ref().map(|value| self.expression(value));
+                self.terminate(Terminator::Return(value));
+            }
+            StatementKind::Printf(value) => {
+                let value = self.expression(value);
+                self.emit(Instruction::Print(value));
+            }
+            StatementKind::Declaration(declaration) => {
+                if let Some(value) = &declaration.value {
+                    self.expression_into(value, Place::Variable(declaration.name.clone()));
+                }
+            }
+            StatementKind::Assignment { target, value } => {
+                self.expression_into(value, Place::Variable(target.clone()));
+            }
+            StatementKind::Call {
+                function,
+                arguments,
+            } => {
+                let arguments = self.arguments(arguments);
+                self.emit(Instruction::Call {
+                    dest: None,
+                    function: function.clone(),
+                    arguments,
+                });
+            }
+        }
+    }
+
+    /// Jump to the block, unless control never gets here
+    fn jump(&mut self, target: BlockId) {
+        if self.current.is_some() {
+            self.terminate(Terminator::Jump(target));
+        }
+    }
+
+    /// Lower a loop that checks the condition before each iteration, with the step of a for
+    /// loop executed after the body
+    fn emit_loop(&mut self, condition: &Expression, body: &Statement, step: Option<&Statement>) {
+        let header_block = self.new_block();
+        self.jump(header_block);
+        self.switch_to(header_block);
+        let condition = self.expression(condition);
+        let body_block = self.new_block();
+        let exit_block = self.new_block();
+        self.terminate(Terminator::Branch {
+            condition,
+            if_true: body_block,
+            if_false: exit_block,
+        });
+        self.switch_to(body_block);
+        self.statement(body);
+        if let Some(step) = step {
+            self.statement(step);
+        }
+        self.jump(header_block);
+        self.switch_to(exit_block);
+    }
+
+    /// Lower the arguments of a call from left to right
+    fn arguments(&mut self, arguments: &[Expression]) -> Vec<Operand> {
+        let mut operands = Vec::new();
+        for (index, argument) in arguments.iter().enumerate() {
+            let mut operand = self.expression(argument);
+            // Like the left operand of a binary expression
+            let rest = &arguments[index + 1..];
+            if matches!(operand, Operand::Place(Place::Variable(_)))
+                && rest.iter().any(contains_assignment)
+            {
+                let temp = self.new_temp();
+                self.emit(Instruction::Copy {
+                    dest: temp.clone(),
+                    src: operand,
+                });
+                operand = Operand::Place(temp);
+            }
+            operands.push(operand);
+        }
+        operands
+    }
+
+    /// Lower the expression and return the operand holding its value
+    fn expression(&mut self, expression: &Expression) -> Operand {
+        match &expression.kind {
+            ExpressionKind::Int(value) => Operand::Constant(Constant::Int(*value)),
+            ExpressionKind::IntOutOfRange(_) => Operand::Constant(Constant::Int(i64::MAX)),
+            ExpressionKind::Float(value) => Operand::Constant(Constant::Float(*value)),
+            ExpressionKind::Bool(value) => Operand::Constant(Constant::Bool(*value)),
+            ExpressionKind::String(value) => Operand::Constant(Constant::String(value.clone())),
+            ExpressionKind::Variable(name) => Operand::Place(Place::Variable(name.clone())),
+            ExpressionKind::Assign { target, value } => {
+                let dest = Place::Variable(target.clone());
+                self.expression_into(value, dest.clone());
+                Operand::Place(dest)
+            }
+            _ => {
+                let dest = self.new_temp();
+                self.expression_into(expression, dest.clone());
+                Operand::Place(dest)
+            }
+        }
+    }
+
+    /// Lower the expression so that its value ends up in dest
+    fn expression_into(&mut self, expression: &Expression, dest: Place) {
+        match &expression.kind {
+            ExpressionKind::Binary { operator, lhs, rhs } => {
+                let mut lhs = self.expression(lhs);
+                // An assignment in the right operand must not change the value of the left one
+                if matches!(lhs, Operand::Place(Place::Variable(_))) && contains_assignment(rhs) {
+                    let temp = self.new_temp();
+                    self.emit(Instruction::Copy {
+                        dest: temp.clone(),
+                        src: lhs,
+                    });
+                    lhs = Operand::Place(temp);
+                }
+                let rhs = self.expression(rhs);
+                self.emit(Instruction::Binary {
+                    dest,
+                    operator: *operator,
+                    lhs,
+                    rhs,
+                });
+            }
+            ExpressionKind::Negate(operand) => {
+                let operand = self.expression(operand);
+                self.emit(Instruction::Negate { dest, operand });
+            }
+            ExpressionKind::Call {
+                function,
+                arguments,
+            } => {
+                let arguments = self.arguments(arguments);
+                self.emit(Instruction::Call {
+                    dest: Some(dest),
+                    function: function.clone(),
+                    arguments,
+                });
+            }
+            _ => {
+                let src = self.expression(expression);
+                self.emit(Instruction::Copy { dest, src });
+            }
+        }
+    }
+}
+
+fn contains_assignment(expression: &Expression) -> bool {
+    match &expression.kind {
+        ExpressionKind::Assign { .. } => true,
+        ExpressionKind::Binary { lhs, rhs, .. } => {
+            contains_assignment(lhs) || contains_assignment(rhs)
+        }
+        ExpressionKind::Negate(operand) => contains_assignment(operand),
+        ExpressionKind::Call { arguments, .. } => arguments.iter().any(contains_assignment),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ir::lower_program;
+    use crate::{C1Parser, Dialect, ParserOptions};
+
+    fn dump(text: &str) -> String {
+        lower_program(&C1Parser::parse_program(text).unwrap()).to_string()
+    }
+
+    #[test]
+    fn straight_line_code() {
+        assert_eq!(
+            dump("int blub() { blub1 = 23; blub4 = blub1 * (blub2 + 1.0); return blub4; }"),
+            "int blub() {\n\
+             bb0:\n    \
+                 blub1 = 23\n    \
+                 t0 = blub2 + 1.0\n    \
+                 blub4 = blub1 * t0\n    \
+                 return blub4\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn if_statement_creates_blocks() {
+        assert_eq!(
+            dump("void main() { a = 1; if (a <= 2) printf(a); foo(); }"),
+            "void main() {\n\
+             bb0:\n    \
+                 a = 1\n    \
+                 t0 = a <= 2\n    \
+                 branch t0, bb1, bb2\n\
+             bb1:\n    \
+                 print a\n    \
+                 jump bb2\n\
+             bb2:\n    \
+                 call foo\n    \
+                 return\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn statements_after_return_get_own_block() {
+        assert_eq!(
+            dump("int f() { if (true) { return 1; } return 2; x = 3; }"),
+            "int f() {\n\
+             bb0:\n    \
+                 branch true, bb1, bb2\n\
+             bb1:\n    \
+                 return 1\n\
+             bb2:\n    \
+                 return 2\n\
+             bb3:\n    \
+                 x = 3\n    \
+                 return\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn nested_assignments_and_calls() {
+        assert_eq!(
+            dump("float f() { x = y = -g(); printf(x + (x = 2)); }"),
+            "float f() {\n\
+             bb0:\n    \
+                 t0 = call g\n    \
+                 y = -t0\n    \
+                 x = y\n    \
+                 t2 = x\n    \
+                 x = 2\n    \
+                 t1 = t2 + x\n    \
+                 print t1\n    \
+                 return\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn functions_are_separated() {
+        assert_eq!(
+            dump("void a() {} bool b() { return true || false; }"),
+            "void a() {\n\
+             bb0:\n    \
+                 return\n\
+             }\n\
+             \n\
+             bool b() {\n\
+             bb0:\n    \
+                 t0 = true || false\n    \
+                 return t0\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn logical_operators_evaluate_both_operands() {
+        assert_eq!(
+            dump("bool f() { return false && g(); }"),
+            "bool f() {\n\
+             bb0:\n    \
+                 t1 = call g\n    \
+                 t0 = false && t1\n    \
+                 return t0\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn c1_control_flow() {
+        let options = ParserOptions {
+            dialect: Dialect::C1,
+            ..ParserOptions::default()
+        };
+        let text = "int f(int n) {\n\
+                    for (int i = 0; i < n; i = i + 1) if (i) g(i, n); else printf(\"-\");\n\
+                    do n = n - 1; while (n);\n\
+                    }";
+        let program = C1Parser::with_options(text, options).program().unwrap();
+        assert_eq!(
+            lower_program(&program).to_string(),
+            "int f(n) {\n\
+             bb0:\n    \
+                 i = 0\n    \
+                 jump bb1\n\
+             bb1:\n    \
+                 t0 = i < n\n    \
+                 branch t0, bb2, bb3\n\
+             bb2:\n    \
+                 branch i, bb4, bb5\n\
+             bb3:\n    \
+                 jump bb7\n\
+             bb4:\n    \
+                 call g(i, n)\n    \
+                 jump bb6\n\
+             bb5:\n    \
+                 print \"-\"\n    \
+                 jump bb6\n\
+             bb6:\n    \
+                 i = i + 1\n    \
+                 jump bb1\n\
+             bb7:\n    \
+                 n = n - 1\n    \
+                 branch n, bb7, bb8\n\
+             bb8:\n    \
+                 return\n\
+             }\n"
+        );
+    }
+}
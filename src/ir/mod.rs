@@ -0,0 +1,411 @@
+//! Three-address code (TAC) intermediate representation.
+//!
+//! Every function is lowered into a list of labelled basic blocks. A basic block is a sequence of
+//! instructions with at most one operator each, followed by exactly one terminator that transfers
+//! control to other blocks or returns from the function. The `Display` implementations produce
+//! the human-readable dump format:
+//! ```text
+//! int blub() {
+//! bb0:
+//!     blub1 = 23
+//!     t0 = blub1 < 42
+//!     branch t0, bb1, bb2
+//! bb1:
+//!     return blub1
+//! bb2:
+//!     return 42
+//! }
+//! ```
+//!
+//! Unlike in C, `&&` and `||` do not short-circuit: they are binary instructions like `+`, so
+//! both operands are evaluated, including calls and assignments in the right operand when the
+//! left one already decides the result. The interpreter and the dead assignment warnings of
+//! [`crate::warnings`] assume the same.
+use crate::ast::{BinaryOperator, Type};
+use std::fmt;
+
+mod cfg;
+mod dominators;
+pub mod interp;
+mod lower;
+mod ssa;
+
+pub use cfg::Cfg;
+pub use dominators::DominatorTree;
+pub use lower::{lower_function, lower_program};
+pub use ssa::{into_ssa, out_of_ssa};
+
+/// Label of a basic block, which is also its index in `Function::blocks`
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
+pub struct BlockId(pub usize);
+
+/// Compiler generated temporary. Every temporary is assigned exactly once.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
+pub struct Temp(pub usize);
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Constant {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    /// Only printed
+    String(String),
+}
+
+/// Something that can be assigned to
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum Place {
+    Temp(Temp),
+    Variable(String),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Operand {
+    Place(Place),
+    Constant(Constant),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Instruction {
+    /// dest = src
+    Copy { dest: Place, src: Operand },
+    /// dest = -operand
+    Negate { dest: Place, operand: Operand },
+    /// dest = lhs op rhs
+    Binary {
+        dest: Place,
+        operator: BinaryOperator,
+        lhs: Operand,
+        rhs: Operand,
+    },
+    /// (dest =)? call function(arguments)
+    Call {
+        dest: Option<Place>,
+        function: String,
+        arguments: Vec<Operand>,
+    },
+    /// print value
+    Print(Operand),
+    /// dest = phi [block: operand, ...], selects the operand of the predecessor control came
+    /// from. Phis only occur in SSA form and always precede the other instructions of a block.
+    Phi {
+        dest: Place,
+        arguments: Vec<(BlockId, Operand)>,
+    },
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Terminator {
+    Jump(BlockId),
+    Branch {
+        condition: Operand,
+        if_true: BlockId,
+        if_false: BlockId,
+    },
+    Return(Option<Operand>),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct BasicBlock {
+    pub id: BlockId,
+    pub instructions: Vec<Instruction>,
+    pub terminator: Terminator,
+}
+
+/// A lowered function. The entry block is always `bb0`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Function {
+    pub name: String,
+    pub return_type: Type,
+    /// The variables holding the arguments on entry
+    pub parameters: Vec<String>,
+    pub blocks: Vec<BasicBlock>,
+}
+
+/// All lowered functions of a program
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Module {
+    pub functions: Vec<Function>,
+}
+
+impl Function {
+    /// The block every execution of the function starts in
+    pub const ENTRY: BlockId = BlockId(0);
+
+    pub fn block(&self, id: BlockId) -> &BasicBlock {
+        &self.blocks[id.0]
+    }
+
+    pub fn block_mut(&mut self, id: BlockId) -> &mut BasicBlock {
+        &mut self.blocks[id.0]
+    }
+}
+
+impl Module {
+    /// Return the function with the given name, if there is one
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+}
+
+impl Terminator {
+    /// Return the operand read by the terminator, if any
+    pub fn operand(&self) -> Option<&Operand> {
+        match self {
+            Terminator::Jump(_) => None,
+            Terminator::Branch { condition, .. } => Some(condition),
+            Terminator::Return(value) => value.as_ref(),
+        }
+    }
+
+    /// Return a mutable reference to the operand read by the terminator, if any
+    pub fn operand_mut(&mut self) -> Option<&mut Operand> {
+        match self {
+            Terminator::Jump(_) => None,
+            Terminator::Branch { condition, .. } => Some(condition),
+            Terminator::Return(value) => value.as_mut(),
+        }
+    }
+
+    /// Redirect every edge to `from` so that it leads to `to` instead
+    pub fn retarget(&mut self, from: BlockId, to: BlockId) {
+        match self {
+            Terminator::Jump(target) if *target == from => *target = to,
+            Terminator::Branch {
+                if_true, if_false, ..
+            } => {
+                if *if_true == from {
+                    *if_true = to;
+                }
+                if *if_false == from {
+                    *if_false = to;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Return the blocks control may be transferred to, without duplicates
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![*target],
+            Terminator::Branch {
+                if_true, if_false, ..
+            } if if_true == if_false => vec![*if_true],
+            Terminator::Branch {
+                if_true, if_false, ..
+            } => vec![*if_true, *if_false],
+            Terminator::Return(_) => vec![],
+        }
+    }
+}
+
+impl Instruction {
+    /// Return the place written by the instruction, if any
+    pub fn dest(&self) -> Option<&Place> {
+        match self {
+            Instruction::Copy { dest, .. }
+            | Instruction::Negate { dest, .. }
+            | Instruction::Binary { dest, .. }
+            | Instruction::Phi { dest, .. } => Some(dest),
+            Instruction::Call { dest, .. } => dest.as_ref(),
+            Instruction::Print(_) => None,
+        }
+    }
+
+    /// Return the operands read by the instruction
+    pub fn operands(&self) -> Vec<&Operand> {
+        match self {
+            Instruction::Copy { src, .. } => vec![src],
+            Instruction::Negate { operand, .. } => vec![operand],
+            Instruction::Binary { lhs, rhs, .. } => vec![lhs, rhs],
+            Instruction::Call { arguments, .. } => arguments.iter().collect(),
+            Instruction::Print(value) => vec![value],
+            Instruction::Phi { arguments, .. } => {
+                arguments.iter().map(|(_, argument)| argument).collect()
+            }
+        }
+    }
+
+    /// Return mutable references to the operands read by the instruction
+    pub fn operands_mut(&mut self) -> Vec<&mut Operand> {
+        match self {
+            Instruction::Copy { src, .. } => vec![src],
+            Instruction::Negate { operand, .. } => vec![operand],
+            Instruction::Binary { lhs, rhs, .. } => vec![lhs, rhs],
+            Instruction::Call { arguments, .. } => arguments.iter_mut().collect(),
+            Instruction::Print(value) => vec![value],
+            Instruction::Phi { arguments, .. } => {
+                arguments.iter_mut().map(|(_, argument)| argument).collect()
+            }
+        }
+    }
+
+    /// Return a mutable reference to the place written by the instruction, if any
+    pub fn dest_mut(&mut self) -> Option<&mut Place> {
+        match self {
+            Instruction::Copy { dest, .. }
+            | Instruction::Negate { dest, .. }
+            | Instruction::Binary { dest, .. }
+            | Instruction::Phi { dest, .. } => Some(dest),
+            Instruction::Call { dest, .. } => dest.as_mut(),
+            Instruction::Print(_) => None,
+        }
+    }
+
+    pub fn is_phi(&self) -> bool {
+        matches!(self, Instruction::Phi { .. })
+    }
+}
+
+impl From<Place> for Operand {
+    fn from(place: Place) -> Self {
+        Operand::Place(place)
+    }
+}
+
+impl From<Temp> for Place {
+    fn from(temp: Temp) -> Self {
+        Place::Temp(temp)
+    }
+}
+
+impl fmt::Display for BlockId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "bb{}", self.0)
+    }
+}
+
+impl fmt::Display for Temp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "t{}", self.0)
+    }
+}
+
+impl fmt::Display for Constant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Constant::Int(value) => write!(f, "{}", value),
+            // Debug formatting keeps the decimal point of whole numbers, e.g. 1.0
+            Constant::Float(value) => write!(f, "{:?}", value),
+            Constant::Bool(value) => write!(f, "{}", value),
+            Constant::String(value) => write!(f, "\"{}\"", value),
+        }
+    }
+}
+
+impl fmt::Display for Place {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Place::Temp(temp) => write!(f, "{}", temp),
+            Place::Variable(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Place(place) => write!(f, "{}", place),
+            Operand::Constant(constant) => write!(f, "{}", constant),
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instruction::Copy { dest, src } => write!(f, "{} = {}", dest, src),
+            Instruction::Negate { dest, operand } => write!(f, "{} = -{}", dest, operand),
+            Instruction::Binary {
+                dest,
+                operator,
+                lhs,
+                rhs,
+            } => write!(f, "{} = {} {} {}", dest, lhs, operator.symbol(), rhs),
+            Instruction::Call {
+                dest,
+                function,
+                arguments,
+            } => {
+                if let Some(dest) = dest {
+                    write!(f, "{} = ", dest)?;
+                }
+                write!(f, "call {}", function)?;
+                // Calls without arguments are written without parentheses
+                for (index, argument) in arguments.iter().enumerate() {
+                    let separator = if index == 0 { "(" } else { ", " };
+                    write!(f, "{}{}", separator, argument)?;
+                }
+                if !arguments.is_empty() {
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+            Instruction::Print(value) => write!(f, "print {}", value),
+            Instruction::Phi { dest, arguments } => {
+                write!(f, "{} = phi [", dest)?;
+                for (index, (block, argument)) in arguments.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", block, argument)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Terminator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Terminator::Jump(target) => write!(f, "jump {}", target),
+            Terminator::Branch {
+                condition,
+                if_true,
+                if_false,
+            } => write!(f, "branch {}, {}, {}", condition, if_true, if_false),
+            Terminator::Return(None) => write!(f, "return"),
+            Terminator::Return(Some(value)) => write!(f, "return {}", value),
+        }
+    }
+}
+
+impl fmt::Display for BasicBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}:", self.id)?;
+        for instruction in &self.instructions {
+            writeln!(f, "    {}", instruction)?;
+        }
+        writeln!(f, "    {}", self.terminator)
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} {}({}) {{",
+            self.return_type.keyword(),
+            self.name,
+            self.parameters.join(", ")
+        )?;
+        for block in &self.blocks {
+            write!(f, "{}", block)?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+impl fmt::Display for Module {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, function) in self.functions.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", function)?;
+        }
+        Ok(())
+    }
+}
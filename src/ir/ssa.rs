@@ -0,0 +1,412 @@
+//! Conversion into and out of static single assignment (SSA) form.
+//!
+//! In SSA form every variable is assigned exactly once. Each assignment to a source variable `x`
+//! defines a new version `x.1`, `x.2`, ... and phi instructions merge the versions reaching a
+//! block from different predecessors. `x.0` is the (unassigned) value of `x` on function entry,
+//! for a parameter the argument.
+use crate::ir::{
+    BasicBlock, BlockId, Cfg, DominatorTree, Function, Instruction, Operand, Place, Temp,
+    Terminator,
+};
+use std::collections::{HashMap, HashSet};
+
+/// Transform the function into SSA form using the algorithm of Cytron et al.
+///
+/// Phis are only placed for variables that are live across blocks (semi-pruned SSA). Blocks that
+/// are unreachable from the entry block are left unchanged.
+pub fn into_ssa(function: &mut Function) {
+    let cfg = Cfg::new(function);
+    let tree = DominatorTree::new(&cfg);
+    let frontiers = tree.dominance_frontiers(&cfg);
+    let reachable = cfg.reachable();
+
+    // Variables read before being assigned in some block, and the blocks assigning each variable
+    let mut global_variables = HashSet::new();
+    let mut definitions: HashMap<String, Vec<BlockId>> = HashMap::new();
+    for block in function.blocks.iter().filter(|block| reachable[block.id.0]) {
+        let mut defined = HashSet::new();
+        for instruction in &block.instructions {
+            for operand in instruction.operands() {
+                if let Operand::Place(Place::Variable(name)) = operand {
+                    if !defined.contains(name) {
+                        global_variables.insert(name.clone());
+                    }
+                }
+            }
+            if let Some(Place::Variable(name)) = instruction.dest() {
+                defined.insert(name.clone());
+                definitions.entry(name.clone()).or_default().push(block.id);
+            }
+        }
+        if let Some(Operand::Place(Place::Variable(name))) = block.terminator.operand() {
+            if !defined.contains(name) {
+                global_variables.insert(name.clone());
+            }
+        }
+    }
+
+    // Insert phis at the iterated dominance frontier of the definitions. Sorting keeps the order
+    // of the phis, and thereby the version numbers, deterministic.
+    let mut phi_variables: Vec<Vec<String>> = vec![Vec::new(); function.blocks.len()];
+    let mut names: Vec<&String> = global_variables.iter().collect();
+    names.sort();
+    for name in names {
+        let mut worklist = definitions.get(name).cloned().unwrap_or_default();
+        let mut has_phi = HashSet::new();
+        while let Some(block) = worklist.pop() {
+            for &frontier in &frontiers[block.0] {
+                if has_phi.insert(frontier) {
+                    phi_variables[frontier.0].push(name.clone());
+                    worklist.push(frontier);
+                }
+            }
+        }
+    }
+    for block in &mut function.blocks {
+        let phis = phi_variables[block.id.0]
+            .iter()
+            .map(|name| Instruction::Phi {
+                dest: Place::Variable(name.clone()),
+                arguments: cfg
+                    .predecessors(block.id)
+                    .iter()
+                    .filter(|predecessor| reachable[predecessor.0])
+                    .map(|&predecessor| {
+                        (predecessor, Operand::Place(Place::Variable(name.clone())))
+                    })
+                    .collect(),
+            });
+        block.instructions.splice(0..0, phis);
+    }
+
+    let mut renamer = Renamer {
+        cfg: &cfg,
+        tree: &tree,
+        phi_variables: &phi_variables,
+        versions: HashMap::new(),
+        stacks: HashMap::new(),
+    };
+    renamer.rename(function, Function::ENTRY);
+    for parameter in &mut function.parameters {
+        *parameter = format!("{}.0", parameter);
+    }
+}
+
+struct Renamer<'a> {
+    cfg: &'a Cfg,
+    tree: &'a DominatorTree,
+    /// Source variable of each phi, indexed by block and position
+    phi_variables: &'a [Vec<String>],
+    /// Last version handed out per variable
+    versions: HashMap<String, usize>,
+    /// Versions visible at the current block, innermost definition last
+    stacks: HashMap<String, Vec<String>>,
+}
+
+impl<'a> Renamer<'a> {
+    fn current(&self, name: &str) -> String {
+        match self.stacks.get(name).and_then(|stack| stack.last()) {
+            Some(version) => version.clone(),
+            None => format!("{}.0", name),
+        }
+    }
+
+    fn rename_operand(&self, operand: &mut Operand) {
+        if let Operand::Place(Place::Variable(name)) = operand {
+            *name = self.current(name);
+        }
+    }
+
+    fn rename(&mut self, function: &mut Function, block: BlockId) {
+        let mut pushed = Vec::new();
+        let phi_count = self.phi_variables[block.0].len();
+        for (index, instruction) in function
+            .block_mut(block)
+            .instructions
+            .iter_mut()
+            .enumerate()
+        {
+            if index >= phi_count {
+                for operand in instruction.operands_mut() {
+                    self.rename_operand(operand);
+                }
+            }
+            if let Some(Place::Variable(name)) = instruction.dest_mut() {
+                let version = self.versions.entry(name.clone()).or_default();
+                *version += 1;
+                let versioned = format!("{}.{}", name, version);
+                self.stacks
+                    .entry(name.clone())
+                    .or_default()
+                    .push(versioned.clone());
+                pushed.push(name.clone());
+                *name = versioned;
+            }
+        }
+        if let Some(operand) = function.block_mut(block).terminator.operand_mut() {
+            self.rename_operand(operand);
+        }
+
+        for &successor in self.cfg.successors(block) {
+            for (index, name) in self.phi_variables[successor.0].iter().enumerate() {
+                let current = self.current(name);
+                if let Instruction::Phi { arguments, .. } =
+                    &mut function.block_mut(successor).instructions[index]
+                {
+                    for (predecessor, argument) in arguments.iter_mut() {
+                        if *predecessor == block {
+                            *argument = Operand::Place(Place::Variable(current.clone()));
+                        }
+                    }
+                }
+            }
+        }
+
+        for &child in self.tree.children(block) {
+            self.rename(function, child);
+        }
+        for name in pushed {
+            self.stacks.get_mut(&name).map(Vec::pop);
+        }
+    }
+}
+
+/// Replace the phis of a function in SSA form by copies at the end of the predecessors.
+///
+/// Critical edges (from a block with several successors to a block with phis) are split first,
+/// so that the copies only execute on the edge they belong to. The phis of a block are parallel
+/// assignments; if one of them reads the result of another, the copies go through temporaries.
+pub fn out_of_ssa(function: &mut Function) {
+    let cfg = Cfg::new(function);
+    let mut next_temp = next_free_temp(function);
+    for block in cfg.blocks() {
+        let phis: Vec<(Place, Vec<(BlockId, Operand)>)> = function
+            .block(block)
+            .instructions
+            .iter()
+            .filter_map(|instruction| match instruction {
+                Instruction::Phi { dest, arguments } => Some((dest.clone(), arguments.clone())),
+                _ => None,
+            })
+            .collect();
+        if phis.is_empty() {
+            continue;
+        }
+        function
+            .block_mut(block)
+            .instructions
+            .retain(|instruction| !instruction.is_phi());
+
+        let dests: HashSet<&Place> = phis.iter().map(|(dest, _)| dest).collect();
+        for &predecessor in cfg.predecessors(block) {
+            let copies: Vec<(Place, Operand)> = phis
+                .iter()
+                .filter_map(|(dest, arguments)| {
+                    arguments
+                        .iter()
+                        .find(|(from, _)| *from == predecessor)
+                        .map(|(_, argument)| (dest.clone(), argument.clone()))
+                })
+                .collect();
+            if copies.is_empty() {
+                continue;
+            }
+
+            let target = if cfg.successors(predecessor).len() > 1 {
+                let split = BlockId(function.blocks.len());
+                function.blocks.push(BasicBlock {
+                    id: split,
+                    instructions: Vec::new(),
+                    terminator: Terminator::Jump(block),
+                });
+                function
+                    .block_mut(predecessor)
+                    .terminator
+                    .retarget(block, split);
+                split
+            } else {
+                predecessor
+            };
+
+            let reads_dest = copies.iter().any(|(_, argument)| match argument {
+                Operand::Place(place) => dests.contains(place),
+                Operand::Constant(_) => false,
+            });
+            let instructions = &mut function.block_mut(target).instructions;
+            if reads_dest {
+                let temps: Vec<Place> = copies
+                    .iter()
+                    .map(|(_, argument)| {
+                        let temp = Place::Temp(Temp(next_temp));
+                        next_temp += 1;
+                        instructions.push(Instruction::Copy {
+                            dest: temp.clone(),
+                            src: argument.clone(),
+                        });
+                        temp
+                    })
+                    .collect();
+                for ((dest, _), temp) in copies.into_iter().zip(temps) {
+                    instructions.push(Instruction::Copy {
+                        dest,
+                        src: Operand::Place(temp),
+                    });
+                }
+            } else {
+                for (dest, src) in copies {
+                    instructions.push(Instruction::Copy { dest, src });
+                }
+            }
+        }
+    }
+}
+
+fn next_free_temp(function: &Function) -> usize {
+    function
+        .blocks
+        .iter()
+        .flat_map(|block| &block.instructions)
+        .filter_map(|instruction| match instruction.dest() {
+            Some(Place::Temp(Temp(index))) => Some(index + 1),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ir::interp::run;
+    use crate::ir::{into_ssa, lower_program, out_of_ssa, Cfg, Module, Place};
+    use crate::C1Parser;
+    use std::collections::HashSet;
+
+    fn lower(text: &str) -> Module {
+        lower_program(&C1Parser::parse_program(text).unwrap())
+    }
+
+    fn transform(module: &Module, transformation: fn(&mut Module)) -> Module {
+        let mut module = module.clone();
+        transformation(&mut module);
+        module
+    }
+
+    fn to_ssa(module: &mut Module) {
+        module.functions.iter_mut().for_each(into_ssa);
+    }
+
+    fn through_ssa(module: &mut Module) {
+        to_ssa(module);
+        module.functions.iter_mut().for_each(out_of_ssa);
+    }
+
+    #[test]
+    fn phi_for_variable_assigned_in_branch() {
+        let module = transform(
+            &lower("void main() { a = 1; if (b) { a = a + 1; c = 2; } printf(a); }"),
+            to_ssa,
+        );
+        assert_eq!(
+            module.to_string(),
+            "void main() {\n\
+             bb0:\n    \
+                 a.1 = 1\n    \
+                 branch b.0, bb1, bb2\n\
+             bb1:\n    \
+                 a.2 = a.1 + 1\n    \
+                 c.1 = 2\n    \
+                 jump bb2\n\
+             bb2:\n    \
+                 a.3 = phi [bb0: a.1, bb1: a.2]\n    \
+                 print a.3\n    \
+                 return\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn out_of_ssa_splits_critical_edges() {
+        let module = transform(
+            &lower("void main() { a = 1; if (b) { a = 2; } printf(a); }"),
+            through_ssa,
+        );
+        assert_eq!(
+            module.to_string(),
+            "void main() {\n\
+             bb0:\n    \
+                 a.1 = 1\n    \
+                 branch b.0, bb1, bb3\n\
+             bb1:\n    \
+                 a.2 = 2\n    \
+                 a.3 = a.2\n    \
+                 jump bb2\n\
+             bb2:\n    \
+                 print a.3\n    \
+                 return\n\
+             bb3:\n    \
+                 a.3 = a.1\n    \
+                 jump bb2\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn every_place_is_assigned_once() {
+        let module = transform(
+            &lower(include_str!("../../tests/data/beispiel.c-1")),
+            to_ssa,
+        );
+        for function in &module.functions {
+            let reachable = Cfg::new(function).reachable();
+            let mut assigned: HashSet<&Place> = HashSet::new();
+            for block in function.blocks.iter().filter(|block| reachable[block.id.0]) {
+                for dest in block.instructions.iter().filter_map(|i| i.dest()) {
+                    assert!(assigned.insert(dest), "{} assigned twice", dest);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn transformations_preserve_results() {
+        let programs = [
+            include_str!("../../tests/data/beispiel.c-1"),
+            "int main() {
+                a = 1; b = 2; c = 3;
+                if (a < b) { a = b + 1; if (a == 3) { b = a * 2; } c = a + b; }
+                if (c > 100) { c = 0; a = 0; }
+                printf(a); printf(b); printf(c);
+                if (x = a) { x = x + (x = 10); }
+                printf(x);
+                return a + b + c;
+            }",
+            "float main() {
+                x = -1.5;
+                if (f()) x = x * f();
+                printf(x);
+                if (g() == 0) { return x; y = 2; }
+                return y;
+            }
+            int f() { n = n + 1; if (n) return 2; return 3; }
+            int g() { return 0; }",
+            "bool main() {
+                if (true) { if (false) { a = 1; } e = 2; }
+                return (a == 0) && (e == 2) || false;
+            }",
+        ];
+        for text in programs {
+            let module = lower(text);
+            let expected = run(&module, "main").unwrap();
+            assert_eq!(
+                run(&transform(&module, to_ssa), "main"),
+                Ok(expected.clone())
+            );
+            assert_eq!(
+                run(&transform(&module, through_ssa), "main"),
+                Ok(expected),
+                "{}",
+                text
+            );
+        }
+    }
+}
@@ -1,6 +1,11 @@
 use logos::{Lexer, Logos};
+use std::collections::VecDeque;
+use std::fmt;
+use std::ops::Range;
 
-#[derive(Logos, Debug, PartialEq, Copy, Clone)]
+#[derive(Logos, Debug, PartialEq, Eq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum C1Token {
     #[token("bool")]
     KwBoolean,
@@ -120,15 +125,15 @@ pub enum C1Token {
     #[regex("[a-zA-Z]+[0-9a-zA-Z]*")]
     Identifier,
 
-    #[regex(r"/\*[^\*/]*\*/", logos::skip)]
+    // Comments, whitespace and linebreaks are trivia. C1Lexer skips them unless it is created
+    // with C1Lexer::with_trivia.
+    #[regex(r"/\*[^\*/]*\*/")]
     CComment,
 
-    #[regex("//[^\n]*(\n)?", logos::skip)]
+    #[regex("//[^\n]*(\n)?")]
     CPPComment,
 
-    // We can also use this variant to define whitespace,
-    // or any other matches we wish to skip.
-    #[regex(r"[ \t\f]+", logos::skip)]
+    #[regex(r"[ \t\f]+")]
     Whitespace,
 
     #[regex(r"(\r)?[\n]")]
@@ -140,10 +145,21 @@ pub enum C1Token {
     Error,
 }
 
+impl C1Token {
+    /// Check whether the token is a comment, whitespace or a linebreak
+    pub fn is_trivia(self) -> bool {
+        matches!(
+            self,
+            C1Token::CComment | C1Token::CPPComment | C1Token::Whitespace | C1Token::Linebreak
+        )
+    }
+}
+
 /// # Overview
 /// Extended lexer based on the logos crate. The lexer keeps track of the current token and the next token
 /// in the lexed text. Furthermore, the lexer keeps track of the line number in which each token is
-/// located, and of the text associated with each token.
+/// located, and of the text associated with each token. Tokens further ahead are available through
+/// [`C1Lexer::peek_nth`], and the lexer is an iterator over the remaining tokens.
 ///
 /// # Examples
 /// ```
@@ -177,21 +193,79 @@ pub enum C1Token {
 pub struct C1Lexer<'a> {
     logos_lexer: Lexer<'a, C1Token>,
     logos_line_number: usize,
-    current_token: Option<TokenData<'a>>,
-    peek_token: Option<TokenData<'a>>,
+    /// Byte offset of a position on the current line and its column, to count columns from
+    column_offset: usize,
+    column: usize,
+    /// Previously lexed tokens that are handed out instead of running the logos lexer
+    replay: Option<std::slice::Iter<'a, Token>>,
+    /// Whether comments, whitespace and linebreaks are handed out instead of being skipped
+    trivia: bool,
+    /// Ring buffer of the tokens lexed ahead, starting with the current token. It holds at least
+    /// the current and the next token, unless the text ends before.
+    lookahead: VecDeque<TokenData<'a>>,
 }
 
 impl<'a> C1Lexer<'a> {
     /// Initialize a new C1Lexer for the given string slice
-    pub fn new(text: &'a str) -> C1Lexer {
+    pub fn new(text: &'a str) -> C1Lexer<'a> {
+        Self::initialize(text, None, false, (0, 1, 1))
+    }
+
+    /// Initialize a new C1Lexer that also hands out comments, whitespace and linebreaks as
+    /// tokens. A linebreak is located on the line it ends.
+    /// ```
+    /// use cb_3::{C1Lexer, C1Token};
+    /// let mut lexer = C1Lexer::with_trivia("x /* y */\n");
+    ///
+    /// let mut tokens = Vec::new();
+    /// while let Some(token) = lexer.current_token() {
+    ///     tokens.push((token, lexer.current_text().unwrap(), lexer.current_line_number().unwrap()));
+    ///     lexer.eat();
+    /// }
+    /// assert_eq!(
+    ///     tokens,
+    ///     [
+    ///         (C1Token::Identifier, "x", 1),
+    ///         (C1Token::Whitespace, " ", 1),
+    ///         (C1Token::CComment, "/* y */", 1),
+    ///         (C1Token::Linebreak, "\n", 1),
+    ///     ]
+    /// );
+    /// ```
+    pub fn with_trivia(text: &'a str) -> C1Lexer<'a> {
+        Self::initialize(text, None, true, (0, 1, 1))
+    }
+
+    /// Initialize a C1Lexer that hands out the tokens of `text` lexed before, e.g. by
+    /// [`tokenize`], instead of lexing the text again
+    pub fn from_tokens(text: &'a str, tokens: &'a [Token]) -> C1Lexer<'a> {
+        Self::initialize(text, Some(tokens.iter()), false, (0, 1, 1))
+    }
+
+    /// Initialize a new C1Lexer that starts lexing at a byte offset of the text, located at the
+    /// given line and column
+    pub(crate) fn resume(text: &'a str, offset: usize, line: usize, column: usize) -> C1Lexer<'a> {
+        Self::initialize(text, None, false, (offset, line, column))
+    }
+
+    fn initialize(
+        text: &'a str,
+        replay: Option<std::slice::Iter<'a, Token>>,
+        trivia: bool,
+        (offset, line, column): (usize, usize, usize),
+    ) -> C1Lexer<'a> {
+        let mut logos_lexer = C1Token::lexer(text);
+        logos_lexer.bump(offset);
         let mut lexer = C1Lexer {
-            logos_lexer: C1Token::lexer(text),
-            logos_line_number: 1,
-            current_token: None,
-            peek_token: None,
+            logos_lexer,
+            logos_line_number: line,
+            column_offset: offset,
+            column,
+            replay,
+            trivia,
+            lookahead: VecDeque::new(),
         };
-        lexer.current_token = lexer.next_token();
-        lexer.peek_token = lexer.next_token();
+        lexer.fill(1);
         lexer
     }
 
@@ -207,7 +281,7 @@ impl<'a> C1Lexer<'a> {
     /// assert_eq!(lexer.current_text(), Some("current"));
     /// ```
     pub fn current_token(&self) -> Option<C1Token> {
-        self.current_token.token_type()
+        self.lookahead.front().token_type()
     }
 
     /// Return the C1Token variant of the next token without consuming it.
@@ -222,27 +296,66 @@ impl<'a> C1Lexer<'a> {
     /// assert_eq!(lexer.peek_text(), Some("next"));
     /// ```
     pub fn peek_token(&self) -> Option<C1Token> {
-        self.peek_token.token_type()
+        self.lookahead.get(1).token_type()
     }
 
     /// Return the text of the current token
-    pub fn current_text(&self) -> Option<&str> {
-        self.current_token.text()
+    pub fn current_text(&self) -> Option<&'a str> {
+        self.lookahead.front().text()
     }
 
     /// Return the text of the next token
-    pub fn peek_text(&self) -> Option<&str> {
-        self.peek_token.text()
+    pub fn peek_text(&self) -> Option<&'a str> {
+        self.lookahead.get(1).text()
     }
 
     /// Return the line number where the current token is located
     pub fn current_line_number(&self) -> Option<usize> {
-        self.current_token.line_number()
+        self.lookahead.front().line_number()
     }
 
     /// Return the line number where the next token is located
     pub fn peek_line_number(&self) -> Option<usize> {
-        self.peek_token.line_number()
+        self.lookahead.get(1).line_number()
+    }
+
+    /// Return the column where the current token starts, counted in characters from 1
+    /// ```
+    /// use cb_3::C1Lexer;
+    /// let lexer = C1Lexer::new("\tä = 1");
+    ///
+    /// assert_eq!(lexer.current_column(), Some(2));
+    /// assert_eq!(lexer.peek_column(), Some(4));
+    /// ```
+    pub fn current_column(&self) -> Option<usize> {
+        self.lookahead.front().column()
+    }
+
+    /// Return the column where the next token starts
+    pub fn peek_column(&self) -> Option<usize> {
+        self.lookahead.get(1).column()
+    }
+
+    /// Return the byte range of the current token in the lexed text
+    /// ```
+    /// use cb_3::C1Lexer;
+    /// let lexer = C1Lexer::new("int  main");
+    ///
+    /// assert_eq!(lexer.current_span(), Some(0..3));
+    /// assert_eq!(lexer.peek_span(), Some(5..9));
+    /// ```
+    pub fn current_span(&self) -> Option<Range<usize>> {
+        self.lookahead.front().span()
+    }
+
+    /// Return the byte range of the next token in the lexed text
+    pub fn peek_span(&self) -> Option<Range<usize>> {
+        self.lookahead.get(1).span()
+    }
+
+    /// Return the whole text the lexer works on
+    pub fn source(&self) -> &'a str {
+        self.logos_lexer.source()
     }
 
     /// Drop the current token and retrieve the next token in the text.
@@ -266,53 +379,258 @@ impl<'a> C1Lexer<'a> {
     /// assert_eq!(lexer.peek_text(), None);
     /// ```
     pub fn eat(&mut self) {
-        self.current_token = self.peek_token.take();
-        self.peek_token = self.next_token();
+        self.lookahead.pop_front();
+        self.fill(1);
+    }
+
+    /// Return the C1Token variant of the token `n` tokens ahead without consuming anything. The
+    /// current token is 0 tokens ahead, the next token 1.
+    /// ```
+    /// use cb_3::{C1Lexer, C1Token};
+    /// let mut lexer = C1Lexer::new("int x = 1;");
+    ///
+    /// assert_eq!(lexer.peek_nth(0), Some(C1Token::KwInt));
+    /// assert_eq!(lexer.peek_nth(3), Some(C1Token::ConstInt));
+    /// assert_eq!(lexer.peek_nth_text(3), Some("1"));
+    /// assert_eq!(lexer.peek_nth(5), None);
+    /// assert_eq!(lexer.current_token(), Some(C1Token::KwInt));
+    /// ```
+    pub fn peek_nth(&mut self, n: usize) -> Option<C1Token> {
+        self.fill(n);
+        self.lookahead.get(n).token_type()
+    }
+
+    /// Return the text of the token `n` tokens ahead without consuming anything
+    pub fn peek_nth_text(&mut self, n: usize) -> Option<&'a str> {
+        self.fill(n);
+        self.lookahead.get(n).text()
+    }
+
+    /// Remember the current position, to return to it with [`C1Lexer::rewind`]
+    /// ```
+    /// use cb_3::C1Lexer;
+    /// let mut lexer = C1Lexer::new("a\nb c");
+    ///
+    /// let checkpoint = lexer.checkpoint();
+    /// lexer.eat();
+    /// lexer.eat();
+    /// assert_eq!(lexer.current_text(), Some("c"));
+    ///
+    /// lexer.rewind(checkpoint);
+    /// assert_eq!(lexer.current_text(), Some("a"));
+    /// lexer.eat();
+    /// assert_eq!(lexer.current_line_number(), Some(2));
+    /// ```
+    pub fn checkpoint(&self) -> Checkpoint<'a> {
+        Checkpoint {
+            logos_lexer: self.logos_lexer.clone(),
+            logos_line_number: self.logos_line_number,
+            column_offset: self.column_offset,
+            column: self.column,
+            replay: self.replay.clone(),
+            lookahead: self.lookahead.clone(),
+        }
+    }
+
+    /// Return to the position of a checkpoint taken from this lexer
+    pub fn rewind(&mut self, checkpoint: Checkpoint<'a>) {
+        self.logos_lexer = checkpoint.logos_lexer;
+        self.logos_line_number = checkpoint.logos_line_number;
+        self.column_offset = checkpoint.column_offset;
+        self.column = checkpoint.column;
+        self.replay = checkpoint.replay;
+        self.lookahead = checkpoint.lookahead;
+    }
+
+    /// Lex ahead until the token `n` tokens ahead is buffered or the text ends
+    fn fill(&mut self, n: usize) {
+        while self.lookahead.len() <= n {
+            match self.next_token() {
+                Some(token) => self.lookahead.push_back(token),
+                None => break,
+            }
+        }
     }
 
     /// Private method for reading the next token from the logos::Lexer and extracting the required data
     /// from it
     fn next_token(&mut self) -> Option<TokenData<'a>> {
-        // Retrieve the next token from the internal lexer
-        if let Some(c1_token) = self.logos_lexer.next() {
-            match c1_token {
-                C1Token::Linebreak => {
-                    // If the token is a linebreak, increase the line number and get the next token
-                    self.logos_line_number += 1;
-                    self.next_token()
+        if let Some(replay) = &mut self.replay {
+            return replay.next().map(|token| TokenData {
+                token_type: token.kind,
+                token_text: &token.text,
+                token_line: token.line,
+                token_column: token.column,
+                token_span: token.span.clone(),
+            });
+        }
+        // Retrieve the next token from the internal lexer, skipping trivia unless requested
+        while let Some(c1_token) = self.logos_lexer.next() {
+            let span = self.logos_lexer.span();
+            let text = self.logos_lexer.slice();
+            let line = self.logos_line_number;
+            let column = self.column
+                + self.logos_lexer.source()[self.column_offset..span.start]
+                    .chars()
+                    .count();
+            // Linebreaks and comments may end lines, the following tokens are located after them
+            match text.rfind('\n') {
+                Some(index) => {
+                    self.logos_line_number += text.matches('\n').count();
+                    self.column_offset = span.start + index + 1;
+                    self.column = 1;
+                }
+                None => {
+                    self.column_offset = span.start;
+                    self.column = column;
                 }
-                _ => Some(TokenData {
-                    // If the token is not a linebreak, initialize and return a TokenData instance
+            }
+            if self.trivia || !c1_token.is_trivia() {
+                return Some(TokenData {
                     token_type: c1_token,
-                    token_text: self.logos_lexer.slice(),
-                    token_line: self.logos_line_number,
-                }),
+                    token_text: text,
+                    token_line: line,
+                    token_column: column,
+                    token_span: span,
+                });
             }
+        }
+        None
+    }
+}
+
+/// A token as yielded by C1Lexer's iterator
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Token {
+    pub kind: C1Token,
+    pub text: String,
+    pub line: usize,
+    /// Column of the first character, counted in characters from 1
+    pub column: usize,
+    /// Byte range of the token in the lexed text
+    pub span: Range<usize>,
+}
+
+/// Yield the remaining tokens, starting with the current token
+/// ```
+/// use cb_3::{C1Lexer, C1Token};
+///
+/// let kinds: Vec<C1Token> = C1Lexer::new("x = 1;").map(|token| token.kind).collect();
+/// assert_eq!(
+///     kinds,
+///     [C1Token::Identifier, C1Token::Assign, C1Token::ConstInt, C1Token::Semicolon]
+/// );
+/// ```
+impl<'a> Iterator for C1Lexer<'a> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        let data = self.lookahead.pop_front()?;
+        self.fill(1);
+        Some(Token {
+            kind: data.token_type,
+            text: data.token_text.to_string(),
+            line: data.token_line,
+            column: data.token_column,
+            span: data.token_span,
+        })
+    }
+}
+
+/// A position of a C1Lexer, see [`C1Lexer::checkpoint`]
+#[derive(Clone)]
+pub struct Checkpoint<'a> {
+    logos_lexer: Lexer<'a, C1Token>,
+    logos_line_number: usize,
+    column_offset: usize,
+    column: usize,
+    replay: Option<std::slice::Iter<'a, Token>>,
+    lookahead: VecDeque<TokenData<'a>>,
+}
+
+/// Text that is no token of C1
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LexError {
+    pub text: String,
+    pub line: usize,
+    pub column: usize,
+    pub span: Range<usize>,
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unrecognized text '{}' at line {} column {}",
+            self.text, self.line, self.column
+        )
+    }
+}
+
+impl std::error::Error for LexError {}
+
+/// Lex the whole text at once and return its tokens without trivia, or all pieces of text that
+/// are no tokens. The tokens can be parsed with [`C1Parser::parse_tokens`](crate::C1Parser).
+/// ```
+/// use cb_3::{tokenize, C1Token};
+///
+/// let tokens = tokenize("int main() {\n  return 0;\n}").unwrap();
+/// assert_eq!(tokens.len(), 9);
+/// assert_eq!((tokens[5].kind, tokens[5].line, tokens[5].column), (C1Token::KwReturn, 2, 3));
+///
+/// let errors = tokenize("x = 1 & 2 $").unwrap_err();
+/// assert_eq!(errors[0].to_string(), "unrecognized text '&' at line 1 column 7");
+/// assert_eq!(errors[1].span, 10..11);
+/// ```
+pub fn tokenize(text: &str) -> Result<Vec<Token>, Vec<LexError>> {
+    let mut tokens = Vec::new();
+    let mut errors = Vec::new();
+    for token in C1Lexer::new(text) {
+        if token.kind == C1Token::Error {
+            errors.push(LexError {
+                text: token.text,
+                line: token.line,
+                column: token.column,
+                span: token.span,
+            });
         } else {
-            None
+            tokens.push(token);
         }
     }
+    if errors.is_empty() {
+        Ok(tokens)
+    } else {
+        Err(errors)
+    }
 }
 
 /// Hidden struct for capsuling the data associated with a token.
+#[derive(Clone)]
 struct TokenData<'a> {
     token_type: C1Token,
     token_text: &'a str,
     token_line: usize,
+    token_column: usize,
+    token_span: Range<usize>,
 }
 
 /// Hidden trait that makes it possible to implemented the required getter functionality directly for
-/// Option<TokenData>.
+/// Option<&TokenData>.
 trait TokenDataProvider<'a> {
     /// Return the type of the token, aka. its C1Token variant.
     fn token_type(&self) -> Option<C1Token>;
     /// Return the text of the token
-    fn text(&self) -> Option<&str>;
+    fn text(&self) -> Option<&'a str>;
     /// Return the line number of the token
     fn line_number(&self) -> Option<usize>;
+    /// Return the column of the token
+    fn column(&self) -> Option<usize>;
+    /// Return the byte range of the token
+    fn span(&self) -> Option<Range<usize>>;
 }
 
-impl<'a> TokenDataProvider<'a> for Option<TokenData<'a>> {
+impl<'a> TokenDataProvider<'a> for Option<&TokenData<'a>> {
     fn token_type(&self) -> Option<C1Token> {
         self.as_ref().map(|data| data.token_type)
     }
@@ -324,11 +642,19 @@ impl<'a> TokenDataProvider<'a> for Option<TokenData<'a>> {
     fn line_number(&self) -> Option<usize> {
         self.as_ref().map(|data| data.token_line)
     }
+
+    fn column(&self) -> Option<usize> {
+        self.as_ref().map(|data| data.token_column)
+    }
+
+    fn span(&self) -> Option<Range<usize>> {
+        self.as_ref().map(|data| data.token_span.clone())
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::lexer::C1Lexer;
+    use crate::lexer::{tokenize, C1Lexer, LexError, Token};
     use crate::C1Token;
 
     #[test]
@@ -384,4 +710,111 @@ mod tests {
         let lexer = C1Lexer::new("33E+2");
         assert_eq!(lexer.current_token(), Some(C1Token::ConstFloat));
     }
+
+    #[test]
+    fn trivia_mode() {
+        let text = "int // comment\r\n\tx /* a\nb */ = 1;\r\n";
+        let mut lexer = C1Lexer::with_trivia(text);
+        let mut tokens = Vec::new();
+        let mut restored = String::new();
+        while let Some(token) = lexer.current_token() {
+            if token.is_trivia() {
+                tokens.push((token, lexer.current_line_number().unwrap()));
+            }
+            restored += lexer.current_text().unwrap();
+            lexer.eat();
+        }
+        assert_eq!(restored, text);
+        assert_eq!(
+            tokens,
+            vec![
+                (C1Token::Whitespace, 1),
+                (C1Token::CPPComment, 1),
+                (C1Token::Whitespace, 2),
+                (C1Token::Whitespace, 2),
+                (C1Token::CComment, 2),
+                (C1Token::Whitespace, 3),
+                (C1Token::Whitespace, 3),
+                (C1Token::Linebreak, 3),
+            ]
+        );
+
+        // The default mode skips the same trivia
+        let mut lexer = C1Lexer::new(text);
+        let mut kinds = Vec::new();
+        while let Some(token) = lexer.current_token() {
+            kinds.push(token);
+            lexer.eat();
+        }
+        use C1Token::*;
+        assert_eq!(kinds, vec![KwInt, Identifier, Assign, ConstInt, Semicolon]);
+    }
+
+    #[test]
+    fn linebreaks_in_comments_are_counted() {
+        let text = "a // x\n  b /* y\n z */ c\r\n/**/ d";
+        let positions: Vec<(usize, usize)> = C1Lexer::new(text)
+            .map(|token| (token.line, token.column))
+            .collect();
+        assert_eq!(positions, vec![(1, 1), (2, 3), (3, 7), (4, 6)]);
+    }
+
+    #[test]
+    fn tokenize_collects_all_errors() {
+        let text = "int x;\n  x = 'a' + 1;";
+        let errors = tokenize(text).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                LexError {
+                    text: "'".to_string(),
+                    line: 2,
+                    column: 7,
+                    span: 13..14
+                },
+                LexError {
+                    text: "'".to_string(),
+                    line: 2,
+                    column: 9,
+                    span: 15..16
+                },
+            ]
+        );
+
+        let tokens = tokenize("int x;").unwrap();
+        let mut lexer = C1Lexer::from_tokens("int x;", &tokens);
+        assert_eq!(lexer.current_token(), Some(C1Token::KwInt));
+        lexer.eat();
+        assert_eq!(lexer.current_text(), Some("x"));
+        assert_eq!(lexer.current_column(), Some(5));
+        lexer.eat();
+        lexer.eat();
+        assert_eq!(lexer.current_token(), None);
+    }
+
+    #[test]
+    fn lookahead_and_iterator_agree() {
+        let text = include_str!("../tests/data/beispiel.c-1");
+        let tokens: Vec<Token> = C1Lexer::new(text).collect();
+        assert_eq!(tokens[1].text, "blub");
+        assert_eq!(tokens.last().unwrap().line, 30);
+
+        let mut lexer = C1Lexer::new(text);
+        for n in (0..tokens.len() + 2).rev() {
+            assert_eq!(lexer.peek_nth(n), tokens.get(n).map(|token| token.kind));
+        }
+        // Looking far ahead does not change what is eaten
+        for (index, token) in tokens.iter().enumerate() {
+            assert_eq!(lexer.current_text(), Some(token.text.as_str()));
+            assert_eq!(lexer.current_line_number(), Some(token.line));
+            assert_eq!(lexer.current_span(), Some(token.span.clone()));
+            assert_eq!(
+                lexer.peek_nth(2),
+                tokens.get(index + 2).map(|token| token.kind)
+            );
+            lexer.eat();
+        }
+        assert_eq!(lexer.current_token(), None);
+        assert_eq!(lexer.next(), None);
+    }
 }
@@ -1,12 +1,34 @@
+pub mod ast;
+pub mod call_graph;
+pub mod cst;
+pub mod diagnostic;
+pub mod dot;
+pub mod grammar;
+pub mod incremental;
+pub mod ir;
 mod lexer;
+pub mod lint;
+#[cfg(feature = "lsp")]
+pub mod lsp;
+pub mod operators;
 
 // Type definition for the Result that is being used by the parser. You may change it to anything
 // you want
-pub type ParseResult = Result<(), String>;
+pub type ParseResult = Result<(), ParseError>;
 
 pub use lexer::C1Lexer;
 pub use lexer::C1Token;
+pub use lexer::Checkpoint;
+pub use lexer::{tokenize, LexError, Token};
 
 // You will need a re-export of your C1Parser definition. Here is an example:
 mod parser;
-pub use parser::C1Parser;
+pub use parser::{
+    C1Parser, Dialect, EventToken, ParseError, ParseEvent, ParseListener, ParserLimits,
+    ParserOptions, Rule, TraceEntry, TraceStep,
+};
+
+pub mod sexp;
+pub mod source;
+pub mod visit;
+pub mod warnings;
@@ -0,0 +1,524 @@
+//! Pluggable lints over the syntax tree.
+//!
+//! A [`Lint`] inspects a parsed program and reports [`LintWarning`]s identified by the lint's
+//! code. The [`LintRegistry`] holds the lints and decides which of them run:
+//! ```
+//! use cb_3::lint::LintRegistry;
+//! use cb_3::C1Parser;
+//!
+//! let program = C1Parser::parse_program("void main() { if (x = y) {} }").unwrap();
+//! let mut registry = LintRegistry::default();
+//! registry.disable("empty-if");
+//! registry.disable("dead-assignment");
+//!
+//! let warnings = registry.run(&program);
+//! assert_eq!(warnings.len(), 1);
+//! assert_eq!(warnings[0].code, "assignment-in-condition");
+//! ```
+use crate::ast::{BinaryOperator, Expression, ExpressionKind, Program, Statement, StatementKind};
+use crate::visit::{walk_expression, walk_statement, Visitor};
+use crate::warnings::{self, WarningKind};
+use std::fmt;
+
+/// A warning reported by a lint
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LintWarning {
+    /// Code of the lint that reported the warning
+    pub code: &'static str,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "warning[{}] at line {}: {}",
+            self.code, self.line, self.message
+        )
+    }
+}
+
+pub trait Lint {
+    /// Unique, kebab-case name of the lint, used to enable and disable it
+    fn code(&self) -> &'static str;
+
+    /// One sentence describing what the lint reports
+    fn description(&self) -> &'static str;
+
+    /// Report every occurrence in the program
+    fn check(&self, program: &Program) -> Vec<LintWarning>;
+}
+
+/// The set of known lints and whether each of them is enabled
+pub struct LintRegistry {
+    lints: Vec<(Box<dyn Lint>, bool)>,
+}
+
+impl Default for LintRegistry {
+    /// Create a registry with all built-in lints enabled
+    fn default() -> Self {
+        let mut registry = LintRegistry::empty();
+        registry.register(Box::new(AssignmentInCondition));
+        registry.register(Box::new(SelfAssignment));
+        registry.register(Box::new(BoolComparison));
+        registry.register(Box::new(RedundantBlock));
+        registry.register(Box::new(EmptyIf));
+        registry.register(Box::new(IntegerOutOfRange));
+        registry.register(Box::new(WarningLint(WarningKind::UnreachableCode)));
+        registry.register(Box::new(WarningLint(WarningKind::UnusedFunction)));
+        registry.register(Box::new(WarningLint(WarningKind::DeadAssignment)));
+        registry
+    }
+}
+
+impl LintRegistry {
+    /// Create a registry without any lints
+    pub fn empty() -> LintRegistry {
+        LintRegistry { lints: Vec::new() }
+    }
+
+    /// Add an enabled lint. A lint with the same code is replaced.
+    pub fn register(&mut self, lint: Box<dyn Lint>) {
+        self.lints.retain(|(known, _)| known.code() != lint.code());
+        self.lints.push((lint, true));
+    }
+
+    /// Enable the lint with the given code. Return false if there is no such lint.
+    pub fn enable(&mut self, code: &str) -> bool {
+        self.set_enabled(code, true)
+    }
+
+    /// Disable the lint with the given code. Return false if there is no such lint.
+    pub fn disable(&mut self, code: &str) -> bool {
+        self.set_enabled(code, false)
+    }
+
+    fn set_enabled(&mut self, code: &str, enabled: bool) -> bool {
+        match self.lints.iter_mut().find(|(lint, _)| lint.code() == code) {
+            Some((_, state)) => {
+                *state = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_enabled(&self, code: &str) -> bool {
+        self.lints
+            .iter()
+            .any(|(lint, enabled)| *enabled && lint.code() == code)
+    }
+
+    /// Iterate over all registered lints, enabled or not
+    pub fn lints(&self) -> impl Iterator<Item = &dyn Lint> {
+        self.lints.iter().map(|(lint, _)| lint.as_ref())
+    }
+
+    /// Run all enabled lints and return their warnings ordered by line
+    pub fn run(&self, program: &Program) -> Vec<LintWarning> {
+        let mut warnings: Vec<LintWarning> = self
+            .lints
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .flat_map(|(lint, _)| lint.check(program))
+            .collect();
+        warnings.sort_by_key(|warning| warning.line);
+        warnings
+    }
+}
+
+/// `if (x = y)`: probably meant `==`
+pub struct AssignmentInCondition;
+
+impl Lint for AssignmentInCondition {
+    fn code(&self) -> &'static str {
+        "assignment-in-condition"
+    }
+
+    fn description(&self) -> &'static str {
+        "the condition of an if statement is an assignment"
+    }
+
+    fn check(&self, program: &Program) -> Vec<LintWarning> {
+        let mut warnings = Vec::new();
+        for_each_statement(program, &mut |statement| {
+            if let StatementKind::If { condition, .. } = &statement.kind {
+                if let ExpressionKind::Assign { target, .. } = &condition.kind {
+                    warnings.push(LintWarning {
+                        code: self.code(),
+                        line: condition.line,
+                        message: format!(
+                            "assignment to '{}' used as condition, did you mean '=='?",
+                            target
+                        ),
+                    });
+                }
+            }
+        });
+        warnings
+    }
+}
+
+/// `x = x`
+pub struct SelfAssignment;
+
+impl Lint for SelfAssignment {
+    fn code(&self) -> &'static str {
+        "self-assignment"
+    }
+
+    fn description(&self) -> &'static str {
+        "a variable is assigned to itself"
+    }
+
+    fn check(&self, program: &Program) -> Vec<LintWarning> {
+        let mut warnings = Vec::new();
+        let mut check = |target: &str, value: &Expression, line: usize| {
+            if let ExpressionKind::Variable(name) = &value.kind {
+                if name == target {
+                    warnings.push(LintWarning {
+                        code: self.code(),
+                        line,
+                        message: format!("'{}' is assigned to itself", target),
+                    });
+                }
+            }
+        };
+        for_each_statement(program, &mut |statement| {
+            if let StatementKind::Assignment { target, value } = &statement.kind {
+                check(target, value, statement.line);
+            }
+        });
+        for_each_expression(program, &mut |expression| {
+            if let ExpressionKind::Assign { target, value } = &expression.kind {
+                check(target, value, expression.line);
+            }
+        });
+        warnings
+    }
+}
+
+/// `x == true`, `(a < b) != false`
+pub struct BoolComparison;
+
+impl Lint for BoolComparison {
+    fn code(&self) -> &'static str {
+        "bool-comparison"
+    }
+
+    fn description(&self) -> &'static str {
+        "an expression is compared to true or false"
+    }
+
+    fn check(&self, program: &Program) -> Vec<LintWarning> {
+        let mut warnings = Vec::new();
+        for_each_expression(program, &mut |expression| {
+            if let ExpressionKind::Binary { operator, lhs, rhs } = &expression.kind {
+                if !matches!(operator, BinaryOperator::Equal | BinaryOperator::NotEqual) {
+                    return;
+                }
+                let literal = [lhs, rhs]
+                    .into_iter()
+                    .find_map(|operand| match operand.kind {
+                        ExpressionKind::Bool(value) => Some(value),
+                        _ => None,
+                    });
+                if let Some(value) = literal {
+                    warnings.push(LintWarning {
+                        code: self.code(),
+                        line: expression.line,
+                        message: format!(
+                            "comparison with '{}' can be simplified to the expression itself{}",
+                            value,
+                            if value == (*operator == BinaryOperator::Equal) {
+                                ""
+                            } else {
+                                " or its negation"
+                            }
+                        ),
+                    });
+                }
+            }
+        });
+        warnings
+    }
+}
+
+/// `{ { ... } }`
+pub struct RedundantBlock;
+
+impl Lint for RedundantBlock {
+    fn code(&self) -> &'static str {
+        "redundant-block"
+    }
+
+    fn description(&self) -> &'static str {
+        "a block consists of nothing but another block"
+    }
+
+    fn check(&self, program: &Program) -> Vec<LintWarning> {
+        let mut warnings = Vec::new();
+        let mut check = |statements: &[Statement]| {
+            if let [inner] = statements {
+                if let StatementKind::Block(_) = inner.kind {
+                    warnings.push(LintWarning {
+                        code: self.code(),
+                        line: inner.line,
+                        message: "redundant nested block".to_string(),
+                    });
+                }
+            }
+        };
+        for function in &program.functions {
+            check(&function.body);
+        }
+        for_each_statement(program, &mut |statement| {
+            if let StatementKind::Block(statements) = &statement.kind {
+                check(statements);
+            }
+        });
+        warnings
+    }
+}
+
+/// `if (x) {}`
+pub struct EmptyIf;
+
+impl Lint for EmptyIf {
+    fn code(&self) -> &'static str {
+        "empty-if"
+    }
+
+    fn description(&self) -> &'static str {
+        "the body of an if statement is empty"
+    }
+
+    fn check(&self, program: &Program) -> Vec<LintWarning> {
+        let mut warnings = Vec::new();
+        for_each_statement(program, &mut |statement| {
+            if let StatementKind::If { body, .. } = &statement.kind {
+                if let StatementKind::Block(statements) = &body.kind {
+                    if statements.is_empty() {
+                        warnings.push(LintWarning {
+                            code: self.code(),
+                            line: body.line,
+                            message: "empty body of if statement".to_string(),
+                        });
+                    }
+                }
+            }
+        });
+        warnings
+    }
+}
+
+/// `x = 9223372036854775808`: lowering saturates the constant to i64::MAX
+pub struct IntegerOutOfRange;
+
+impl Lint for IntegerOutOfRange {
+    fn code(&self) -> &'static str {
+        "integer-out-of-range"
+    }
+
+    fn description(&self) -> &'static str {
+        "an integer constant does not fit into 64 bits"
+    }
+
+    fn check(&self, program: &Program) -> Vec<LintWarning> {
+        let mut warnings = Vec::new();
+        for_each_expression(program, &mut |expression| {
+            if let ExpressionKind::IntOutOfRange(digits) = &expression.kind {
+                warnings.push(LintWarning {
+                    code: self.code(),
+                    line: expression.line,
+                    message: format!("integer constant {} is out of range", digits),
+                });
+            }
+        });
+        warnings
+    }
+}
+
+/// Makes one kind of the analyses in [`crate::warnings`] available as lint
+struct WarningLint(WarningKind);
+
+impl Lint for WarningLint {
+    fn code(&self) -> &'static str {
+        match self.0 {
+            WarningKind::UnreachableCode => "unreachable-code",
+            WarningKind::UnusedFunction => "unused-function",
+            WarningKind::DeadAssignment => "dead-assignment",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self.0 {
+            WarningKind::UnreachableCode => "a statement follows a return in the same block",
+            WarningKind::UnusedFunction => "a function is never called from main",
+            WarningKind::DeadAssignment => "an assigned value is never read",
+        }
+    }
+
+    fn check(&self, program: &Program) -> Vec<LintWarning> {
+        // Each lint runs only the analysis of its kind, so running all of them analyses the
+        // program once
+        warnings::check_kind(program, self.0)
+            .into_iter()
+            .map(|warning| LintWarning {
+                code: self.code(),
+                line: warning.line,
+                message: warning.message,
+            })
+            .collect()
+    }
+}
+
+/// Call f for every statement of the program, outer statements first
+fn for_each_statement(program: &Program, f: &mut dyn FnMut(&Statement)) {
+    struct Statements<'f>(&'f mut dyn FnMut(&Statement));
+
+    impl Visitor for Statements<'_> {
+        fn visit_statement(&mut self, statement: &Statement) {
+            (self.0)(statement);
+            walk_statement(self, statement);
+        }
+
+        fn visit_expression(&mut self, _: &Expression) {}
+    }
+
+    Statements(f).visit_program(program);
+}
+
+/// Call f for every expression of the program, outer expressions first
+fn for_each_expression(program: &Program, f: &mut dyn FnMut(&Expression)) {
+    struct Expressions<'f>(&'f mut dyn FnMut(&Expression));
+
+    impl Visitor for Expressions<'_> {
+        fn visit_expression(&mut self, expression: &Expression) {
+            (self.0)(expression);
+            walk_expression(self, expression);
+        }
+    }
+
+    Expressions(f).visit_program(program);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::Program;
+    use crate::lint::{Lint, LintRegistry, LintWarning};
+    use crate::C1Parser;
+
+    fn lint(text: &str) -> Vec<(&'static str, usize)> {
+        LintRegistry::default()
+            .run(&C1Parser::parse_program(text).unwrap())
+            .into_iter()
+            .map(|warning| (warning.code, warning.line))
+            .collect()
+    }
+
+    #[test]
+    fn assignment_in_condition() {
+        assert_eq!(
+            lint("int f() {\n if (x = y) return x;\n return 0;\n}"),
+            vec![("assignment-in-condition", 2)]
+        );
+        assert_eq!(lint("int f() { if (x == y) return x; return 0; }"), vec![]);
+    }
+
+    #[test]
+    fn self_assignment() {
+        assert_eq!(
+            lint("int f() {\n x = 1;\n x = x;\n y = (x = x);\n return y + x;\n}"),
+            vec![("self-assignment", 3), ("self-assignment", 4)]
+        );
+    }
+
+    #[test]
+    fn bool_comparison() {
+        assert_eq!(
+            lint("void f() {\n printf(x == true);\n printf((x < y) != false);\n printf(true);\n}"),
+            vec![("bool-comparison", 2), ("bool-comparison", 3)]
+        );
+    }
+
+    #[test]
+    fn redundant_block() {
+        assert_eq!(
+            lint("void f() {\n {\n g();\n }\n}\nvoid g() {\n {\n {\n }\n }\n g();\n}"),
+            vec![("redundant-block", 2), ("redundant-block", 8)]
+        );
+    }
+
+    #[test]
+    fn empty_if() {
+        assert_eq!(
+            lint("void f() {\n if (x) {\n }\n if (x) { f(); }\n}"),
+            vec![("empty-if", 2)]
+        );
+    }
+
+    #[test]
+    fn integer_out_of_range() {
+        assert_eq!(
+            lint("void f() {\n printf(9223372036854775807);\n printf(-9223372036854775808);\n}"),
+            vec![("integer-out-of-range", 3)]
+        );
+    }
+
+    #[test]
+    fn analyses_are_lints() {
+        assert_eq!(
+            lint("void f() {}\nint main() {\n x = 1;\n return 0;\n printf(2);\n}"),
+            vec![
+                ("unused-function", 1),
+                ("dead-assignment", 3),
+                ("unreachable-code", 5)
+            ]
+        );
+    }
+
+    #[test]
+    fn registry() {
+        struct NoFunctions;
+        impl Lint for NoFunctions {
+            fn code(&self) -> &'static str {
+                "no-functions"
+            }
+            fn description(&self) -> &'static str {
+                "the program is empty"
+            }
+            fn check(&self, program: &Program) -> Vec<LintWarning> {
+                match program.functions.is_empty() {
+                    true => vec![LintWarning {
+                        code: self.code(),
+                        line: 1,
+                        message: "empty program".to_string(),
+                    }],
+                    false => vec![],
+                }
+            }
+        }
+
+        let program = C1Parser::parse_program("").unwrap();
+        let mut registry = LintRegistry::empty();
+        assert!(registry.run(&program).is_empty());
+        registry.register(Box::new(NoFunctions));
+        assert_eq!(
+            registry.run(&program)[0].to_string(),
+            "warning[no-functions] at line 1: empty program"
+        );
+        assert!(registry.disable("no-functions"));
+        assert!(!registry.is_enabled("no-functions"));
+        assert!(registry.run(&program).is_empty());
+        assert!(registry.enable("no-functions"));
+        assert_eq!(registry.run(&program).len(), 1);
+        assert!(!registry.disable("unknown"));
+
+        let default = LintRegistry::default();
+        let codes: Vec<&str> = default.lints().map(|lint| lint.code()).collect();
+        assert_eq!(codes.len(), 9);
+        assert!(codes.iter().all(|code| default.is_enabled(code)));
+    }
+}
@@ -0,0 +1,463 @@
+//! Language server for C(-1), speaking the Language Server Protocol over stdio.
+//!
+//! The server keeps the full text of every open document (full text synchronisation) and
+//! publishes the syntax error reported by [`C1Parser`](crate::C1Parser) or, for syntactically
+//! correct documents, the warnings of the default [`LintRegistry`]. Go to definition, find
+//! references, hover and document symbols work on function identifiers. They are computed from
+//! the token stream, so they keep working while the document contains syntax errors.
+use crate::lint::LintRegistry;
+use crate::source::SourceMap;
+use crate::{C1Lexer, C1Token};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::ops::Range;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// LSP SymbolKind of functions
+const SYMBOL_KIND_FUNCTION: i64 = 12;
+
+/// Serve LSP messages read from `input` until the client sends `exit`. Return the process exit
+/// code: 0 if the client requested a shutdown before exiting, 1 otherwise.
+pub fn run(mut input: impl BufRead, mut output: impl Write) -> io::Result<i32> {
+    let mut server = Server::default();
+    while let Some(body) = read_message(&mut input)? {
+        let responses = match serde_json::from_str::<Value>(&body) {
+            Ok(message) => server.handle(&message),
+            Err(error) => vec![error_response(Value::Null, PARSE_ERROR, &error.to_string())],
+        };
+        for response in responses {
+            write_message(&mut output, &response)?;
+        }
+        if let Some(code) = server.exit_code() {
+            return Ok(code);
+        }
+    }
+    // The client went away without saying goodbye
+    Ok(1)
+}
+
+/// Read the body of the next message. Return None at the end of the input.
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            if content_length.is_some() {
+                break;
+            }
+            continue;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                content_length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let mut body = vec![0; content_length.unwrap_or_default()];
+    input.read_exact(&mut body)?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+/// Write a message with its Content-Length header
+pub fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+/// State of the language server, independent of the transport
+#[derive(Default)]
+pub struct Server {
+    /// Text of each open document by URI
+    documents: HashMap<String, String>,
+    shutdown_requested: bool,
+    exit_code: Option<i32>,
+}
+
+impl Server {
+    /// Handle one incoming request or notification and return the messages to send back
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = match message["method"].as_str() {
+            Some(method) => method,
+            // Responses to requests of the server; we never send any
+            None if message.get("id").is_some() && message.get("result").is_some() => return vec![],
+            None => {
+                let id = message.get("id").cloned().unwrap_or(Value::Null);
+                return vec![error_response(id, INVALID_REQUEST, "missing method")];
+            }
+        };
+        let params = &message["params"];
+        match message.get("id") {
+            Some(id) => vec![match self.request(method, params) {
+                Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+                Err((code, error)) => error_response(id.clone(), code, &error),
+            }],
+            None => self.notification(method, params),
+        }
+    }
+
+    /// Return the exit code once the client sent `exit`
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    fn request(&mut self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "definitionProvider": true,
+                    "referencesProvider": true,
+                    "hoverProvider": true,
+                    "documentSymbolProvider": true,
+                },
+                "serverInfo": {"name": "c1-lsp", "version": env!("CARGO_PKG_VERSION")},
+            })),
+            "shutdown" => {
+                self.shutdown_requested = true;
+                Ok(Value::Null)
+            }
+            "textDocument/definition" => {
+                let (uri, document, offset) = self.position_params(params)?;
+                let symbols = Symbols::new(document);
+                Ok(symbols
+                    .occurrence_at(offset)
+                    .and_then(|(name, _)| symbols.definition(name))
+                    .map(|definition| location(uri, document, &definition.name_span))
+                    .unwrap_or(Value::Null))
+            }
+            "textDocument/references" => {
+                let (uri, document, offset) = self.position_params(params)?;
+                let include_declaration = params["context"]["includeDeclaration"]
+                    .as_bool()
+                    .unwrap_or(true);
+                let symbols = Symbols::new(document);
+                let locations: Vec<Value> = match symbols.occurrence_at(offset) {
+                    Some((name, _)) => symbols
+                        .occurrences
+                        .iter()
+                        .filter(|occurrence| occurrence.name == name)
+                        .filter(|occurrence| include_declaration || !occurrence.is_definition)
+                        .map(|occurrence| location(uri, document, &occurrence.span))
+                        .collect(),
+                    None => vec![],
+                };
+                Ok(Value::from(locations))
+            }
+            "textDocument/hover" => {
+                let (_, document, offset) = self.position_params(params)?;
+                let symbols = Symbols::new(document);
+                Ok(symbols
+                    .occurrence_at(offset)
+                    .and_then(|(name, span)| Some((symbols.definition(name)?, span)))
+                    .map(|(definition, span)| {
+                        json!({
+                            "contents": {
+                                "kind": "markdown",
+                                "value": format!(
+                                    "```c\n{} {}()\n```",
+                                    definition.return_type, definition.name
+                                ),
+                            },
+                            "range": range(document, span),
+                        })
+                    })
+                    .unwrap_or(Value::Null))
+            }
+            "textDocument/documentSymbol" => {
+                let uri = document_uri(params)?;
+                let document = self.document(uri)?;
+                let symbols = Symbols::new(document);
+                Ok(Value::from(
+                    symbols
+                        .definitions
+                        .iter()
+                        .map(|definition| {
+                            json!({
+                                "name": definition.name,
+                                "detail": definition.return_type,
+                                "kind": SYMBOL_KIND_FUNCTION,
+                                "range": range(document, &definition.span),
+                                "selectionRange": range(document, &definition.name_span),
+                            })
+                        })
+                        .collect::<Vec<_>>(),
+                ))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method '{}'", method))),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.to_string(), text.to_string());
+                vec![publish_diagnostics(uri, text)]
+            }
+            "textDocument/didChange" => {
+                // With full synchronisation, the last change contains the whole document
+                let changes = params["contentChanges"].as_array();
+                match changes.and_then(|changes| changes.last()?["text"].as_str()) {
+                    Some(text) => {
+                        self.documents.insert(uri.to_string(), text.to_string());
+                        vec![publish_diagnostics(uri, text)]
+                    }
+                    None => vec![],
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                vec![json!({
+                    "jsonrpc": "2.0",
+                    "method": "textDocument/publishDiagnostics",
+                    "params": {"uri": uri, "diagnostics": []},
+                })]
+            }
+            "exit" => {
+                self.exit_code = Some(if self.shutdown_requested { 0 } else { 1 });
+                vec![]
+            }
+            // "initialized", "$/cancelRequest" and everything else needs no reaction
+            _ => vec![],
+        }
+    }
+
+    fn document(&self, uri: &str) -> Result<&str, (i64, String)> {
+        self.documents
+            .get(uri)
+            .map(String::as_str)
+            .ok_or_else(|| (INVALID_PARAMS, format!("document '{}' is not open", uri)))
+    }
+
+    /// Extract the document and the byte offset of a TextDocumentPositionParams
+    fn position_params<'a>(
+        &'a self,
+        params: &'a Value,
+    ) -> Result<(&'a str, &'a str, usize), (i64, String)> {
+        let uri = document_uri(params)?;
+        let document = self.document(uri)?;
+        let position = &params["position"];
+        match (position["line"].as_u64(), position["character"].as_u64()) {
+            (Some(line), Some(character)) => Ok((
+                uri,
+                document,
+                offset(document, line as usize, character as usize),
+            )),
+            _ => Err((INVALID_PARAMS, "missing position".to_string())),
+        }
+    }
+}
+
+fn document_uri(params: &Value) -> Result<&str, (i64, String)> {
+    params["textDocument"]["uri"]
+        .as_str()
+        .ok_or_else(|| (INVALID_PARAMS, "missing text document".to_string()))
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
+
+fn publish_diagnostics(uri: &str, text: &str) -> Value {
+    let mut sources = SourceMap::new();
+    sources.add(uri, text);
+    let diagnostics: Vec<Value> = match sources.parse_program(Default::default()) {
+        Err(error) => vec![json!({
+            "range": range(text, &error.span),
+            "severity": 1,
+            "source": "c1",
+            "message": error.to_string(),
+        })],
+        Ok(program) => LintRegistry::default()
+            .run(&program)
+            .into_iter()
+            .map(|warning| {
+                let span = sources.line_span(warning.line).unwrap_or_default();
+                json!({
+                    "range": range(text, &span),
+                    "severity": 2,
+                    "source": "c1",
+                    "code": warning.code,
+                    "message": warning.message,
+                })
+            })
+            .collect(),
+    };
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": {"uri": uri, "diagnostics": diagnostics},
+    })
+}
+
+fn location(uri: &str, text: &str, span: &Range<usize>) -> Value {
+    json!({"uri": uri, "range": range(text, span)})
+}
+
+fn range(text: &str, span: &Range<usize>) -> Value {
+    json!({"start": position(text, span.start), "end": position(text, span.end)})
+}
+
+/// Convert a byte offset into an LSP position, which counts characters in UTF-16 code units
+fn position(text: &str, offset: usize) -> Value {
+    let offset = offset.min(text.len());
+    let line_start = text[..offset].rfind('\n').map_or(0, |index| index + 1);
+    json!({
+        "line": text[..line_start].matches('\n').count(),
+        "character": text[line_start..offset].encode_utf16().count(),
+    })
+}
+
+/// Convert an LSP position into a byte offset, clamped to the line and the text
+fn offset(text: &str, line: usize, character: usize) -> usize {
+    let line_start = match line {
+        0 => 0,
+        _ => match text.match_indices('\n').nth(line - 1) {
+            Some((index, _)) => index + 1,
+            None => return text.len(),
+        },
+    };
+    let mut units = 0;
+    for (index, char) in text[line_start..].char_indices() {
+        if units >= character || char == '\n' {
+            return line_start + index;
+        }
+        units += char.len_utf16();
+    }
+    text.len()
+}
+
+/// A function definition found in the token stream
+struct Definition<'a> {
+    name: &'a str,
+    return_type: &'a str,
+    /// From the return type up to and including the closing brace of the body
+    span: Range<usize>,
+    name_span: Range<usize>,
+}
+
+/// A function identifier in a definition or call
+struct Occurrence<'a> {
+    name: &'a str,
+    span: Range<usize>,
+    is_definition: bool,
+}
+
+/// The function identifiers of a document. An identifier followed by "(" names a function; it is
+/// defined there if a type precedes it and called otherwise.
+struct Symbols<'a> {
+    definitions: Vec<Definition<'a>>,
+    occurrences: Vec<Occurrence<'a>>,
+}
+
+impl<'a> Symbols<'a> {
+    fn new(text: &'a str) -> Symbols<'a> {
+        let mut tokens = Vec::new();
+        let mut lexer = C1Lexer::new(text);
+        while let (Some(token), Some(span)) = (lexer.current_token(), lexer.current_span()) {
+            tokens.push((token, &text[span.clone()], span));
+            lexer.eat();
+        }
+
+        let mut symbols = Symbols {
+            definitions: Vec::new(),
+            occurrences: Vec::new(),
+        };
+        for (index, (token, name, span)) in tokens.iter().enumerate() {
+            let is_call_or_definition = *token == C1Token::Identifier
+                && matches!(
+                    tokens.get(index + 1),
+                    Some((C1Token::LeftParenthesis, _, _))
+                );
+            if !is_call_or_definition {
+                continue;
+            }
+            let return_type = match index.checked_sub(1).map(|previous| &tokens[previous]) {
+                Some((
+                    C1Token::KwBoolean | C1Token::KwFloat | C1Token::KwInt | C1Token::KwVoid,
+                    text,
+                    span,
+                )) => Some((*text, span.start)),
+                _ => None,
+            };
+            symbols.occurrences.push(Occurrence {
+                name,
+                span: span.clone(),
+                is_definition: return_type.is_some(),
+            });
+            if let Some((return_type, start)) = return_type {
+                symbols.definitions.push(Definition {
+                    name,
+                    return_type,
+                    span: start..body_end(&tokens[index..], text.len()),
+                    name_span: span.clone(),
+                });
+            }
+        }
+        symbols
+    }
+
+    /// Return the name and span of the function identifier at the offset
+    fn occurrence_at(&self, offset: usize) -> Option<(&'a str, &Range<usize>)> {
+        self.occurrences
+            .iter()
+            .find(|occurrence| occurrence.span.start <= offset && offset <= occurrence.span.end)
+            .map(|occurrence| (occurrence.name, &occurrence.span))
+    }
+
+    fn definition(&self, name: &str) -> Option<&Definition<'a>> {
+        self.definitions
+            .iter()
+            .find(|definition| definition.name == name)
+    }
+}
+
+/// Return the end of the brace that closes the first "{" of the tokens, or `end` if it is never
+/// closed
+fn body_end(tokens: &[(C1Token, &str, Range<usize>)], end: usize) -> usize {
+    let mut depth = 0;
+    for (token, _, span) in tokens {
+        match token {
+            C1Token::LeftBrace => depth += 1,
+            C1Token::RightBrace if depth == 1 => return span.end,
+            C1Token::RightBrace if depth > 1 => depth -= 1,
+            _ => {}
+        }
+    }
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lsp::{offset, position};
+    use serde_json::json;
+
+    #[test]
+    fn positions_count_utf16_code_units() {
+        let text = "a\n// ä𝄞\nxy";
+        assert_eq!(position(text, 0), json!({"line": 0, "character": 0}));
+        assert_eq!(position(text, 2), json!({"line": 1, "character": 0}));
+        // 'ä' takes two bytes and one code unit, '𝄞' four bytes and two code units
+        assert_eq!(position(text, 11), json!({"line": 1, "character": 6}));
+        assert_eq!(position(text, 13), json!({"line": 2, "character": 1}));
+
+        assert_eq!(offset(text, 1, 6), 11);
+        assert_eq!(offset(text, 2, 1), 13);
+        // Positions past the end of a line or the text are clamped
+        assert_eq!(offset(text, 0, 10), 1);
+        assert_eq!(offset(text, 7, 0), text.len());
+    }
+}
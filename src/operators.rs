@@ -0,0 +1,115 @@
+//! The operators of expressions as data, from which [`C1Parser`](crate::C1Parser) parses
+//! expressions by precedence climbing.
+//!
+//! Each precedence level stands for one rule of the grammar: in C(-1), level 1 is `expr`, level
+//! 2 `simpexpr` and level 3 `term`, and the operands of the highest level are `factor`s:
+//! ```
+//! use cb_3::operators::{Associativity, Fixity, Operator, C1_OPERATORS};
+//! use cb_3::C1Token;
+//!
+//! let minus = Operator::find(C1_OPERATORS, C1Token::Minus, Fixity::Prefix).unwrap();
+//! assert_eq!((minus.precedence, minus.associativity), (2, Associativity::None));
+//! let less = Operator::find(C1_OPERATORS, C1Token::Less, Fixity::Infix).unwrap();
+//! assert_eq!((less.precedence, less.associativity), (1, Associativity::None));
+//! ```
+use crate::lexer::C1Token;
+use C1Token::*;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Fixity {
+    /// Written before its operand, e.g. `-x`
+    Prefix,
+    /// Written between its operands, e.g. `x - y`
+    Infix,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Associativity {
+    /// `a - b - c` is `(a - b) - c`
+    Left,
+    /// `a = b = c` is `a = (b = c)`. A prefix operator may be applied to itself, `- -a`.
+    Right,
+    /// `a < b < c` is an error. A prefix operator may not be applied to itself.
+    None,
+}
+
+/// An operator of expressions
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Operator {
+    pub token: C1Token,
+    pub fixity: Fixity,
+    /// How tightly the operator binds, higher binds tighter. Levels start at 1. An infix
+    /// operator's operands are expressions of the next higher level, a prefix operator's operand
+    /// as well, and a prefix operator may only start an expression of its own level or a lower
+    /// one.
+    pub precedence: usize,
+    pub associativity: Associativity,
+}
+
+/// The operators of C(-1):
+/// ```text
+/// expr     ::= simpexpr ( ( "==" | "!=" | "<=" | ">=" | "<" | ">" ) simpexpr )?
+/// simpexpr ::= ( "-" )? term ( ( "+" | "-" | "||" ) term )*
+/// term     ::= factor ( ( "*" | "/" | "&&" ) factor )*
+/// ```
+pub const C1_OPERATORS: &[Operator] = &[
+    Operator::infix(Equal, 1, Associativity::None),
+    Operator::infix(NotEqual, 1, Associativity::None),
+    Operator::infix(LessEqual, 1, Associativity::None),
+    Operator::infix(GreaterEqual, 1, Associativity::None),
+    Operator::infix(Less, 1, Associativity::None),
+    Operator::infix(Greater, 1, Associativity::None),
+    Operator::prefix(Minus, 2, Associativity::None),
+    Operator::infix(Plus, 2, Associativity::Left),
+    Operator::infix(Minus, 2, Associativity::Left),
+    Operator::infix(Or, 2, Associativity::Left),
+    Operator::infix(Asterisk, 3, Associativity::Left),
+    Operator::infix(Slash, 3, Associativity::Left),
+    Operator::infix(And, 3, Associativity::Left),
+];
+
+impl Operator {
+    pub const fn prefix(token: C1Token, precedence: usize, associativity: Associativity) -> Self {
+        Operator {
+            token,
+            fixity: Fixity::Prefix,
+            precedence,
+            associativity,
+        }
+    }
+
+    pub const fn infix(token: C1Token, precedence: usize, associativity: Associativity) -> Self {
+        Operator {
+            token,
+            fixity: Fixity::Infix,
+            precedence,
+            associativity,
+        }
+    }
+
+    /// Return the operator of the table written with the token
+    pub fn find(table: &[Operator], token: C1Token, fixity: Fixity) -> Option<Operator> {
+        table
+            .iter()
+            .find(|operator| operator.token == token && operator.fixity == fixity)
+            .copied()
+    }
+
+    /// Return the highest precedence level of the table
+    pub fn levels(table: &[Operator]) -> usize {
+        table
+            .iter()
+            .map(|operator| operator.precedence)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Return the level of the operands: a right-associative operator's right operand may
+    /// contain the operator again
+    pub fn operand_precedence(self) -> usize {
+        match self.associativity {
+            Associativity::Right => self.precedence,
+            Associativity::Left | Associativity::None => self.precedence + 1,
+        }
+    }
+}
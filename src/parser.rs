@@ -1,244 +1,1142 @@
-use crate::lexer::{C1Lexer, C1Token};
+use crate::ast::*;
+use crate::lexer::{C1Lexer, C1Token, Token};
+use crate::operators::{Associativity, Fixity, Operator, C1_OPERATORS};
 use crate::ParseResult;
-use std::ops::{Deref, DerefMut};
+use std::fmt;
+use std::ops::{Deref, DerefMut, Range};
 
 use C1Token::*;
 
-pub struct C1Parser<'a>(C1Lexer<'a>);
+/// Result of a single grammar rule
+type RuleResult<T> = Result<T, ParseError>;
+
+/// Bounds on the input a parser accepts, so that untrusted text cannot crash it or keep it busy
+/// for long. Exceeding a limit is a syntax error with the reason "nesting too deep", "too many
+/// tokens" or "input too large".
+/// ```
+/// use cb_3::{C1Parser, ParserLimits};
+///
+/// let limits = ParserLimits {
+///     max_tokens: 10,
+///     ..ParserLimits::default()
+/// };
+/// assert!(C1Parser::parse_program_with_limits("void f() { g(); }", limits).is_ok());
+/// let error = C1Parser::parse_program_with_limits("void f() { g(); g(); }", limits).unwrap_err();
+/// assert_eq!(error.reason, "too many tokens");
+/// ```
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct ParserLimits {
+    /// How deeply blocks, i.e. statements including the bodies of if statements and loops, and
+    /// assignments, including the expressions in parentheses, may be nested. Each of them takes
+    /// recursive calls, so deeper nesting, e.g. by thousands of "(", would overflow the stack.
+    /// The default of 128 is safe on a 2 MiB stack and allows e.g. `x = ((...(1)...));` with 126
+    /// pairs of parentheses.
+    pub max_nesting: usize,
+    /// How many tokens the text may consist of, not counting whitespace and comments
+    pub max_tokens: usize,
+    /// How many bytes the text may be long
+    pub max_bytes: usize,
+}
+
+impl Default for ParserLimits {
+    /// Limit the nesting only, the default cannot know how large inputs may be
+    fn default() -> Self {
+        ParserLimits {
+            max_nesting: 128,
+            max_tokens: usize::MAX,
+            max_bytes: usize::MAX,
+        }
+    }
+}
+
+/// The language a parser accepts
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub enum Dialect {
+    /// Exactly the grammar in c-1-syntax.ebnf
+    #[default]
+    CMinus1,
+    /// C(-1) with else branches, while, do-while and for loops, parameters and arguments,
+    /// declarations of local and global variables and printing strings
+    C1,
+}
+
+/// How a parser is configured
+/// ```
+/// use cb_3::{C1Parser, Dialect, ParserOptions};
+///
+/// let text = "void f(int n) { while (n > 0) n = n - 1; }";
+/// let error = C1Parser::parse_program(text).unwrap_err();
+/// assert_eq!(error.reason, "parameters are not part of C(-1)");
+///
+/// let options = ParserOptions {
+///     dialect: Dialect::C1,
+///     ..ParserOptions::default()
+/// };
+/// let program = C1Parser::with_options(text, options).program().unwrap();
+/// assert_eq!(program.functions[0].parameters[0].name, "n");
+/// ```
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct ParserOptions {
+    pub dialect: Dialect,
+    pub limits: ParserLimits,
+    /// The operators of expressions, their levels correspond to the rules `expr`, `simpexpr`
+    /// and `term`. Only `-` may be a prefix operator and only the tokens of [`BinaryOperator`]s
+    /// infix operators; other entries are syntax errors where their tokens appear.
+    pub operators: &'static [Operator],
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        ParserOptions {
+            dialect: Dialect::default(),
+            limits: ParserLimits::default(),
+            operators: C1_OPERATORS,
+        }
+    }
+}
+
+/// A syntax error, located at the token where parsing failed
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParseError {
+    /// What went wrong, e.g. "unexpected token"
+    pub reason: String,
+    /// Line of the offending token, None if the text ended unexpectedly
+    pub line: Option<usize>,
+    /// Text of the offending token, None if the text ended unexpectedly
+    pub text: Option<String>,
+    /// Byte range of the offending token. At the end of the text, this is the empty range after
+    /// the last byte.
+    pub span: Range<usize>,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.line, &self.text) {
+            (Some(line), Some(text)) => write!(
+                f,
+                "{} at line {:?} with text: '{}'",
+                self.reason, line, text
+            ),
+            _ => write!(f, "{}. Reached EOF", self.reason),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// A rule of the C1 grammar in c-1-syntax.ebnf, or of the extensions of [`Dialect::C1`], each
+/// parsed by one method of C1Parser
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum Rule {
+    Program,
+    FunctionDefinition,
+    FunctionCall,
+    StatementList,
+    Block,
+    Statement,
+    IfStatement,
+    ReturnStatement,
+    Printf,
+    Type,
+    ParameterList,
+    WhileStatement,
+    DoWhileStatement,
+    ForStatement,
+    DeclAssignment,
+    StatAssignment,
+    Assignment,
+    Expr,
+    SimpExpr,
+    Term,
+    Factor,
+}
+
+impl Rule {
+    /// Return the name of the rule in the grammar
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::Program => "program",
+            Rule::FunctionDefinition => "functiondefinition",
+            Rule::FunctionCall => "functioncall",
+            Rule::StatementList => "statementlist",
+            Rule::Block => "block",
+            Rule::Statement => "statement",
+            Rule::IfStatement => "ifstatement",
+            Rule::ReturnStatement => "returnstatement",
+            Rule::Printf => "printf",
+            Rule::Type => "type",
+            Rule::ParameterList => "parameterlist",
+            Rule::WhileStatement => "whilestatement",
+            Rule::DoWhileStatement => "dowhilestatement",
+            Rule::ForStatement => "forstatement",
+            Rule::DeclAssignment => "declassignment",
+            Rule::StatAssignment => "statassignment",
+            Rule::Assignment => "assignment",
+            Rule::Expr => "expr",
+            Rule::SimpExpr => "simpexpr",
+            Rule::Term => "term",
+            Rule::Factor => "factor",
+        }
+    }
+
+    /// Whether the rule counts towards [`ParserLimits::max_nesting`]: every recursion of the
+    /// grammar passes a block, which is any statement of a statement list or a body, or an
+    /// assignment. Programs and function definitions do not
+    /// count, so function definitions parsed on their own hit the limit where they do in a
+    /// program.
+    fn nests(self) -> bool {
+        matches!(self, Rule::Block | Rule::Assignment)
+    }
+}
+
+/// A token as reported in a ParseEvent
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct EventToken<'t> {
+    pub token: C1Token,
+    pub text: &'t str,
+    pub line: usize,
+    pub span: Range<usize>,
+}
+
+/// What C1Parser reports to a listener while it parses
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ParseEvent<'t> {
+    /// A rule is entered at the current token, which is None at the end of the text
+    Enter {
+        rule: Rule,
+        token: Option<EventToken<'t>>,
+    },
+    /// The current token is consumed
+    Token(EventToken<'t>),
+    /// A rule is left at the current token, with the error if it failed
+    Exit {
+        rule: Rule,
+        token: Option<EventToken<'t>>,
+        error: Option<&'t ParseError>,
+    },
+}
+
+/// One line of a parser trace, see [`C1Parser::parse_program_with_trace`]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TraceEntry {
+    /// Number of enclosing rules
+    pub depth: usize,
+    pub rule: Rule,
+    pub step: TraceStep,
+    /// The current token and its line, None at the end of the text
+    pub token: Option<(String, usize)>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum TraceStep {
+    Enter,
+    /// The rule was parsed successfully
+    Accept,
+    /// The rule failed with the error
+    Reject(ParseError),
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let step = match self.step {
+            TraceStep::Enter => "enter",
+            TraceStep::Accept => "accept",
+            TraceStep::Reject(_) => "reject",
+        };
+        write!(
+            f,
+            "{:indent$}{} {}",
+            "",
+            step,
+            self.rule.name(),
+            indent = 2 * self.depth
+        )?;
+        match &self.token {
+            Some((text, line)) => write!(f, " at line {} '{}'", line, text)?,
+            None => write!(f, " at EOF")?,
+        }
+        if let TraceStep::Reject(error) = &self.step {
+            write!(f, ": {}", error.reason)?;
+        }
+        Ok(())
+    }
+}
+
+/// Receiver of the events of a parse
+pub type ParseListener<'l> = dyn FnMut(ParseEvent<'_>) + 'l;
+
+/// A [`ParseEvent`] of an attempt, kept until the attempt succeeds
+enum PendingEvent<'a> {
+    Enter {
+        rule: Rule,
+        token: Option<EventToken<'a>>,
+    },
+    Token(EventToken<'a>),
+    Exit {
+        rule: Rule,
+        token: Option<EventToken<'a>>,
+        error: Option<ParseError>,
+    },
+}
+
+impl PendingEvent<'_> {
+    fn event(&self) -> ParseEvent<'_> {
+        match self {
+            PendingEvent::Enter { rule, token } => ParseEvent::Enter {
+                rule: *rule,
+                token: token.clone(),
+            },
+            PendingEvent::Token(token) => ParseEvent::Token(token.clone()),
+            PendingEvent::Exit { rule, token, error } => ParseEvent::Exit {
+                rule: *rule,
+                token: token.clone(),
+                error: error.as_ref(),
+            },
+        }
+    }
+}
+
+pub struct C1Parser<'a> {
+    lexer: C1Lexer<'a>,
+    listener: Option<&'a mut ParseListener<'a>>,
+    /// Events of the attempts in progress, reported to the listener once they succeed
+    pending: Option<Vec<PendingEvent<'a>>>,
+    options: ParserOptions,
+    /// Number of nested rules currently being parsed that count towards the nesting limit
+    depth: usize,
+    /// Number of tokens consumed so far
+    tokens: usize,
+}
 // Implement Deref and DerefMut to enable the direct use of the lexer's methods
 impl<'a> Deref for C1Parser<'a> {
     type Target = C1Lexer<'a>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.lexer
     }
 }
 
 impl<'a> DerefMut for C1Parser<'a> {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.lexer
     }
 }
 
 impl<'a> C1Parser<'a> {
     pub fn parse(text: &str) -> ParseResult {
+        Self::parse_program(text).map(|_| ())
+    }
+
+    /// Parse the given text and return its syntax tree
+    pub fn parse_program(text: &str) -> Result<Program, ParseError> {
         let mut parser = Self::initialize_parser(text);
         parser.program()
     }
 
-    fn initialize_parser(text: &str) -> C1Parser {
-        C1Parser(C1Lexer::new(text))
+    /// Like parse_program, but fail if the text exceeds the given limits instead of the default
+    /// ones
+    pub fn parse_program_with_limits(
+        text: &str,
+        limits: ParserLimits,
+    ) -> Result<Program, ParseError> {
+        let options = ParserOptions {
+            limits,
+            ..ParserOptions::default()
+        };
+        C1Parser::with_options(text, options).program()
+    }
+
+    /// Initialize a parser for the text with the given options. Parse the text with
+    /// [`program`](C1Parser::program).
+    pub fn with_options(text: &'a str, options: ParserOptions) -> Self {
+        let mut parser = Self::initialize_parser(text);
+        parser.options = options;
+        parser
+    }
+
+    /// Parse the tokens of the given text that [`tokenize`](crate::tokenize) returned, without
+    /// lexing the text again
+    /// ```
+    /// use cb_3::{tokenize, C1Parser};
+    ///
+    /// let text = "void main() { printf(1); }";
+    /// let tokens = tokenize(text).unwrap();
+    /// assert_eq!(C1Parser::parse_tokens(text, &tokens), C1Parser::parse_program(text));
+    /// ```
+    pub fn parse_tokens(text: &str, tokens: &[Token]) -> Result<Program, ParseError> {
+        C1Parser::from_lexer(C1Lexer::from_tokens(text, tokens)).program()
+    }
+
+    /// Like parse_program, but report each rule entered and left and each token consumed to the
+    /// listener
+    /// ```
+    /// use cb_3::{C1Parser, ParseEvent};
+    ///
+    /// let mut rules = Vec::new();
+    /// let mut listener = |event: ParseEvent| {
+    ///     if let ParseEvent::Enter { rule, .. } = event {
+    ///         rules.push(rule.name());
+    ///     }
+    /// };
+    /// C1Parser::parse_program_with_listener("void f() {}", &mut listener).unwrap();
+    /// assert_eq!(rules, ["program", "functiondefinition", "type", "statementlist"]);
+    /// ```
+    pub fn parse_program_with_listener(
+        text: &'a str,
+        listener: &'a mut ParseListener<'a>,
+    ) -> Result<Program, ParseError> {
+        Self::with_listener(text, listener).program()
+    }
+
+    /// Like parse_program, but report each rule entered and exited with its outcome to `trace`.
+    /// Productions that are tried and abandoned do not show up in the trace.
+    /// ```
+    /// use cb_3::C1Parser;
+    ///
+    /// let mut trace = Vec::new();
+    /// let result = C1Parser::parse_program_with_trace("int f() { return }", &mut |entry| {
+    ///     trace.push(entry.to_string());
+    /// });
+    /// assert!(result.is_err());
+    /// assert_eq!(trace[0], "enter program at line 1 'int'");
+    /// assert_eq!(trace[1], "  enter functiondefinition at line 1 'int'");
+    /// assert_eq!(
+    ///     trace[trace.len() - 2],
+    ///     "  reject functiondefinition at line 1 '}': unexpected token"
+    /// );
+    /// ```
+    pub fn parse_program_with_trace(
+        text: &str,
+        trace: &mut dyn FnMut(TraceEntry),
+    ) -> Result<Program, ParseError> {
+        let mut depth = 0;
+        let mut listener = |event: ParseEvent| {
+            let entry = match event {
+                ParseEvent::Enter { rule, token } => {
+                    depth += 1;
+                    TraceEntry {
+                        depth: depth - 1,
+                        rule,
+                        step: TraceStep::Enter,
+                        token: token.map(|token| (token.text.to_string(), token.line)),
+                    }
+                }
+                ParseEvent::Token(_) => return,
+                ParseEvent::Exit { rule, token, error } => {
+                    depth -= 1;
+                    TraceEntry {
+                        depth,
+                        rule,
+                        step: match error {
+                            Some(error) => TraceStep::Reject(error.clone()),
+                            None => TraceStep::Accept,
+                        },
+                        token: token.map(|token| (token.text.to_string(), token.line)),
+                    }
+                }
+            };
+            trace(entry);
+        };
+        C1Parser::with_listener(text, &mut listener).program()
     }
-	fn expect_token(&mut self, token: C1Token) -> ParseResult {
-		if self.current_matches(&token) {
+
+    fn initialize_parser(text: &str) -> C1Parser<'_> {
+        C1Parser::from_lexer(C1Lexer::new(text))
+    }
+
+    /// Initialize a parser that continues at the lexer's current token
+    pub(crate) fn from_lexer(lexer: C1Lexer<'a>) -> C1Parser<'a> {
+        C1Parser {
+            lexer,
+            listener: None,
+            pending: None,
+            options: ParserOptions::default(),
+            depth: 0,
+            tokens: 0,
+        }
+    }
+
+    pub(crate) fn with_listener(text: &'a str, listener: &'a mut ParseListener<'a>) -> Self {
+        C1Parser {
+            lexer: C1Lexer::new(text),
+            listener: Some(listener),
+            pending: None,
+            options: ParserOptions::default(),
+            depth: 0,
+            tokens: 0,
+        }
+    }
+
+    /// Consume all remaining tokens, e.g. to report them to the listener after a syntax error
+    pub(crate) fn skip_rest(&mut self) {
+        while self.current_token().is_some() {
             self.eat();
-            Ok(())
+        }
+    }
+
+    /// Parse a rule and report entering and leaving it to the listener
+    fn rule<T>(
+        &mut self,
+        rule: Rule,
+        parse: impl FnOnce(&mut Self) -> RuleResult<T>,
+    ) -> RuleResult<T> {
+        let nests = rule.nests();
+        if nests {
+            self.check_nesting()?;
         } else {
-            Err(self.error_message_current("unexpected token"))
+            self.check_token_count()?;
         }
-	}
-	// TODO here
-    // program ::= ( functiondefinition )* <EOF>
-    fn program(&mut self) -> ParseResult {
-		while let Some(_) = self.current_token(){
-			self.function_definition()?;
-		}
-        ParseResult::Ok(())
-    }
-	// functiondefinition  ::= type <ID> "(" ")" "{" statementlist "}"
-	// <ID> identifier
-	fn function_definition(&mut self) -> ParseResult {
-		self.return_type()?;
-		self.expect_token(Identifier)?;
-		self.expect_token(LeftParenthesis)?;
-		self.expect_token(RightParenthesis)?;
-		self.expect_token(LeftBrace)?;
-		self.statement_list()?;
-		self.expect_token(RightBrace)?;
-		Result::Ok(())
-    }
-	// functioncall ::= <ID> "(" ")"
-	fn function_call(&mut self) -> ParseResult {
-        self.expect_token(Identifier)?;
-		self.expect_token(LeftParenthesis)?;
-		self.expect_token(RightParenthesis)?;
-		Result::Ok(())
-    }
-	// statementlist ::= ( block )*
-	fn statement_list(&mut self) -> ParseResult {
-        while !self.current_empty_or_matches(&RightBrace){
-			self.block()?;
-		}
-		Result::Ok(())
-    }
-	// block ::= "{" statementlist "}" | statement
-	fn block(&mut self) -> ParseResult {
-        if self.current_matches(&LeftBrace){
-			self.eat();
-			self.statement_list()?;
-			self.expect_token(RightBrace)
-		} 
-		else {
-			self.statement()
-		}
-    }
-	/*statement       ::= ifstatement
-                      | returnstatement ";"
-                      | printf ";"
-                      | statassignment ";"
-                      | functioncall ";" */
-	fn statement(&mut self) -> ParseResult {
-        if self.current_matches(&KwIf){
-			self.if_statement()
-		}
-		else if self.current_matches(&KwReturn){
-			self.return_statement()?;
-			self.expect_token(Semicolon)
-		}
-		else if self.current_matches(&KwPrintf){
-			self.printf()?;
-			self.expect_token(Semicolon)
-		}
-		else if self.current_matches(&Identifier){
-			if self.next_matches(&Assign){
-				self.stat_assignment()?;
-			} else {
-				self.function_call()?;
-			}
-			self.expect_token(Semicolon)
-		}
-		else{
-			Result::Err(self.error_message_current("empty statement"))
-		}
-    }
-	//ifstatement ::= <KW_IF> "(" assignment ")" block
-	fn if_statement(&mut self) -> ParseResult {
-        self.expect_token(KwIf)?;
-		self.expect_token(LeftParenthesis)?;
-		self.assignment()?;
-		self.expect_token(RightParenthesis)?;
-		self.block()?;
-		Result::Ok(())
-    }
-	// returnstatement ::= <KW_RETURN> ( assignment )?
-	fn return_statement(&mut self) -> ParseResult {
-        self.expect_token(KwReturn)?;
-		if self.current_empty_or_matches(&Semicolon){
-			Result::Ok(())
-		}
-		else {
-			self.assignment()
-		}
-    }
-	// printf ::= <KW_PRINTF> "(" assignment ")"
-	fn printf(&mut self) -> ParseResult {
-        self.expect_token(KwPrintf)?;
-		self.expect_token(LeftParenthesis)?;
-		self.assignment()?;
-		self.expect_token(RightParenthesis)?;
-		Result::Ok(())
-    }
-	fn return_type(&mut self) -> ParseResult {
-        self.any_match_and_eat(&[KwBoolean,KwFloat,KwInt, KwVoid], &self.error_message_current("unexpected type"))
-    }
-	// statassignment ::= <ID> "=" assignment
-	fn stat_assignment(&mut self) -> ParseResult {
-        self.expect_token(Identifier)?;
-		self.expect_token(Assign)?;
-		self.assignment()
-    }
-	// assignment ::= ( ( <ID> "=" assignment ) | expr )
-	fn assignment(&mut self) -> ParseResult {
-		if self.current_matches(&Identifier) && self.next_matches(&Assign){
-			self.eat(); 
-			self.eat();
-			self.assignment()
-		}else{
-			self.expr()
-		}
-    }
-	// expr ::= simpexpr ( ( "==" | "!=" | "<=" | ">=" | "<" | ">" ) simpexpr )?
-	fn expr(&mut self) -> ParseResult {
-        self.simpexpr()?;
-		if self.any_match_current(&[Equal,NotEqual, Less, Greater, LessEqual, GreaterEqual]){
-			self.eat();
-			self.simpexpr()
-		}
-		else{
-			Result::Ok(())
-		}
-    }
-	// simpexpr ::= ( "-" )? term ( ( "+" | "-" | "||" ) term )*
-	fn simpexpr(&mut self) -> ParseResult {
-        if self.current_matches(&Minus){
-			self.eat();
-		}
-		self.term()?;
-		while self.any_match_current(&[Plus,Minus,Or]){
-			self.eat();
-			self.term()?;
-		}
-		Result::Ok(())
-    }
-	// term ::= factor ( ( "*" | "/" | "&&" ) factor )*
-	fn term(&mut self) -> ParseResult {
-		self.factor()?;
-		while self.any_match_current(&[Asterisk,Slash,And]){
-			self.eat();
-			self.factor()?;
-		}
-		Result::Ok(())
-    }
-	// factor / atom
-	fn factor(&mut self) -> ParseResult {
-        if self.any_match_current(&[ConstInt, ConstFloat, ConstBoolean]){
-			self.eat();
-			return Result::Ok(());
-		}
-		if self.current_matches(&Identifier){
-			if self.next_matches(&LeftParenthesis){
-				self.function_call()
-			}
-			else {
-				self.eat();
-				Result::Ok(())
-			}
-		}
-		else{
-			self.expect_token(LeftParenthesis)?;
-			self.assignment()?;
-			self.expect_token(RightParenthesis)?;
-			Result::Ok(())
-		}
-    }
-	
-
-    /// Check whether the current token is equal to the given token. If yes, consume it, otherwise
-    /// return an error with the given error message
-    fn check_and_eat_token(&mut self, token: &C1Token, error_message: &str) -> ParseResult {
-        if self.current_matches(token) {
+        if self.listener.is_some() {
+            self.enter(rule);
+        }
+        self.depth += nests as usize;
+        let result = parse(self);
+        self.depth -= nests as usize;
+        if self.listener.is_some() {
+            self.exit(rule, result.as_ref().err());
+        }
+        result
+    }
+
+    // Reporting is kept out of rule, whose frames make up the stack of deeply nested texts
+    fn enter(&mut self, rule: Rule) {
+        let token = event_token(&self.lexer);
+        self.report(PendingEvent::Enter { rule, token });
+    }
+
+    fn exit(&mut self, rule: Rule, error: Option<&ParseError>) {
+        let token = event_token(&self.lexer);
+        let error = error.cloned();
+        self.report(PendingEvent::Exit { rule, token, error });
+    }
+
+    /// Report an event to the listener, or keep it while an attempt is in progress
+    fn report(&mut self, event: PendingEvent<'a>) {
+        match (&mut self.pending, &mut self.listener) {
+            (Some(pending), _) => pending.push(event),
+            (None, Some(listener)) => listener(event.event()),
+            (None, None) => {}
+        }
+    }
+
+    /// Parse a production that is no rule of the grammar but nests, e.g. an extra level of the
+    /// operator table or the operand of a right-associative operator, within the nesting limit
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> RuleResult<T>) -> RuleResult<T> {
+        self.check_nesting()?;
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Fail if entering another rule would nest too deep or more tokens were consumed than
+    /// allowed
+    fn check_nesting(&self) -> ParseResult {
+        if self.depth == self.options.limits.max_nesting {
+            return Err(self.error_message_current("nesting too deep"));
+        }
+        self.check_token_count()
+    }
+
+    /// Try to parse a production. If it fails, rewind to where the attempt started and return
+    /// None, so that another production can be tried; the error is dropped. The listener only
+    /// sees the events of a successful attempt, which are kept until the attempt succeeds.
+    /// Productions that one or two tokens of lookahead decide do not need it.
+    /// ```
+    /// use cb_3::{C1Parser, C1Token, ParseError};
+    ///
+    /// // A cast "(" type ")", which C1 does not have, else a parenthesised expression
+    /// let mut parser = C1Parser::with_options("(x) + 1", Default::default());
+    /// let cast = parser.attempt(|parser| {
+    ///     parser.eat();
+    ///     let cast = parser.current_token() == Some(C1Token::KwFloat);
+    ///     match (cast, parser.peek_token()) {
+    ///         (true, Some(C1Token::RightParenthesis)) => Ok("float"),
+    ///         _ => Err(ParseError {
+    ///             reason: "not a cast".to_string(),
+    ///             line: parser.current_line_number(),
+    ///             text: parser.current_text().map(str::to_string),
+    ///             span: parser.current_span().unwrap_or_default(),
+    ///         }),
+    ///     }
+    /// });
+    /// assert_eq!(cast, None);
+    /// assert_eq!(parser.current_text(), Some("("));
+    /// ```
+    pub fn attempt<T>(
+        &mut self,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Option<T> {
+        let checkpoint = self.lexer.checkpoint();
+        let tokens = self.tokens;
+        let outer = self.pending.replace(Vec::new());
+        let result = parse(self);
+        let events = std::mem::replace(&mut self.pending, outer).unwrap_or_default();
+        match result {
+            Ok(value) => {
+                for event in events {
+                    self.report(event);
+                }
+                Some(value)
+            }
+            Err(_) => {
+                self.lexer.rewind(checkpoint);
+                self.tokens = tokens;
+                None
+            }
+        }
+    }
+
+    /// Fail if more tokens were consumed than allowed. Checked on entering each rule and at the
+    /// end of the text; no rule consumes more than a handful of tokens before entering the next.
+    fn check_token_count(&self) -> ParseResult {
+        if self.tokens > self.options.limits.max_tokens {
+            return Err(self.error_message_current("too many tokens"));
+        }
+        Ok(())
+    }
+
+    /// Fail if the text is longer than allowed, at the token that crosses the limit
+    fn check_input_size(&mut self) -> ParseResult {
+        if self.source().len() <= self.options.limits.max_bytes {
+            return Ok(());
+        }
+        while let Some(span) = self.current_span() {
+            if span.end > self.options.limits.max_bytes {
+                break;
+            }
+            self.lexer.eat();
+        }
+        Err(self.error_message_current("input too large"))
+    }
+
+    /// Consume the current token and report it to the listener
+    fn eat(&mut self) {
+        if self.listener.is_some() {
+            if let Some(token) = event_token(&self.lexer) {
+                self.report(PendingEvent::Token(token));
+            }
+        }
+        self.lexer.eat();
+        self.tokens += 1;
+    }
+
+    fn expect_token(&mut self, token: C1Token) -> ParseResult {
+        if self.current_matches(&token) {
             self.eat();
             Ok(())
         } else {
-            Err(String::from(error_message))
+            Err(self.error_message_current("unexpected token"))
         }
     }
 
-    /// For each token in the given slice, check whether the token is equal to the current token,
-    /// consume the current token, and check the next token in the slice against the next token
-    /// provided by the lexer.
-    /* fn check_and_eat_tokens(&mut self, token: &[C1Token], error_message: &str) -> ParseResult {
-        match token
-            .iter()
-            .map(|t| self.check_and_eat_token(t, error_message))
-            .filter(ParseResult::is_err)
-            .last()
-        {
-            None => Ok(()),
-            Some(err) => err,
+    /// Like expect_token(Identifier), but return the identifier's text
+    fn expect_identifier(&mut self) -> RuleResult<String> {
+        match self.current_text() {
+            Some(text) if self.current_matches(&Identifier) => {
+                let name = text.to_string();
+                self.eat();
+                Ok(name)
+            }
+            _ => Err(self.error_message_current("expected identifier")),
         }
     }
-	*/
+
+    // program ::= ( functiondefinition )* <EOF>
+    // C1: program ::= ( declassignment ";" | functiondefinition )* <EOF>
+    /// Parse the whole text as program
+    pub fn program(&mut self) -> Result<Program, ParseError> {
+        self.check_input_size()?;
+        self.rule(Rule::Program, |parser| {
+            let mut functions = Vec::new();
+            let mut globals = Vec::new();
+            while parser.current_token().is_some() {
+                if parser.options.dialect == Dialect::C1 && !parser.starts_function_definition() {
+                    globals.push(parser.decl_assignment()?);
+                    parser.expect_token(Semicolon)?;
+                } else {
+                    functions.push(parser.function_definition()?);
+                }
+            }
+            parser.check_token_count()?;
+            Ok(Program { functions, globals })
+        })
+    }
+
+    // functiondefinition  ::= type <ID> "(" ")" "{" statementlist "}"
+    // C1: functiondefinition ::= type <ID> "(" ( parameterlist )? ")" "{" statementlist "}"
+    pub(crate) fn function_definition(&mut self) -> RuleResult<FunctionDefinition> {
+        self.rule(Rule::FunctionDefinition, |parser| {
+            let return_type = parser.return_type()?;
+            let line = parser.line();
+            let name = parser.expect_identifier()?;
+            if matches!(parser.current_token(), Some(Assign | Semicolon)) {
+                parser.require_c1("global variables are not part of C(-1)")?;
+            }
+            parser.expect_token(LeftParenthesis)?;
+            let parameters = if parser.current_is_type() {
+                parser.require_c1("parameters are not part of C(-1)")?;
+                parser.parameter_list()?
+            } else {
+                Vec::new()
+            };
+            parser.expect_token(RightParenthesis)?;
+            parser.expect_token(LeftBrace)?;
+            let body = parser.statement_list()?;
+            parser.expect_token(RightBrace)?;
+            Ok(FunctionDefinition {
+                return_type,
+                name,
+                parameters,
+                body,
+                line,
+            })
+        })
+    }
+
+    // C1: parameterlist ::= type <ID> ( "," type <ID> )*
+    fn parameter_list(&mut self) -> RuleResult<Vec<Parameter>> {
+        self.rule(Rule::ParameterList, |parser| {
+            let mut parameters = Vec::new();
+            loop {
+                let line = parser.line();
+                let parameter_type = parser.return_type()?;
+                let name = parser.expect_identifier()?;
+                parameters.push(Parameter {
+                    parameter_type,
+                    name,
+                    line,
+                });
+                if !parser.current_matches(&Comma) {
+                    return Ok(parameters);
+                }
+                parser.eat();
+            }
+        })
+    }
+
+    // functioncall ::= <ID> "(" ")"
+    // C1: functioncall ::= <ID> "(" ( assignment ( "," assignment )* )? ")"
+    fn function_call(&mut self) -> RuleResult<(String, Vec<Expression>)> {
+        self.rule(Rule::FunctionCall, |parser| {
+            let name = parser.expect_identifier()?;
+            parser.expect_token(LeftParenthesis)?;
+            let mut arguments = Vec::new();
+            if parser.starts_expression() {
+                parser.require_c1("arguments are not part of C(-1)")?;
+                arguments.push(parser.assignment()?);
+                while parser.current_matches(&Comma) {
+                    parser.eat();
+                    arguments.push(parser.assignment()?);
+                }
+            }
+            parser.expect_token(RightParenthesis)?;
+            Ok((name, arguments))
+        })
+    }
+
+    // statementlist ::= ( block )*
+    fn statement_list(&mut self) -> RuleResult<Vec<Statement>> {
+        self.rule(Rule::StatementList, |parser| {
+            let mut statements = Vec::new();
+            while !parser.current_empty_or_matches(&RightBrace) {
+                statements.push(parser.block()?);
+            }
+            Ok(statements)
+        })
+    }
+
+    // block ::= "{" statementlist "}" | statement
+    fn block(&mut self) -> RuleResult<Statement> {
+        self.rule(Rule::Block, |parser| {
+            if parser.current_matches(&LeftBrace) {
+                let line = parser.line();
+                parser.eat();
+                let statements = parser.statement_list()?;
+                parser.expect_token(RightBrace)?;
+                Ok(Statement {
+                    kind: StatementKind::Block(statements),
+                    line,
+                })
+            } else {
+                parser.statement()
+            }
+        })
+    }
+
+    /*statement       ::= ifstatement
+    | returnstatement ";"
+    | printf ";"
+    | statassignment ";"
+    | functioncall ";"
+    C1 adds:
+    | forstatement
+    | whilestatement
+    | dowhilestatement ";"
+    | declassignment ";" */
+    fn statement(&mut self) -> RuleResult<Statement> {
+        self.rule(Rule::Statement, |parser| {
+            let line = parser.line();
+            let kind = match parser.current_token() {
+                Some(KwIf) => return parser.if_statement(),
+                Some(KwWhile) => {
+                    parser.require_c1("`while` is not part of C(-1)")?;
+                    return parser.while_statement();
+                }
+                Some(KwFor) => {
+                    parser.require_c1("`for` is not part of C(-1)")?;
+                    return parser.for_statement();
+                }
+                Some(KwDo) => {
+                    parser.require_c1("`do` is not part of C(-1)")?;
+                    parser.do_while_statement()?
+                }
+                Some(KwReturn) => parser.return_statement()?,
+                Some(KwPrintf) => parser.printf()?,
+                Some(KwBoolean | KwFloat | KwInt | KwVoid) => {
+                    parser.require_c1("declarations are not part of C(-1)")?;
+                    StatementKind::Declaration(parser.decl_assignment()?)
+                }
+                Some(Identifier) if parser.next_matches(&Assign) => parser.stat_assignment()?,
+                Some(Identifier) => {
+                    let (function, arguments) = parser.function_call()?;
+                    StatementKind::Call {
+                        function,
+                        arguments,
+                    }
+                }
+                _ => return Err(parser.error_message_current("empty statement")),
+            };
+            parser.expect_token(Semicolon)?;
+            Ok(Statement { kind, line })
+        })
+    }
+
+    // ifstatement ::= <KW_IF> "(" assignment ")" block
+    // C1: ifstatement ::= <KW_IF> "(" assignment ")" block ( <KW_ELSE> block )?
+    fn if_statement(&mut self) -> RuleResult<Statement> {
+        self.rule(Rule::IfStatement, |parser| {
+            let line = parser.line();
+            parser.expect_token(KwIf)?;
+            parser.expect_token(LeftParenthesis)?;
+            let condition = parser.assignment()?;
+            parser.expect_token(RightParenthesis)?;
+            let body = Box::new(parser.block()?);
+            // An else belongs to the innermost if
+            let else_body = if parser.current_matches(&KwElse) {
+                parser.require_c1("`else` is not part of C(-1)")?;
+                parser.eat();
+                Some(Box::new(parser.block()?))
+            } else {
+                None
+            };
+            Ok(Statement {
+                kind: StatementKind::If {
+                    condition,
+                    body,
+                    else_body,
+                },
+                line,
+            })
+        })
+    }
+
+    // C1: whilestatement ::= <KW_WHILE> "(" assignment ")" block
+    fn while_statement(&mut self) -> RuleResult<Statement> {
+        self.rule(Rule::WhileStatement, |parser| {
+            let line = parser.line();
+            parser.expect_token(KwWhile)?;
+            parser.expect_token(LeftParenthesis)?;
+            let condition = parser.assignment()?;
+            parser.expect_token(RightParenthesis)?;
+            let body = Box::new(parser.block()?);
+            Ok(Statement {
+                kind: StatementKind::While { condition, body },
+                line,
+            })
+        })
+    }
+
+    // C1: dowhilestatement ::= <KW_DO> block <KW_WHILE> "(" assignment ")"
+    fn do_while_statement(&mut self) -> RuleResult<StatementKind> {
+        self.rule(Rule::DoWhileStatement, |parser| {
+            parser.expect_token(KwDo)?;
+            let body = Box::new(parser.block()?);
+            parser.expect_token(KwWhile)?;
+            parser.expect_token(LeftParenthesis)?;
+            let condition = parser.assignment()?;
+            parser.expect_token(RightParenthesis)?;
+            Ok(StatementKind::DoWhile { body, condition })
+        })
+    }
+
+    /*C1: forstatement ::= <KW_FOR> "(" ( statassignment | declassignment ) ";" expr ";"
+    statassignment ")" block */
+    fn for_statement(&mut self) -> RuleResult<Statement> {
+        self.rule(Rule::ForStatement, |parser| {
+            let line = parser.line();
+            parser.expect_token(KwFor)?;
+            parser.expect_token(LeftParenthesis)?;
+            let init_line = parser.line();
+            let init = if parser.current_is_type() {
+                StatementKind::Declaration(parser.decl_assignment()?)
+            } else {
+                parser.stat_assignment()?
+            };
+            parser.expect_token(Semicolon)?;
+            let condition = parser.expr()?;
+            parser.expect_token(Semicolon)?;
+            let step_line = parser.line();
+            let step = parser.stat_assignment()?;
+            parser.expect_token(RightParenthesis)?;
+            let body = Box::new(parser.block()?);
+            Ok(Statement {
+                kind: StatementKind::For {
+                    init: Box::new(Statement {
+                        kind: init,
+                        line: init_line,
+                    }),
+                    condition,
+                    step: Box::new(Statement {
+                        kind: step,
+                        line: step_line,
+                    }),
+                    body,
+                },
+                line,
+            })
+        })
+    }
+
+    // C1: declassignment ::= type <ID> ( "=" assignment )?
+    fn decl_assignment(&mut self) -> RuleResult<Declaration> {
+        self.rule(Rule::DeclAssignment, |parser| {
+            let line = parser.line();
+            let variable_type = parser.return_type()?;
+            let name = parser.expect_identifier()?;
+            let value = if parser.current_matches(&Assign) {
+                parser.eat();
+                Some(parser.assignment()?)
+            } else {
+                None
+            };
+            Ok(Declaration {
+                variable_type,
+                name,
+                value,
+                line,
+            })
+        })
+    }
+
+    // returnstatement ::= <KW_RETURN> ( assignment )?
+    fn return_statement(&mut self) -> RuleResult<StatementKind> {
+        self.rule(Rule::ReturnStatement, |parser| {
+            parser.expect_token(KwReturn)?;
+            if parser.current_empty_or_matches(&Semicolon) {
+                Ok(StatementKind::Return(None))
+            } else {
+                Ok(StatementKind::Return(Some(parser.assignment()?)))
+            }
+        })
+    }
+
+    // printf ::= <KW_PRINTF> "(" assignment ")"
+    // C1: printf ::= <KW_PRINTF> "(" ( assignment | <CONST_STRING> ) ")"
+    fn printf(&mut self) -> RuleResult<StatementKind> {
+        self.rule(Rule::Printf, |parser| {
+            parser.expect_token(KwPrintf)?;
+            parser.expect_token(LeftParenthesis)?;
+            let value = match parser.current_text() {
+                Some(text) if parser.current_matches(&ConstString) => {
+                    parser.require_c1("strings are not part of C(-1)")?;
+                    let value = Expression {
+                        kind: ExpressionKind::String(text[1..text.len() - 1].to_string()),
+                        line: parser.line(),
+                    };
+                    parser.eat();
+                    value
+                }
+                _ => parser.assignment()?,
+            };
+            parser.expect_token(RightParenthesis)?;
+            Ok(StatementKind::Printf(value))
+        })
+    }
+
+    // type ::= <KW_BOOLEAN> | <KW_FLOAT> | <KW_INT> | <KW_VOID>
+    fn return_type(&mut self) -> RuleResult<Type> {
+        self.rule(Rule::Type, |parser| {
+            let return_type = match parser.current_token() {
+                Some(KwBoolean) => Type::Boolean,
+                Some(KwFloat) => Type::Float,
+                Some(KwInt) => Type::Int,
+                Some(KwVoid) => Type::Void,
+                _ => return Err(parser.error_message_current("unexpected type")),
+            };
+            parser.eat();
+            Ok(return_type)
+        })
+    }
+
+    // statassignment ::= <ID> "=" assignment
+    fn stat_assignment(&mut self) -> RuleResult<StatementKind> {
+        self.rule(Rule::StatAssignment, |parser| {
+            let target = parser.expect_identifier()?;
+            parser.expect_token(Assign)?;
+            let value = parser.assignment()?;
+            Ok(StatementKind::Assignment { target, value })
+        })
+    }
+
+    // assignment ::= ( ( <ID> "=" assignment ) | expr )
+    fn assignment(&mut self) -> RuleResult<Expression> {
+        self.rule(Rule::Assignment, |parser| {
+            // Only the "=" tells the target of an assignment from a variable in an expression
+            if parser.current_matches(&Identifier) && parser.next_matches(&Assign) {
+                let line = parser.line();
+                let target = parser.expect_identifier()?;
+                parser.eat();
+                let value = Box::new(parser.assignment()?);
+                Ok(Expression {
+                    kind: ExpressionKind::Assign { target, value },
+                    line,
+                })
+            } else {
+                parser.expr()
+            }
+        })
+    }
+
+    // expr ::= simpexpr ( ( "==" | "!=" | "<=" | ">=" | "<" | ">" ) simpexpr )?
+    fn expr(&mut self) -> RuleResult<Expression> {
+        self.operation(1)
+    }
+
+    /// Parse an expression of a precedence level of the operator table: operands of the next
+    /// level joined by the infix operators of this level, the first one possibly preceded by a
+    /// prefix operator of this level. The operands of the highest level are factors.
+    fn operation(&mut self, precedence: usize) -> RuleResult<Expression> {
+        if precedence > Operator::levels(self.options.operators) {
+            return self.factor();
+        }
+        let parse = move |parser: &mut Self| {
+            let line = parser.line();
+            let mut lhs = match parser.operator(Fixity::Prefix, precedence) {
+                Some(operator) => {
+                    // Only "-" has a node of the syntax tree
+                    if operator.token != Minus {
+                        return Err(parser.error_message_current("not a prefix operator"));
+                    }
+                    parser.eat();
+                    let operand = Box::new(parser.operand(operator)?);
+                    Expression {
+                        kind: ExpressionKind::Negate(operand),
+                        line,
+                    }
+                }
+                None => parser.operation(precedence + 1)?,
+            };
+            while let Some(operator) = parser.operator(Fixity::Infix, precedence) {
+                let binary = parser.binary_operator()?;
+                let rhs = parser.operand(operator)?;
+                lhs = Expression::binary(binary, lhs, rhs);
+                if operator.associativity == Associativity::None {
+                    break;
+                }
+            }
+            Ok(lhs)
+        };
+        // The levels of C(-1) are rules of the grammar, extra levels are not
+        match [Rule::Expr, Rule::SimpExpr, Rule::Term].get(precedence - 1) {
+            Some(&rule) => self.rule(rule, parse),
+            None => self.nested(parse),
+        }
+    }
+
+    /// Parse the operand of a prefix operator or the right operand of an infix operator
+    fn operand(&mut self, operator: Operator) -> RuleResult<Expression> {
+        let precedence = operator.operand_precedence();
+        match operator.associativity {
+            // The operand may contain the operator again, which no rule counts
+            Associativity::Right => self.nested(|parser| parser.operation(precedence)),
+            Associativity::Left | Associativity::None => self.operation(precedence),
+        }
+    }
+
+    /*factor          ::= <CONST_INT>
+    | <CONST_FLOAT>
+    | <CONST_BOOLEAN>
+    | functioncall
+    | <ID>
+    | "(" assignment ")" */
+    fn factor(&mut self) -> RuleResult<Expression> {
+        self.rule(Rule::Factor, |parser| {
+            let line = parser.line();
+            let text = parser.current_text().unwrap_or_default();
+            let kind = match parser.current_token() {
+                Some(ConstInt) => match text.parse() {
+                    Ok(value) => ExpressionKind::Int(value),
+                    Err(_) => ExpressionKind::IntOutOfRange(text.to_string()),
+                },
+                Some(ConstFloat) => ExpressionKind::Float(text.parse().unwrap_or(f64::INFINITY)),
+                Some(ConstBoolean) => ExpressionKind::Bool(text == "true"),
+                Some(Identifier) if parser.next_matches(&LeftParenthesis) => {
+                    let (function, arguments) = parser.function_call()?;
+                    return Ok(Expression {
+                        kind: ExpressionKind::Call {
+                            function,
+                            arguments,
+                        },
+                        line,
+                    });
+                }
+                Some(Identifier) => ExpressionKind::Variable(text.to_string()),
+                _ => {
+                    parser.expect_token(LeftParenthesis)?;
+                    let inner = parser.assignment()?;
+                    parser.expect_token(RightParenthesis)?;
+                    return Ok(inner);
+                }
+            };
+            parser.eat();
+            Ok(Expression { kind, line })
+        })
+    }
+
+    /// Return the operator of the table with the precedence written with the current token
+    fn operator(&self, fixity: Fixity, precedence: usize) -> Option<Operator> {
+        Operator::find(self.options.operators, self.current_token()?, fixity)
+            .filter(|operator| operator.precedence == precedence)
+    }
+
+    /// Consume the current token and return the binary operator it is written with. Fail at
+    /// tokens that an operator table makes infix operators but that have no node of the syntax
+    /// tree.
+    fn binary_operator(&mut self) -> RuleResult<BinaryOperator> {
+        let operator = match self.current_token() {
+            Some(Equal) => BinaryOperator::Equal,
+            Some(NotEqual) => BinaryOperator::NotEqual,
+            Some(Less) => BinaryOperator::Less,
+            Some(Greater) => BinaryOperator::Greater,
+            Some(LessEqual) => BinaryOperator::LessEqual,
+            Some(GreaterEqual) => BinaryOperator::GreaterEqual,
+            Some(Plus) => BinaryOperator::Add,
+            Some(Minus) => BinaryOperator::Subtract,
+            Some(Or) => BinaryOperator::Or,
+            Some(Asterisk) => BinaryOperator::Multiply,
+            Some(Slash) => BinaryOperator::Divide,
+            Some(And) => BinaryOperator::And,
+            _ => return Err(self.error_message_current("not a binary operator")),
+        };
+        self.eat();
+        Ok(operator)
+    }
+
+    /// Line of the current token, or 0 at EOF
+    fn line(&self) -> usize {
+        self.current_line_number().unwrap_or_default()
+    }
 
     /// Check whether the given token matches the current token
     fn current_matches(&self, token: &C1Token) -> bool {
@@ -247,72 +1145,86 @@ impl<'a> C1Parser<'a> {
             Some(current) => current == token,
         }
     }
-	fn current_empty_or_matches(&self, token: &C1Token) -> bool {
+    fn current_empty_or_matches(&self, token: &C1Token) -> bool {
         match &self.current_token() {
             None => true,
             Some(current) => current == token,
         }
     }
 
-    /// Check whether the given token matches the next token
-    fn next_matches(&self, token: &C1Token) -> bool {
-        match &self.peek_token() {
+    /// Check whether the current token is one of the types
+    fn current_is_type(&self) -> bool {
+        matches!(
+            self.current_token(),
+            Some(KwBoolean | KwFloat | KwInt | KwVoid)
+        )
+    }
+
+    /// Check whether the current token may start an expression
+    fn starts_expression(&self) -> bool {
+        match self.current_token() {
+            Some(ConstInt | ConstFloat | ConstBoolean | Identifier | LeftParenthesis) => true,
+            Some(token) => Operator::find(self.options.operators, token, Fixity::Prefix).is_some(),
             None => false,
-            Some(next) => next == token,
         }
     }
 
-    /// Check whether any of the tokens matches the current token.
-    fn any_match_current(&self, token: &[C1Token]) -> bool {
-        token.iter().any(|t| self.current_matches(t))
+    /// Check whether the current token starts a function definition rather than the
+    /// declaration of a global variable, i.e. whether the third token is "("
+    fn starts_function_definition(&mut self) -> bool {
+        let checkpoint = self.lexer.checkpoint();
+        self.lexer.eat();
+        let third = self.peek_token();
+        self.lexer.rewind(checkpoint);
+        third == Some(LeftParenthesis)
     }
 
-    /// Check whether any of the tokens matches the current token, then consume it
-    fn any_match_and_eat(&mut self, token: &[C1Token], error_message: &str) -> ParseResult {
-        if token
-            .iter()
-            .any(|t| self.check_and_eat_token(t, "").is_ok())
-        {
-            Ok(())
-        } else {
-            Err(String::from(error_message))
+    /// Fail at the current token with the reason, unless the dialect is C1, which has the
+    /// construct starting there
+    fn require_c1(&self, reason: &'static str) -> ParseResult {
+        match self.options.dialect {
+            Dialect::C1 => Ok(()),
+            Dialect::CMinus1 => Err(self.error_message_current(reason)),
         }
     }
 
-    fn error_message_current(&self, reason: &'static str) -> String {
-        match self.current_token() {
-            None => format!("{}. Reached EOF", reason),
-            Some(_) => format!(
-                "{} at line {:?} with text: '{}'",
-                reason,
-                self.current_line_number().unwrap(),
-                self.current_text().unwrap()
-            ),
+    /// Check whether the given token matches the next token
+    fn next_matches(&self, token: &C1Token) -> bool {
+        match &self.peek_token() {
+            None => false,
+            Some(next) => next == token,
         }
     }
 
-    /*fn error_message_peek(&mut self, reason: &'static str) -> String {
-        match self.peek_token() {
-            None => format!("{}. Reached EOF", reason),
-            Some(_) => format!(
-                "{} at line {:?} with text: '{}'",
-                reason,
-                self.peek_line_number().unwrap(),
-                self.peek_text().unwrap()
-            ),
+    fn error_message_current(&self, reason: &'static str) -> ParseError {
+        let end = self.source().len();
+        ParseError {
+            reason: reason.to_string(),
+            line: self.current_line_number(),
+            text: self.current_text().map(str::to_string),
+            span: self.current_span().unwrap_or(end..end),
         }
     }
-	*/
+}
 
+/// Return the lexer's current token as reported to listeners
+fn event_token<'a>(lexer: &C1Lexer<'a>) -> Option<EventToken<'a>> {
+    Some(EventToken {
+        token: lexer.current_token()?,
+        text: lexer.current_text()?,
+        line: lexer.current_line_number()?,
+        span: lexer.current_span()?,
+    })
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::parser::{C1Parser, ParseResult};
+    use crate::parser::{C1Parser, ParseError, ParseResult, ParserLimits};
+    use crate::C1Token;
 
-    fn call_method<'a, F>(parse_method: F, text: &'static str) -> ParseResult
+    fn call_method<'a, F, T>(parse_method: F, text: &'static str) -> ParseResult
     where
-        F: Fn(&mut C1Parser<'a>) -> ParseResult,
+        F: Fn(&mut C1Parser<'a>) -> Result<T, ParseError>,
     {
         let mut parser = C1Parser::initialize_parser(text);
         if let Err(message) = parse_method(&mut parser) {
@@ -323,27 +1235,26 @@ mod tests {
         }
     }
 
-	// NOTE additional tests
-	#[test]
+    // NOTE additional tests
+    #[test]
     fn valid_statement() {
-		assert!(call_method(C1Parser::statement, "foo();").is_ok());
-		assert!(call_method(C1Parser::statement, "if(x==y){}").is_ok());
-		assert!(call_method(C1Parser::statement, "return x;").is_ok());
-		assert!(call_method(C1Parser::statement, "x=y;").is_ok());
-		assert!(call_method(C1Parser::statement, "x=a+b;").is_ok());
-	}
-	#[test]
+        assert!(call_method(C1Parser::statement, "foo();").is_ok());
+        assert!(call_method(C1Parser::statement, "if(x==y){}").is_ok());
+        assert!(call_method(C1Parser::statement, "return x;").is_ok());
+        assert!(call_method(C1Parser::statement, "x=y;").is_ok());
+        assert!(call_method(C1Parser::statement, "x=a+b;").is_ok());
+    }
+    #[test]
     fn valid_blocks() {
         assert!(call_method(C1Parser::block, "{}").is_ok());
-		assert!(call_method(C1Parser::block, "{}{}").is_ok());
-		assert!(call_method(C1Parser::block, "{{}}").is_ok());
-		assert!(call_method(C1Parser::block, "{} x=y;").is_ok());
-		assert!(call_method(C1Parser::block, "x=y;").is_ok());
-		assert!(call_method(C1Parser::block, "x=y;{}").is_ok());
+        assert!(call_method(C1Parser::block, "{}{}").is_ok());
+        assert!(call_method(C1Parser::block, "{{}}").is_ok());
+        assert!(call_method(C1Parser::block, "{} x=y;").is_ok());
+        assert!(call_method(C1Parser::block, "x=y;").is_ok());
+        assert!(call_method(C1Parser::block, "x=y;{}").is_ok());
         assert!(call_method(C1Parser::block, "if(x==y){}{}").is_ok());
     }
 
-
     #[test]
     fn parse_empty_program() {
         let result = C1Parser::parse("");
@@ -395,15 +1306,17 @@ mod tests {
         );
         assert!(result.is_ok());
 
-		let result = C1Parser::parse("int blub() {\n\
+        let result = C1Parser::parse(
+            "int blub() {\n\
 			blub1 = 23;\n\
 			blub2 = 17;\n\
 			blub3 = 42;\n\
 			blub4 = blub1 * (blub2 + blub3);\n\
 			if (blub1 < blub4) return blub2;\n\
 			return blub3;\n\
-		}");
-		assert!(result.is_ok());
+		}",
+        );
+        assert!(result.is_ok());
     }
 
     #[test]
@@ -571,6 +1484,185 @@ mod tests {
         assert!(call_method(C1Parser::factor, "bool").is_err());
     }
 
+    #[test]
+    fn failed_attempts_are_rolled_back() {
+        let mut parser = C1Parser::initialize_parser("x\n+ 1");
+        assert_eq!(parser.attempt(|parser| parser.stat_assignment()), None);
+        assert_eq!(parser.current_text(), Some("x"));
+        assert_eq!(parser.peek_line_number(), Some(2));
+        assert!(parser.expr().is_ok());
+        assert_eq!(parser.current_token(), None);
+    }
+
+    #[test]
+    fn listeners_see_successful_attempts_once() {
+        use crate::parser::ParseEvent;
+
+        let mut events = Vec::new();
+        let mut listener = |event: ParseEvent| {
+            events.push(match event {
+                ParseEvent::Enter { rule, .. } => format!("enter {}", rule.name()),
+                ParseEvent::Token(token) => token.text.to_string(),
+                ParseEvent::Exit { rule, error, .. } => {
+                    format!("exit {} {}", rule.name(), error.is_none())
+                }
+            })
+        };
+        let mut parser = C1Parser::with_listener("y = x + 1", &mut listener);
+        let result = parser.attempt(|parser| {
+            let factor = parser.attempt(|parser| {
+                parser.factor()?;
+                parser.expect_token(C1Token::Semicolon)
+            });
+            assert_eq!(factor, None);
+            parser.stat_assignment()
+        });
+        assert!(result.is_some());
+        drop(parser);
+        // The factor of the failed attempt would have been entered first
+        assert_eq!(events.first().unwrap(), "enter statassignment");
+        assert_eq!(events.last().unwrap(), "exit statassignment true");
+        let tokens: Vec<_> = events
+            .iter()
+            .filter(|event| !event.starts_with("enter") && !event.starts_with("exit"))
+            .collect();
+        assert_eq!(tokens, vec!["y", "=", "x", "+", "1"]);
+    }
+
+    #[test]
+    fn trace_shows_nesting_and_outcome() {
+        let mut trace = Vec::new();
+        let result = C1Parser::parse_program_with_trace("void f() {}\nint", &mut |entry| {
+            trace.push(entry.to_string())
+        });
+        assert!(result.is_err());
+        assert_eq!(
+            trace,
+            vec![
+                "enter program at line 1 'void'",
+                "  enter functiondefinition at line 1 'void'",
+                "    enter type at line 1 'void'",
+                "    accept type at line 1 'f'",
+                "    enter statementlist at line 1 '}'",
+                "    accept statementlist at line 1 '}'",
+                "  accept functiondefinition at line 2 'int'",
+                "  enter functiondefinition at line 2 'int'",
+                "    enter type at line 2 'int'",
+                "    accept type at EOF",
+                "  reject functiondefinition at EOF: expected identifier",
+                "reject program at EOF: expected identifier",
+            ]
+        );
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error() {
+        let parens = |n| format!("void f() {{ x = {}1{}; }}", "(".repeat(n), ")".repeat(n));
+        // The block of the statement and the assignment are nested as well
+        assert!(C1Parser::parse(&parens(126)).is_ok());
+        let error = C1Parser::parse(&parens(127)).unwrap_err();
+        assert_eq!(error.reason, "nesting too deep");
+        assert_eq!(error.text.as_deref(), Some("1"));
+
+        let blocks = format!("void f() {}{}", "{".repeat(5000), "}".repeat(5000));
+        assert_eq!(
+            C1Parser::parse(&blocks).unwrap_err().reason,
+            "nesting too deep"
+        );
+    }
+
+    #[test]
+    fn configured_limits() {
+        let limits = |max_nesting, max_tokens, max_bytes| ParserLimits {
+            max_nesting,
+            max_tokens,
+            max_bytes,
+        };
+        let text = "void f() {\n    x = (1);\n}\n";
+        let parse = |limits| C1Parser::parse_program_with_limits(text, limits);
+        // The block of the statement, the assignment and the parenthesis
+        assert!(parse(limits(3, 12, text.len())).is_ok());
+
+        let error = parse(limits(2, 12, text.len())).unwrap_err();
+        assert_eq!(error.reason, "nesting too deep");
+        assert_eq!(error.text.as_deref(), Some("1"));
+
+        // The closing brace is the twelfth token
+        let error = parse(limits(3, 11, text.len())).unwrap_err();
+        assert_eq!(error.reason, "too many tokens");
+        assert_eq!(error.line, None);
+        let error = parse(limits(3, 6, text.len())).unwrap_err();
+        assert_eq!(error.reason, "too many tokens");
+        assert_eq!(error.line, Some(2));
+
+        let error = parse(limits(3, 12, 18)).unwrap_err();
+        assert_eq!(error.reason, "input too large");
+        assert_eq!((error.line, error.text.as_deref()), (Some(2), Some("(")));
+        assert_eq!(error.span, 19..20);
+    }
+
+    #[test]
+    fn syntax_tree() {
+        use crate::ast::*;
+
+        let program = C1Parser::parse_program("int f() {\n x = -a * 2 + b < c;\n}").unwrap();
+        let function = &program.functions[0];
+        assert_eq!(function.return_type, Type::Int);
+        assert_eq!(function.name, "f");
+        assert_eq!(function.line, 1);
+
+        let variable = |name: &str| Expression {
+            kind: ExpressionKind::Variable(name.to_string()),
+            line: 2,
+        };
+        let product = Expression::binary(
+            BinaryOperator::Multiply,
+            variable("a"),
+            Expression {
+                kind: ExpressionKind::Int(2),
+                line: 2,
+            },
+        );
+        let negated = Expression {
+            kind: ExpressionKind::Negate(Box::new(product)),
+            line: 2,
+        };
+        let sum = Expression::binary(BinaryOperator::Add, negated, variable("b"));
+        assert_eq!(
+            function.body,
+            vec![Statement {
+                kind: StatementKind::Assignment {
+                    target: "x".to_string(),
+                    value: Expression::binary(BinaryOperator::Less, sum, variable("c")),
+                },
+                line: 2,
+            }]
+        );
+    }
+
+    #[test]
+    fn integer_constants_out_of_range() {
+        use crate::ast::{ExpressionKind, StatementKind};
+
+        let text = "void f() {\n x = 9223372036854775807;\n y = 99999999999999999999999;\n}";
+        let program = C1Parser::parse_program(text).unwrap();
+        let values: Vec<_> = program.functions[0]
+            .body
+            .iter()
+            .map(|statement| match &statement.kind {
+                StatementKind::Assignment { value, .. } => value.kind.clone(),
+                kind => panic!("{:?}", kind),
+            })
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                ExpressionKind::Int(i64::MAX),
+                ExpressionKind::IntOutOfRange("99999999999999999999999".to_string())
+            ]
+        );
+    }
+
     #[test]
     fn multiple_functions() {
         assert!(call_method(
@@ -579,4 +1671,150 @@ mod tests {
         )
         .is_ok());
     }
+
+    #[test]
+    fn operator_tables() {
+        use crate::operators::{Associativity, Operator, C1_OPERATORS};
+        use crate::sexp::Sexp;
+
+        let parse = |operators: &'static [Operator], text: &str| {
+            let mut parser = C1Parser::initialize_parser(text);
+            parser.options.operators = operators;
+            let result = parser.expr();
+            match (result, parser.current_token()) {
+                (Ok(expression), None) => Ok(Sexp(&expression).to_string()),
+                (Ok(_), Some(_)) => Err(parser.current_text().unwrap().to_string()),
+                (Err(error), _) => Err(error.text.unwrap_or_default()),
+            }
+        };
+        let c1 = |text| parse(C1_OPERATORS, text);
+        assert_eq!(c1("a - b - c"), Ok("(- (- a b) c)".to_string()));
+        assert_eq!(c1("-a * b + c"), Ok("(+ (- (* a b)) c)".to_string()));
+        assert_eq!(c1("a || b && c"), Ok("(|| a (&& b c))".to_string()));
+        assert_eq!(
+            c1("a < -b + c * d"),
+            Ok("(< a (+ (- b) (* c d)))".to_string())
+        );
+        assert_eq!(c1("a < b < c"), Err("<".to_string()));
+        assert_eq!(c1("- -a"), Err("-".to_string()));
+        assert_eq!(c1("a * -b"), Err("-".to_string()));
+
+        // A fourth level for a right-associative negation, and comparisons that chain
+        const OPERATORS: &[Operator] = &[
+            Operator::infix(C1Token::Less, 1, Associativity::Left),
+            Operator::infix(C1Token::Plus, 2, Associativity::Left),
+            Operator::infix(C1Token::Asterisk, 3, Associativity::Left),
+            Operator::prefix(C1Token::Minus, 4, Associativity::Right),
+        ];
+        let custom = |text| parse(OPERATORS, text);
+        assert_eq!(custom("a < b < c"), Ok("(< (< a b) c)".to_string()));
+        assert_eq!(custom("- -a * b"), Ok("(* (- (- a)) b)".to_string()));
+        assert_eq!(custom("a * -b"), Ok("(* a (- b))".to_string()));
+        assert_eq!(custom("a - b"), Err("-".to_string()));
+    }
+
+    #[test]
+    fn dialects() {
+        use crate::parser::{Dialect, ParserOptions};
+        use crate::sexp::Sexp;
+
+        let c1 = |text| {
+            let options = ParserOptions {
+                dialect: Dialect::C1,
+                ..ParserOptions::default()
+            };
+            C1Parser::with_options(text, options).program()
+        };
+        // C(-1) fails at the token where C1 goes on, and says why
+        for (text, reason, token) in [
+            ("int x = 1;", "global variables are not part of C(-1)", "="),
+            (
+                "void f(int a) {}",
+                "parameters are not part of C(-1)",
+                "int",
+            ),
+            (
+                "void f() { g(1, 2); }",
+                "arguments are not part of C(-1)",
+                "1",
+            ),
+            (
+                "void f() { x = g(-1); }",
+                "arguments are not part of C(-1)",
+                "-",
+            ),
+            (
+                "void f() { int x; }",
+                "declarations are not part of C(-1)",
+                "int",
+            ),
+            (
+                "void f() { while (x) {} }",
+                "`while` is not part of C(-1)",
+                "while",
+            ),
+            (
+                "void f() { do {} while (x); }",
+                "`do` is not part of C(-1)",
+                "do",
+            ),
+            (
+                "void f() { for (i = 0; i; i = 1) {} }",
+                "`for` is not part of C(-1)",
+                "for",
+            ),
+            (
+                "void f() { if (x) {} else {} }",
+                "`else` is not part of C(-1)",
+                "else",
+            ),
+            (
+                "void f() { printf(\"x\"); }",
+                "strings are not part of C(-1)",
+                "\"x\"",
+            ),
+        ] {
+            let error = C1Parser::parse_program(text).unwrap_err();
+            assert_eq!(
+                (error.reason.as_str(), error.text.unwrap()),
+                (reason, token.into())
+            );
+            assert!(c1(text).is_ok(), "{}", text);
+        }
+        assert_eq!(
+            C1Parser::parse("void f() { g(; }").unwrap_err().reason,
+            "unexpected token"
+        );
+
+        let program = c1(include_str!("../tests/data/beispiel.c1")).unwrap();
+        assert_eq!(program.globals[0].name, "calls");
+        assert_eq!(program.functions[1].parameters.len(), 2);
+        let sexp = |text| Sexp(&c1(text).unwrap()).to_string();
+        assert_eq!(
+            sexp("int x = 1; bool y; void f() {}"),
+            "(program (var int x 1) (var bool y) (fn void f))"
+        );
+        assert_eq!(
+            sexp("void f() { if (a) if (b) g(); else h(a, b = 1); }"),
+            "(program (fn void f (if a (if b (call g) (call h a (assign b 1))))))"
+        );
+        assert_eq!(
+            sexp("void f() { for (i = 0; i < 3; i = i + 1) do printf(\"a\"); while (0); }"),
+            "(program (fn void f (for (assign i 0) (< i 3) (assign i (+ i 1)) \
+             (do (printf \"a\") 0))))"
+        );
+
+        // Strings are only printed, and the condition of a for loop is no assignment
+        for (text, token) in [
+            ("void f() { x = \"a\"; }", Some("\"a\"")),
+            ("void f() { printf(\"a\" + 1); }", Some("+")),
+            ("void f() { for (i = 0; i = 1; i = 2) {} }", Some("=")),
+            ("void f() { for (i = 0; i < 1; ) {} }", Some(")")),
+            ("void f() { do {} while (x) }", Some("}")),
+            ("void f(int) {}", Some(")")),
+            ("int x = 1", None),
+        ] {
+            assert_eq!(c1(text).unwrap_err().text.as_deref(), token, "{}", text);
+        }
+    }
 }
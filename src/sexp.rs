@@ -0,0 +1,193 @@
+//! Compact S-expression printer for syntax trees.
+//!
+//! Each function definition becomes `(fn <type> <name> <parameter>... <statement>...)`, each
+//! parameter `(param <type> <name>)`, each declaration `(var <type> <name> <value>?)` and each
+//! other statement and compound expression a list headed by its keyword or operator. Constants
+//! and variables are printed as atoms:
+//! ```
+//! use cb_3::sexp::Sexp;
+//! use cb_3::C1Parser;
+//!
+//! let program = C1Parser::parse_program("int blub() { blub1 = 23; return -blub1 + 1; }").unwrap();
+//! assert_eq!(
+//!     Sexp(&program).to_string(),
+//!     "(program (fn int blub (assign blub1 23) (return (+ (- blub1) 1))))"
+//! );
+//! ```
+use crate::ast::{
+    Declaration, Expression, ExpressionKind, FunctionDefinition, Program, Statement, StatementKind,
+};
+use std::fmt;
+
+/// Displays a syntax tree as S-expression
+#[derive(Debug, Copy, Clone)]
+pub struct Sexp<'a, T>(pub &'a T);
+
+impl fmt::Display for Sexp<'_, Program> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("(program")?;
+        for declaration in &self.0.globals {
+            write!(f, " {}", Sexp(declaration))?;
+        }
+        for function in &self.0.functions {
+            write!(f, " {}", Sexp(function))?;
+        }
+        f.write_str(")")
+    }
+}
+
+impl fmt::Display for Sexp<'_, FunctionDefinition> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let function = self.0;
+        write!(
+            f,
+            "(fn {} {}",
+            function.return_type.keyword(),
+            function.name
+        )?;
+        for parameter in &function.parameters {
+            write!(
+                f,
+                " (param {} {})",
+                parameter.parameter_type.keyword(),
+                parameter.name
+            )?;
+        }
+        for statement in &function.body {
+            write!(f, " {}", Sexp(statement))?;
+        }
+        f.write_str(")")
+    }
+}
+
+impl fmt::Display for Sexp<'_, Statement> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0.kind {
+            StatementKind::Block(statements) => {
+                f.write_str("(block")?;
+                for statement in statements {
+                    write!(f, " {}", Sexp(statement))?;
+                }
+                f.write_str(")")
+            }
+            StatementKind::If {
+                condition,
+                body,
+                else_body: None,
+            } => write!(f, "(if {} {})", Sexp(condition), Sexp(body.as_ref())),
+            StatementKind::If {
+                condition,
+                body,
+                else_body: Some(else_body),
+            } => write!(
+                f,
+                "(if {} {} {})",
+                Sexp(condition),
+                Sexp(body.as_ref()),
+                Sexp(else_body.as_ref())
+            ),
+            StatementKind::While { condition, body } => {
+                write!(f, "(while {} {})", Sexp(condition), Sexp(body.as_ref()))
+            }
+            StatementKind::DoWhile { body, condition } => {
+                write!(f, "(do {} {})", Sexp(body.as_ref()), Sexp(condition))
+            }
+            StatementKind::For {
+                init,
+                condition,
+                step,
+                body,
+            } => write!(
+                f,
+                "(for {} {} {} {})",
+                Sexp(init.as_ref()),
+                Sexp(condition),
+                Sexp(step.as_ref()),
+                Sexp(body.as_ref())
+            ),
+            StatementKind::Return(None) => f.write_str("(return)"),
+            StatementKind::Return(Some(value)) => write!(f, "(return {})", Sexp(value)),
+            StatementKind::Printf(value) => write!(f, "(printf {})", Sexp(value)),
+            StatementKind::Declaration(declaration) => write!(f, "{}", Sexp(declaration)),
+            StatementKind::Assignment { target, value } => {
+                write!(f, "(assign {} {})", target, Sexp(value))
+            }
+            StatementKind::Call {
+                function,
+                arguments,
+            } => write_call(f, function, arguments),
+        }
+    }
+}
+
+impl fmt::Display for Sexp<'_, Declaration> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let declaration = self.0;
+        write!(
+            f,
+            "(var {} {}",
+            declaration.variable_type.keyword(),
+            declaration.name
+        )?;
+        if let Some(value) = &declaration.value {
+            write!(f, " {}", Sexp(value))?;
+        }
+        f.write_str(")")
+    }
+}
+
+impl fmt::Display for Sexp<'_, Expression> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0.kind {
+            ExpressionKind::Assign { target, value } => {
+                write!(f, "(assign {} {})", target, Sexp(value.as_ref()))
+            }
+            ExpressionKind::Binary { operator, lhs, rhs } => write!(
+                f,
+                "({} {} {})",
+                operator.symbol(),
+                Sexp(lhs.as_ref()),
+                Sexp(rhs.as_ref())
+            ),
+            ExpressionKind::Negate(operand) => write!(f, "(- {})", Sexp(operand.as_ref())),
+            ExpressionKind::Int(value) => write!(f, "{}", value),
+            ExpressionKind::IntOutOfRange(digits) => write!(f, "{}", digits),
+            // Debug keeps the fractional part of whole numbers, e.g. "1.0"
+            ExpressionKind::Float(value) => write!(f, "{:?}", value),
+            ExpressionKind::Bool(value) => write!(f, "{}", value),
+            ExpressionKind::String(value) => write!(f, "\"{}\"", value),
+            ExpressionKind::Call {
+                function,
+                arguments,
+            } => write_call(f, function, arguments),
+            ExpressionKind::Variable(name) => f.write_str(name),
+        }
+    }
+}
+
+fn write_call(f: &mut fmt::Formatter<'_>, function: &str, arguments: &[Expression]) -> fmt::Result {
+    write!(f, "(call {}", function)?;
+    for argument in arguments {
+        write!(f, " {}", Sexp(argument))?;
+    }
+    f.write_str(")")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sexp::Sexp;
+    use crate::C1Parser;
+
+    #[test]
+    fn statements_and_expressions() {
+        let program = C1Parser::parse_program(
+            "void main() { if (a = b() <= 1.0 && true) { printf(.5); return; } f(); }",
+        )
+        .unwrap();
+        assert_eq!(
+            Sexp(&program.functions[0]).to_string(),
+            "(fn void main (if (assign a (<= (call b) (&& 1.0 true))) \
+             (block (printf 0.5) (return))) (call f))"
+        );
+    }
+}
@@ -0,0 +1,255 @@
+use crate::ast::Program;
+use crate::incremental::LineShift;
+use crate::parser::{C1Parser, ParseError, ParserOptions};
+use crate::visit::VisitorMut;
+use std::fmt;
+use std::ops::Range;
+
+/// The source files of a program, e.g. to report errors in a program spanning several files.
+///
+/// Byte offsets and line numbers run through all files: each file starts one byte after the end
+/// of the previous file and on the line after its last line. So an offset or a line alone tells
+/// the file, and [`locate`](SourceMap::locate) turns it into a file, line and column using an
+/// index of the line starts.
+///
+/// ```
+/// use cb_3::source::SourceMap;
+///
+/// let mut sources = SourceMap::new();
+/// sources.add("a.c1", "void a() {}\n");
+/// sources.add("b.c1", "void b() {\n  x = 1 2;\n}\n");
+/// let error = sources.parse_program(Default::default()).unwrap_err();
+/// assert_eq!(error.line, Some(4));
+/// assert_eq!(sources.locate(error.span.start).unwrap().to_string(), "b.c1:2:9");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+    /// Byte offset of the start of each line, through all files
+    line_starts: Vec<usize>,
+}
+
+/// Identifies a file of a [`SourceMap`]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct FileId(usize);
+
+/// A named text of a [`SourceMap`]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SourceFile {
+    name: String,
+    text: String,
+    /// Byte offset of the first byte
+    start: usize,
+    /// Line number of the first line
+    first_line: usize,
+}
+
+/// A position in a source file. Lines and columns are counted from 1 within the file, columns in
+/// characters.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Location<'a> {
+    pub file: &'a SourceFile,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl SourceMap {
+    pub fn new() -> SourceMap {
+        SourceMap::default()
+    }
+
+    /// Add a file behind the files added before
+    pub fn add(&mut self, name: impl Into<String>, text: impl Into<String>) -> FileId {
+        let start = self.files.last().map_or(0, |file| file.span().end + 1);
+        let text = text.into();
+        let file = SourceFile {
+            name: name.into(),
+            first_line: self.line_starts.len() + 1,
+            start,
+            text,
+        };
+        self.line_starts.push(start);
+        self.line_starts.extend(
+            file.text
+                .match_indices('\n')
+                .map(|(index, _)| start + index + 1),
+        );
+        self.files.push(file);
+        FileId(self.files.len() - 1)
+    }
+
+    pub fn file(&self, id: FileId) -> &SourceFile {
+        &self.files[id.0]
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = &SourceFile> {
+        self.files.iter()
+    }
+
+    /// Return the file containing a byte offset. The offset right after the end of a file, where
+    /// errors at its end are located, belongs to the file.
+    pub fn file_at(&self, offset: usize) -> Option<&SourceFile> {
+        let index = self.files.partition_point(|file| file.start <= offset);
+        let file = &self.files[index.checked_sub(1)?];
+        (offset <= file.span().end).then_some(file)
+    }
+
+    /// Return the file, line and column of a byte offset
+    pub fn locate(&self, offset: usize) -> Option<Location<'_>> {
+        let file = self.file_at(offset)?;
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        let line_start = self.line_starts[line - 1] - file.start;
+        let end = offset - file.start;
+        let column = file.text[line_start..]
+            .char_indices()
+            .take_while(|&(index, _)| line_start + index < end)
+            .count()
+            + 1;
+        Some(Location {
+            file,
+            line: line - file.first_line + 1,
+            column,
+        })
+    }
+
+    /// Return the file and line of a line number, e.g. of a syntax tree node, at column 1
+    pub fn locate_line(&self, line: usize) -> Option<Location<'_>> {
+        self.locate(*self.line_starts.get(line.checked_sub(1)?)?)
+    }
+
+    /// Return the byte range of a line, without its linebreak
+    pub fn line_span(&self, line: usize) -> Option<Range<usize>> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let file = self.file_at(start)?;
+        let end = match file.text[start - file.start..].find('\n') {
+            Some(index) => start + index,
+            None => file.span().end,
+        };
+        Some(start..end)
+    }
+
+    /// Return the text of a byte range within one file
+    pub fn slice(&self, span: Range<usize>) -> Option<&str> {
+        let file = self.file_at(span.start)?;
+        file.text
+            .get(span.start - file.start..span.end.checked_sub(file.start)?)
+    }
+
+    /// Parse each file and join their function definitions and global variables into one
+    /// program, with lines and byte ranges numbered through all files
+    pub fn parse_program(&self, options: ParserOptions) -> Result<Program, ParseError> {
+        let mut program = Program::default();
+        for file in &self.files {
+            let line_delta = file.first_line as isize - 1;
+            let mut part = C1Parser::with_options(&file.text, options)
+                .program()
+                .map_err(|error| ParseError {
+                    line: error.line.map(|line| line + file.first_line - 1),
+                    span: error.span.start + file.start..error.span.end + file.start,
+                    ..error
+                })?;
+            LineShift(line_delta).visit_program_mut(&mut part);
+            program.functions.append(&mut part.functions);
+            program.globals.append(&mut part.globals);
+        }
+        Ok(program)
+    }
+}
+
+impl SourceFile {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Return the byte range of the text in the source map
+    pub fn span(&self) -> Range<usize> {
+        self.start..self.start + self.text.len()
+    }
+
+    /// Return the line numbers of the text in the source map
+    pub fn lines(&self) -> Range<usize> {
+        self.first_line..self.first_line + self.text.matches('\n').count() + 1
+    }
+}
+
+impl fmt::Display for Location<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file.name, self.line, self.column)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::source::SourceMap;
+    use crate::{Dialect, ParserOptions};
+
+    fn locate(sources: &SourceMap, offset: usize) -> Option<String> {
+        sources.locate(offset).map(|location| location.to_string())
+    }
+
+    #[test]
+    fn offsets_and_lines_run_through_all_files() {
+        let mut sources = SourceMap::new();
+        let a = sources.add("a", "ab\ncd");
+        let b = sources.add("b", "\nä x\n");
+        assert_eq!(sources.file(a).span(), 0..5);
+        assert_eq!(sources.file(b).span(), 6..12);
+        assert_eq!(sources.file(a).lines(), 1..3);
+        assert_eq!(sources.file(b).lines(), 3..6);
+
+        assert_eq!(locate(&sources, 0).as_deref(), Some("a:1:1"));
+        assert_eq!(locate(&sources, 4).as_deref(), Some("a:2:2"));
+        // The end of a file is located in the file, the next one starts a byte later
+        assert_eq!(locate(&sources, 5).as_deref(), Some("a:2:3"));
+        assert_eq!(locate(&sources, 6).as_deref(), Some("b:1:1"));
+        // Columns count characters, not bytes
+        assert_eq!(locate(&sources, 10).as_deref(), Some("b:2:3"));
+        assert_eq!(locate(&sources, 12).as_deref(), Some("b:3:1"));
+        assert_eq!(locate(&sources, 13), None);
+
+        assert_eq!(sources.locate_line(4).unwrap().to_string(), "b:2:1");
+        assert!(sources.locate_line(0).is_none());
+        assert!(sources.locate_line(6).is_none());
+        assert_eq!(sources.line_span(2), Some(3..5));
+        assert_eq!(sources.line_span(4), Some(7..11));
+        assert_eq!(sources.slice(7..11), Some("ä x"));
+        assert_eq!(sources.slice(3..7), None);
+    }
+
+    #[test]
+    fn programs_spanning_several_files() {
+        let mut sources = SourceMap::new();
+        sources.add("main.c1", "int calls;\n\nvoid main() {\n  f(1);\n}\n");
+        sources.add("f.c1", "void f(int n) {\n  calls = n;\n}\n");
+        let options = ParserOptions {
+            dialect: Dialect::C1,
+            ..ParserOptions::default()
+        };
+        let program = sources.parse_program(options).unwrap();
+        assert_eq!(program.globals[0].line, 1);
+        assert_eq!(program.functions[0].name, "main");
+        assert_eq!(program.functions[1].name, "f");
+        assert_eq!(program.functions[1].parameters[0].line, 7);
+        let line = program.functions[1].body[0].line;
+        assert_eq!(sources.locate_line(line).unwrap().to_string(), "f.c1:2:1");
+
+        // Errors are located in the file they occur in, also at its end
+        let error = sources.parse_program(Default::default()).unwrap_err();
+        assert_eq!(error.line, Some(1));
+        assert_eq!(
+            locate(&sources, error.span.start).as_deref(),
+            Some("main.c1:1:10")
+        );
+        sources.add("g.c1", "void g() {");
+        let error = sources.parse_program(options).unwrap_err();
+        assert_eq!(error.line, None);
+        assert_eq!(
+            locate(&sources, error.span.start).as_deref(),
+            Some("g.c1:1:11")
+        );
+    }
+}
@@ -0,0 +1,702 @@
+//! Traversal of the syntax tree.
+//!
+//! [`Visitor`] has one method per kind of node, whose default implementation visits the node's
+//! children. So a pass only overrides the nodes it cares about. An override that still wants to
+//! visit the children calls the `walk_*` function of the node, or `visit_expression` for nodes
+//! with a single operand. [`VisitorMut`] has the same methods and `walk_*_mut` functions for
+//! passes that rewrite the tree in place.
+//! ```
+//! use cb_3::visit::{walk_binary, Visitor};
+//! use cb_3::ast::{BinaryOperator, Expression};
+//! use cb_3::C1Parser;
+//!
+//! /// Counts the divisions of a program
+//! struct Divisions(usize);
+//!
+//! impl Visitor for Divisions {
+//!     fn visit_binary(
+//!         &mut self,
+//!         operator: BinaryOperator,
+//!         lhs: &Expression,
+//!         rhs: &Expression,
+//!         _line: usize,
+//!     ) {
+//!         if operator == BinaryOperator::Divide {
+//!             self.0 += 1;
+//!         }
+//!         walk_binary(self, lhs, rhs);
+//!     }
+//! }
+//!
+//! let program = C1Parser::parse_program("void f() { if (a / b) x = 1 / (2 / c); }").unwrap();
+//! let mut divisions = Divisions(0);
+//! divisions.visit_program(&program);
+//! assert_eq!(divisions.0, 3);
+//! ```
+use crate::ast::{
+    BinaryOperator, Declaration, Expression, ExpressionKind, FunctionDefinition, Program,
+    Statement, StatementKind,
+};
+
+/// Visits the nodes of a syntax tree, outer nodes first and children from left to right
+#[allow(unused_variables)]
+pub trait Visitor {
+    fn visit_program(&mut self, program: &Program) {
+        walk_program(self, program)
+    }
+
+    fn visit_function(&mut self, function: &FunctionDefinition) {
+        walk_function(self, function)
+    }
+
+    /// Dispatch to the method of the statement's kind
+    fn visit_statement(&mut self, statement: &Statement) {
+        walk_statement(self, statement)
+    }
+
+    /// "{" statementlist "}"
+    fn visit_block(&mut self, statements: &[Statement], line: usize) {
+        walk_block(self, statements)
+    }
+
+    fn visit_if(
+        &mut self,
+        condition: &Expression,
+        body: &Statement,
+        else_body: Option<&Statement>,
+        line: usize,
+    ) {
+        walk_if(self, condition, body, else_body)
+    }
+
+    fn visit_while(&mut self, condition: &Expression, body: &Statement, line: usize) {
+        walk_while(self, condition, body)
+    }
+
+    /// The body is visited before the condition, as it is executed first
+    fn visit_do_while(&mut self, body: &Statement, condition: &Expression, line: usize) {
+        walk_do_while(self, body, condition)
+    }
+
+    fn visit_for(
+        &mut self,
+        init: &Statement,
+        condition: &Expression,
+        step: &Statement,
+        body: &Statement,
+        line: usize,
+    ) {
+        walk_for(self, init, condition, step, body)
+    }
+
+    fn visit_return(&mut self, value: Option<&Expression>, line: usize) {
+        walk_return(self, value)
+    }
+
+    fn visit_printf(&mut self, value: &Expression, line: usize) {
+        self.visit_expression(value)
+    }
+
+    /// The declaration of a local or global variable
+    fn visit_declaration(&mut self, declaration: &Declaration) {
+        walk_declaration(self, declaration)
+    }
+
+    /// The statement `target = value;`
+    fn visit_assignment(&mut self, target: &str, value: &Expression, line: usize) {
+        self.visit_expression(value)
+    }
+
+    /// A function call, both as statement and as expression
+    fn visit_call(&mut self, function: &str, arguments: &[Expression], line: usize) {
+        walk_call(self, arguments)
+    }
+
+    /// Dispatch to the method of the expression's kind
+    fn visit_expression(&mut self, expression: &Expression) {
+        walk_expression(self, expression)
+    }
+
+    /// The expression `target = value`
+    fn visit_assign(&mut self, target: &str, value: &Expression, line: usize) {
+        self.visit_expression(value)
+    }
+
+    /// Comparisons, sums and products
+    fn visit_binary(
+        &mut self,
+        operator: BinaryOperator,
+        lhs: &Expression,
+        rhs: &Expression,
+        line: usize,
+    ) {
+        walk_binary(self, lhs, rhs)
+    }
+
+    fn visit_negate(&mut self, operand: &Expression, line: usize) {
+        self.visit_expression(operand)
+    }
+
+    fn visit_int(&mut self, value: i64, line: usize) {}
+
+    fn visit_int_out_of_range(&mut self, digits: &str, line: usize) {}
+
+    fn visit_float(&mut self, value: f64, line: usize) {}
+
+    fn visit_bool(&mut self, value: bool, line: usize) {}
+
+    fn visit_string(&mut self, value: &str, line: usize) {}
+
+    fn visit_variable(&mut self, name: &str, line: usize) {}
+}
+
+pub fn walk_program<V: Visitor + ?Sized>(visitor: &mut V, program: &Program) {
+    for declaration in &program.globals {
+        visitor.visit_declaration(declaration);
+    }
+    for function in &program.functions {
+        visitor.visit_function(function);
+    }
+}
+
+pub fn walk_function<V: Visitor + ?Sized>(visitor: &mut V, function: &FunctionDefinition) {
+    for statement in &function.body {
+        visitor.visit_statement(statement);
+    }
+}
+
+pub fn walk_statement<V: Visitor + ?Sized>(visitor: &mut V, statement: &Statement) {
+    let line = statement.line;
+    match &statement.kind {
+        StatementKind::Block(statements) => visitor.visit_block(statements, line),
+        StatementKind::If {
+            condition,
+            body,
+            else_body,
+        } => visitor.visit_if(condition, body, else_body.as_deref(), line),
+        StatementKind::While { condition, body } => visitor.visit_while(condition, body, line),
+        StatementKind::DoWhile { body, condition } => visitor.visit_do_while(body, condition, line),
+        StatementKind::For {
+            init,
+            condition,
+            step,
+            body,
+        } => visitor.visit_for(init, condition, step, body, line),
+        StatementKind::Return(value) => visitor.visit_return(value.as_ref(), line),
+        StatementKind::Printf(value) => visitor.visit_printf(value, line),
+        StatementKind::Declaration(declaration) => visitor.visit_declaration(declaration),
+        StatementKind::Assignment { target, value } => {
+            visitor.visit_assignment(target, value, line)
+        }
+        StatementKind::Call {
+            function,
+            arguments,
+        } => visitor.visit_call(function, arguments, line),
+    }
+}
+
+pub fn walk_block<V: Visitor + ?Sized>(visitor: &mut V, statements: &[Statement]) {
+    for statement in statements {
+        visitor.visit_statement(statement);
+    }
+}
+
+pub fn walk_if<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    condition: &Expression,
+    body: &Statement,
+    else_body: Option<&Statement>,
+) {
+    visitor.visit_expression(condition);
+    visitor.visit_statement(body);
+    if let Some(else_body) = else_body {
+        visitor.visit_statement(else_body);
+    }
+}
+
+pub fn walk_while<V: Visitor + ?Sized>(visitor: &mut V, condition: &Expression, body: &Statement) {
+    visitor.visit_expression(condition);
+    visitor.visit_statement(body);
+}
+
+pub fn walk_do_while<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    body: &Statement,
+    condition: &Expression,
+) {
+    visitor.visit_statement(body);
+    visitor.visit_expression(condition);
+}
+
+pub fn walk_for<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    init: &Statement,
+    condition: &Expression,
+    step: &Statement,
+    body: &Statement,
+) {
+    visitor.visit_statement(init);
+    visitor.visit_expression(condition);
+    visitor.visit_statement(step);
+    visitor.visit_statement(body);
+}
+
+pub fn walk_declaration<V: Visitor + ?Sized>(visitor: &mut V, declaration: &Declaration) {
+    if let Some(value) = &declaration.value {
+        visitor.visit_expression(value);
+    }
+}
+
+pub fn walk_call<V: Visitor + ?Sized>(visitor: &mut V, arguments: &[Expression]) {
+    for argument in arguments {
+        visitor.visit_expression(argument);
+    }
+}
+
+pub fn walk_return<V: Visitor + ?Sized>(visitor: &mut V, value: Option<&Expression>) {
+    if let Some(value) = value {
+        visitor.visit_expression(value);
+    }
+}
+
+pub fn walk_expression<V: Visitor + ?Sized>(visitor: &mut V, expression: &Expression) {
+    let line = expression.line;
+    match &expression.kind {
+        ExpressionKind::Assign { target, value } => visitor.visit_assign(target, value, line),
+        ExpressionKind::Binary { operator, lhs, rhs } => {
+            visitor.visit_binary(*operator, lhs, rhs, line)
+        }
+        ExpressionKind::Negate(operand) => visitor.visit_negate(operand, line),
+        ExpressionKind::Int(value) => visitor.visit_int(*value, line),
+        ExpressionKind::IntOutOfRange(digits) => visitor.visit_int_out_of_range(digits, line),
+        ExpressionKind::Float(value) => visitor.visit_float(*value, line),
+        ExpressionKind::Bool(value) => visitor.visit_bool(*value, line),
+        ExpressionKind::String(value) => visitor.visit_string(value, line),
+        ExpressionKind::Call {
+            function,
+            arguments,
+        } => visitor.visit_call(function, arguments, line),
+        ExpressionKind::Variable(name) => visitor.visit_variable(name, line),
+    }
+}
+
+pub fn walk_binary<V: Visitor + ?Sized>(visitor: &mut V, lhs: &Expression, rhs: &Expression) {
+    visitor.visit_expression(lhs);
+    visitor.visit_expression(rhs);
+}
+
+/// Visits the nodes of a syntax tree mutably, e.g. to replace expressions by simpler ones. It
+/// has the same methods as [`Visitor`], with mutable references. A method may replace its node
+/// entirely; the default implementations then walk into the children of the replacement.
+/// Statements are added to or removed from a block by replacing the block statement in
+/// `visit_statement_mut`.
+#[allow(unused_variables)]
+pub trait VisitorMut {
+    fn visit_program_mut(&mut self, program: &mut Program) {
+        walk_program_mut(self, program)
+    }
+
+    fn visit_function_mut(&mut self, function: &mut FunctionDefinition) {
+        walk_function_mut(self, function)
+    }
+
+    /// Dispatch to the method of the statement's kind
+    fn visit_statement_mut(&mut self, statement: &mut Statement) {
+        walk_statement_mut(self, statement)
+    }
+
+    fn visit_block_mut(&mut self, statements: &mut [Statement], line: usize) {
+        walk_block_mut(self, statements)
+    }
+
+    fn visit_if_mut(
+        &mut self,
+        condition: &mut Expression,
+        body: &mut Statement,
+        else_body: Option<&mut Statement>,
+        line: usize,
+    ) {
+        walk_if_mut(self, condition, body, else_body)
+    }
+
+    fn visit_while_mut(&mut self, condition: &mut Expression, body: &mut Statement, line: usize) {
+        walk_while_mut(self, condition, body)
+    }
+
+    fn visit_do_while_mut(
+        &mut self,
+        body: &mut Statement,
+        condition: &mut Expression,
+        line: usize,
+    ) {
+        walk_do_while_mut(self, body, condition)
+    }
+
+    fn visit_for_mut(
+        &mut self,
+        init: &mut Statement,
+        condition: &mut Expression,
+        step: &mut Statement,
+        body: &mut Statement,
+        line: usize,
+    ) {
+        walk_for_mut(self, init, condition, step, body)
+    }
+
+    fn visit_return_mut(&mut self, value: Option<&mut Expression>, line: usize) {
+        walk_return_mut(self, value)
+    }
+
+    fn visit_printf_mut(&mut self, value: &mut Expression, line: usize) {
+        self.visit_expression_mut(value)
+    }
+
+    /// A local or, visited by walk_program_mut, a global variable
+    fn visit_declaration_mut(&mut self, declaration: &mut Declaration) {
+        walk_declaration_mut(self, declaration)
+    }
+
+    fn visit_assignment_mut(&mut self, target: &mut String, value: &mut Expression, line: usize) {
+        self.visit_expression_mut(value)
+    }
+
+    /// A function call as statement or as expression
+    fn visit_call_mut(&mut self, function: &mut String, arguments: &mut [Expression], line: usize) {
+        walk_call_mut(self, arguments)
+    }
+
+    /// Dispatch to the method of the expression's kind
+    fn visit_expression_mut(&mut self, expression: &mut Expression) {
+        walk_expression_mut(self, expression)
+    }
+
+    fn visit_assign_mut(&mut self, target: &mut String, value: &mut Expression, line: usize) {
+        self.visit_expression_mut(value)
+    }
+
+    fn visit_binary_mut(
+        &mut self,
+        operator: &mut BinaryOperator,
+        lhs: &mut Expression,
+        rhs: &mut Expression,
+        line: usize,
+    ) {
+        walk_binary_mut(self, lhs, rhs)
+    }
+
+    fn visit_negate_mut(&mut self, operand: &mut Expression, line: usize) {
+        self.visit_expression_mut(operand)
+    }
+
+    fn visit_int_mut(&mut self, value: &mut i64, line: usize) {}
+
+    fn visit_int_out_of_range_mut(&mut self, digits: &mut String, line: usize) {}
+
+    fn visit_float_mut(&mut self, value: &mut f64, line: usize) {}
+
+    fn visit_bool_mut(&mut self, value: &mut bool, line: usize) {}
+
+    fn visit_string_mut(&mut self, value: &mut String, line: usize) {}
+
+    fn visit_variable_mut(&mut self, name: &mut String, line: usize) {}
+}
+
+pub fn walk_program_mut<V: VisitorMut + ?Sized>(visitor: &mut V, program: &mut Program) {
+    for declaration in &mut program.globals {
+        visitor.visit_declaration_mut(declaration);
+    }
+    for function in &mut program.functions {
+        visitor.visit_function_mut(function);
+    }
+}
+
+pub fn walk_function_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    function: &mut FunctionDefinition,
+) {
+    for statement in &mut function.body {
+        visitor.visit_statement_mut(statement);
+    }
+}
+
+pub fn walk_statement_mut<V: VisitorMut + ?Sized>(visitor: &mut V, statement: &mut Statement) {
+    let line = statement.line;
+    match &mut statement.kind {
+        StatementKind::Block(statements) => visitor.visit_block_mut(statements, line),
+        StatementKind::If {
+            condition,
+            body,
+            else_body,
+        } => visitor.visit_if_mut(condition, body, else_body.as_deref_mut(), line),
+        StatementKind::While { condition, body } => visitor.visit_while_mut(condition, body, line),
+        StatementKind::DoWhile { body, condition } => {
+            visitor.visit_do_while_mut(body, condition, line)
+        }
+        StatementKind::For {
+            init,
+            condition,
+            step,
+            body,
+        } => visitor.visit_for_mut(init, condition, step, body, line),
+        StatementKind::Return(value) => visitor.visit_return_mut(value.as_mut(), line),
+        StatementKind::Printf(value) => visitor.visit_printf_mut(value, line),
+        StatementKind::Declaration(declaration) => visitor.visit_declaration_mut(declaration),
+        StatementKind::Assignment { target, value } => {
+            visitor.visit_assignment_mut(target, value, line)
+        }
+        StatementKind::Call {
+            function,
+            arguments,
+        } => visitor.visit_call_mut(function, arguments, line),
+    }
+}
+
+pub fn walk_block_mut<V: VisitorMut + ?Sized>(visitor: &mut V, statements: &mut [Statement]) {
+    for statement in statements {
+        visitor.visit_statement_mut(statement);
+    }
+}
+
+pub fn walk_if_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    condition: &mut Expression,
+    body: &mut Statement,
+    else_body: Option<&mut Statement>,
+) {
+    visitor.visit_expression_mut(condition);
+    visitor.visit_statement_mut(body);
+    if let Some(else_body) = else_body {
+        visitor.visit_statement_mut(else_body);
+    }
+}
+
+pub fn walk_while_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    condition: &mut Expression,
+    body: &mut Statement,
+) {
+    visitor.visit_expression_mut(condition);
+    visitor.visit_statement_mut(body);
+}
+
+pub fn walk_do_while_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    body: &mut Statement,
+    condition: &mut Expression,
+) {
+    visitor.visit_statement_mut(body);
+    visitor.visit_expression_mut(condition);
+}
+
+pub fn walk_for_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    init: &mut Statement,
+    condition: &mut Expression,
+    step: &mut Statement,
+    body: &mut Statement,
+) {
+    visitor.visit_statement_mut(init);
+    visitor.visit_expression_mut(condition);
+    visitor.visit_statement_mut(step);
+    visitor.visit_statement_mut(body);
+}
+
+pub fn walk_declaration_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    declaration: &mut Declaration,
+) {
+    if let Some(value) = &mut declaration.value {
+        visitor.visit_expression_mut(value);
+    }
+}
+
+pub fn walk_call_mut<V: VisitorMut + ?Sized>(visitor: &mut V, arguments: &mut [Expression]) {
+    for argument in arguments {
+        visitor.visit_expression_mut(argument);
+    }
+}
+
+pub fn walk_return_mut<V: VisitorMut + ?Sized>(visitor: &mut V, value: Option<&mut Expression>) {
+    if let Some(value) = value {
+        visitor.visit_expression_mut(value);
+    }
+}
+
+pub fn walk_expression_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expression: &mut Expression) {
+    let line = expression.line;
+    match &mut expression.kind {
+        ExpressionKind::Assign { target, value } => visitor.visit_assign_mut(target, value, line),
+        ExpressionKind::Binary { operator, lhs, rhs } => {
+            visitor.visit_binary_mut(operator, lhs, rhs, line)
+        }
+        ExpressionKind::Negate(operand) => visitor.visit_negate_mut(operand, line),
+        ExpressionKind::Int(value) => visitor.visit_int_mut(value, line),
+        ExpressionKind::IntOutOfRange(digits) => visitor.visit_int_out_of_range_mut(digits, line),
+        ExpressionKind::Float(value) => visitor.visit_float_mut(value, line),
+        ExpressionKind::Bool(value) => visitor.visit_bool_mut(value, line),
+        ExpressionKind::String(value) => visitor.visit_string_mut(value, line),
+        ExpressionKind::Call {
+            function,
+            arguments,
+        } => visitor.visit_call_mut(function, arguments, line),
+        ExpressionKind::Variable(name) => visitor.visit_variable_mut(name, line),
+    }
+}
+
+pub fn walk_binary_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    lhs: &mut Expression,
+    rhs: &mut Expression,
+) {
+    visitor.visit_expression_mut(lhs);
+    visitor.visit_expression_mut(rhs);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::{BinaryOperator, Expression, ExpressionKind, Statement, StatementKind};
+    use crate::visit::{
+        walk_binary_mut, walk_call, walk_expression_mut, walk_statement, Visitor, VisitorMut,
+    };
+    use crate::C1Parser;
+
+    /// Records the visited nodes
+    #[derive(Default)]
+    struct Trace(Vec<String>);
+
+    impl Visitor for Trace {
+        fn visit_statement(&mut self, statement: &Statement) {
+            self.0.push(format!("statement {}", statement.line));
+            walk_statement(self, statement)
+        }
+
+        fn visit_call(&mut self, function: &str, arguments: &[Expression], _: usize) {
+            self.0.push(format!("call {}", function));
+            walk_call(self, arguments);
+        }
+
+        fn visit_int(&mut self, value: i64, _: usize) {
+            self.0.push(value.to_string());
+        }
+
+        fn visit_variable(&mut self, name: &str, _: usize) {
+            self.0.push(name.to_string());
+        }
+    }
+
+    #[test]
+    fn visits_outer_nodes_first() {
+        let program = C1Parser::parse_program(
+            "void f() {\n if (a < 1) {\n g();\n }\n return -(b = h() + 2);\n}",
+        )
+        .unwrap();
+        let mut trace = Trace::default();
+        trace.visit_program(&program);
+        assert_eq!(
+            trace.0,
+            vec![
+                "statement 2",
+                "a",
+                "1",
+                "statement 2",
+                "statement 3",
+                "call g",
+                "statement 5",
+                "call h",
+                "2"
+            ]
+        );
+    }
+
+    /// Folds sums of integer constants
+    struct ConstantSums;
+
+    impl VisitorMut for ConstantSums {
+        fn visit_expression_mut(&mut self, expression: &mut Expression) {
+            walk_expression_mut(self, expression);
+            if let ExpressionKind::Binary {
+                operator: BinaryOperator::Add,
+                lhs,
+                rhs,
+            } = &expression.kind
+            {
+                if let (ExpressionKind::Int(lhs), ExpressionKind::Int(rhs)) = (&lhs.kind, &rhs.kind)
+                {
+                    expression.kind = ExpressionKind::Int(lhs.saturating_add(*rhs));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rewrites_in_place() {
+        let mut program =
+            C1Parser::parse_program("int f() { x = 1 + 2 + y; return (1 + 2) + 3; }").unwrap();
+        ConstantSums.visit_program_mut(&mut program);
+        let body = &program.functions[0].body;
+        let StatementKind::Assignment { value, .. } = &body[0].kind else {
+            panic!("{:?}", body[0]);
+        };
+        assert!(matches!(
+            &value.kind,
+            ExpressionKind::Binary { lhs, .. } if lhs.kind == ExpressionKind::Int(3)
+        ));
+        let StatementKind::Return(Some(value)) = &body[1].kind else {
+            panic!("{:?}", body[1]);
+        };
+        assert_eq!(value.kind, ExpressionKind::Int(6));
+    }
+
+    /// Renames variables, turns `<` into `>` by swapping the operands and doubles integer constants
+    struct Rename;
+
+    impl VisitorMut for Rename {
+        fn visit_variable_mut(&mut self, name: &mut String, _: usize) {
+            name.push('_');
+        }
+
+        fn visit_assignment_mut(&mut self, target: &mut String, value: &mut Expression, _: usize) {
+            target.push('_');
+            self.visit_expression_mut(value);
+        }
+
+        fn visit_binary_mut(
+            &mut self,
+            operator: &mut BinaryOperator,
+            lhs: &mut Expression,
+            rhs: &mut Expression,
+            _: usize,
+        ) {
+            if *operator == BinaryOperator::Less {
+                *operator = BinaryOperator::Greater;
+                std::mem::swap(lhs, rhs);
+            }
+            walk_binary_mut(self, lhs, rhs);
+        }
+
+        fn visit_int_mut(&mut self, value: &mut i64, _: usize) {
+            *value *= 2;
+        }
+    }
+
+    #[test]
+    fn rewrites_per_kind() {
+        let mut program = C1Parser::parse_program("int f() { x = a < 2; return b; }").unwrap();
+        Rename.visit_program_mut(&mut program);
+        let body = &program.functions[0].body;
+        let StatementKind::Assignment { target, value } = &body[0].kind else {
+            panic!("{:?}", body[0]);
+        };
+        assert_eq!(target, "x_");
+        let ExpressionKind::Binary { operator, lhs, rhs } = &value.kind else {
+            panic!("{:?}", value);
+        };
+        assert_eq!(*operator, BinaryOperator::Greater);
+        assert_eq!(lhs.kind, ExpressionKind::Int(4));
+        assert_eq!(rhs.kind, ExpressionKind::Variable("a_".to_string()));
+        let StatementKind::Return(Some(value)) = &body[1].kind else {
+            panic!("{:?}", body[1]);
+        };
+        assert_eq!(value.kind, ExpressionKind::Variable("b_".to_string()));
+    }
+}
@@ -0,0 +1,442 @@
+use crate::ast::{
+    Expression, ExpressionKind, FunctionDefinition, Program, Statement, StatementKind,
+};
+use crate::call_graph::CallGraph;
+use std::collections::HashSet;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum WarningKind {
+    /// A statement follows an unconditional return in the same block
+    UnreachableCode,
+    /// A function can not be reached from main through calls
+    UnusedFunction,
+    /// An assigned value is never read
+    DeadAssignment,
+}
+
+/// A problem in a syntactically correct program
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Warning {
+    pub kind: WarningKind,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "warning at line {}: {}", self.line, self.message)
+    }
+}
+
+/// Check the program for unreachable statements, functions that are never called from `main`
+/// and assignments whose value is never read. The warnings are ordered by line.
+pub fn check(program: &Program) -> Vec<Warning> {
+    use WarningKind::*;
+
+    let mut warnings: Vec<Warning> = [UnreachableCode, DeadAssignment, UnusedFunction]
+        .into_iter()
+        .flat_map(|kind| check_kind(program, kind))
+        .collect();
+    warnings.sort_by_key(|warning| warning.line);
+    warnings
+}
+
+/// Run only the analysis finding one kind of warnings, ordered by line
+pub fn check_kind(program: &Program, kind: WarningKind) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    match kind {
+        WarningKind::UnreachableCode => {
+            for function in &program.functions {
+                unreachable_statements(&function.body, &mut warnings);
+            }
+        }
+        WarningKind::UnusedFunction => unused_functions(program, &mut warnings),
+        WarningKind::DeadAssignment => {
+            let globals: HashSet<String> = program
+                .globals
+                .iter()
+                .map(|declaration| declaration.name.clone())
+                .collect();
+            for function in &program.functions {
+                dead_assignments(function, &globals, &mut warnings);
+            }
+        }
+    }
+    warnings.sort_by_key(|warning| warning.line);
+    warnings
+}
+
+/// Check whether control never continues after the statement
+fn always_returns(statement: &Statement) -> bool {
+    match &statement.kind {
+        StatementKind::Return(_) => true,
+        StatementKind::Block(statements) => statements.iter().any(always_returns),
+        StatementKind::If {
+            body,
+            else_body: Some(else_body),
+            ..
+        } => always_returns(body) && always_returns(else_body),
+        StatementKind::DoWhile { body, .. } => always_returns(body),
+        _ => false,
+    }
+}
+
+fn unreachable_statements(statements: &[Statement], warnings: &mut Vec<Warning>) {
+    if let Some(index) = statements.iter().position(always_returns) {
+        if let Some(unreachable) = statements.get(index + 1) {
+            warnings.push(Warning {
+                kind: WarningKind::UnreachableCode,
+                line: unreachable.line,
+                message: "unreachable statement after return".to_string(),
+            });
+        }
+    }
+    for statement in statements {
+        match &statement.kind {
+            StatementKind::Block(statements) => unreachable_statements(statements, warnings),
+            StatementKind::If {
+                body, else_body, ..
+            } => {
+                unreachable_statements(std::slice::from_ref(body), warnings);
+                if let Some(else_body) = else_body {
+                    unreachable_statements(std::slice::from_ref(else_body), warnings);
+                }
+            }
+            StatementKind::While { body, .. }
+            | StatementKind::DoWhile { body, .. }
+            | StatementKind::For { body, .. } => {
+                unreachable_statements(std::slice::from_ref(body), warnings)
+            }
+            _ => {}
+        }
+    }
+}
+
+fn unused_functions(program: &Program, warnings: &mut Vec<Warning>) {
+    // Without an entry point every function may be used from elsewhere
+    if program.function("main").is_none() {
+        return;
+    }
+    let call_graph = CallGraph::new(program);
+    let reachable = call_graph.reachable_from("main");
+    for function in &program.functions {
+        if !reachable.contains(function.name.as_str()) {
+            warnings.push(Warning {
+                kind: WarningKind::UnusedFunction,
+                line: function.line,
+                message: format!("function '{}' is never called from main", function.name),
+            });
+        }
+    }
+}
+
+/// Find assignments to variables that are not live afterwards. Only global variables, which
+/// C(-1) does not have, are live at the end of a function and may be read by called functions.
+fn dead_assignments(
+    function: &FunctionDefinition,
+    globals: &HashSet<String>,
+    warnings: &mut Vec<Warning>,
+) {
+    let mut liveness = Liveness {
+        live: globals.clone(),
+        globals,
+        warnings,
+    };
+    liveness.statements(&function.body);
+}
+
+/// Backward liveness analysis over the syntax tree
+struct Liveness<'a> {
+    /// Variables whose current value may be read later
+    live: HashSet<String>,
+    globals: &'a HashSet<String>,
+    warnings: &'a mut Vec<Warning>,
+}
+
+impl<'a> Liveness<'a> {
+    fn statements(&mut self, statements: &[Statement]) {
+        // Statements after a return are never executed and already reported as unreachable
+        let reachable = match statements.iter().position(always_returns) {
+            Some(index) => &statements[..=index],
+            None => statements,
+        };
+        for statement in reachable.iter().rev() {
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match &statement.kind {
+            StatementKind::Block(statements) => self.statements(statements),
+            StatementKind::If {
+                condition,
+                body,
+                else_body,
+            } => {
+                let live_after = self.live.clone();
+                self.statement(body);
+                let live_in_body = std::mem::replace(&mut self.live, live_after);
+                if let Some(else_body) = else_body {
+                    self.statement(else_body);
+                }
+                self.live.extend(live_in_body);
+                self.expression(condition);
+            }
+            StatementKind::While { condition, body } => {
+                let live_after = self.live.clone();
+                // What is live before the condition, which is evaluated after the body again
+                self.iterate(|liveness, live_before| {
+                    liveness.live = live_before.clone();
+                    liveness.statement(body);
+                    liveness.live.extend(live_after.iter().cloned());
+                    liveness.expression(condition);
+                });
+            }
+            StatementKind::DoWhile { body, condition } => {
+                let live_after = self.live.clone();
+                self.iterate(|liveness, live_before| {
+                    liveness.live = live_before.clone();
+                    liveness.live.extend(live_after.iter().cloned());
+                    liveness.expression(condition);
+                    liveness.statement(body);
+                });
+            }
+            StatementKind::For {
+                init,
+                condition,
+                step,
+                body,
+            } => {
+                let live_after = self.live.clone();
+                self.iterate(|liveness, live_before| {
+                    liveness.live = live_before.clone();
+                    liveness.statement(step);
+                    liveness.statement(body);
+                    liveness.live.extend(live_after.iter().cloned());
+                    liveness.expression(condition);
+                });
+                self.statement(init);
+            }
+            StatementKind::Return(value) => {
+                self.live = self.globals.clone();
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+            }
+            StatementKind::Printf(value) => self.expression(value),
+            StatementKind::Declaration(declaration) => match &declaration.value {
+                Some(value) => {
+                    self.assignment(&declaration.name, declaration.line);
+                    self.expression(value);
+                }
+                None => {
+                    self.live.remove(&declaration.name);
+                }
+            },
+            StatementKind::Assignment { target, value } => {
+                self.assignment(target, statement.line);
+                self.expression(value);
+            }
+            StatementKind::Call { arguments, .. } => self.call(arguments),
+        }
+    }
+
+    /// Analyse a loop, whose variables live at the start of the loop are also live at the end
+    /// of each iteration. `analyse` computes what is live at the start from what is live at
+    /// the end of an iteration. It is repeated until that does not change, reporting warnings
+    /// only in the last round.
+    fn iterate(&mut self, mut analyse: impl FnMut(&mut Self, &HashSet<String>)) {
+        let reported = self.warnings.len();
+        let mut live_before = HashSet::new();
+        loop {
+            analyse(self, &live_before);
+            if self.live == live_before {
+                break;
+            }
+            live_before = self.live.clone();
+            self.warnings.truncate(reported);
+        }
+    }
+
+    /// A called function may read the global variables
+    fn call(&mut self, arguments: &[Expression]) {
+        self.live.extend(self.globals.iter().cloned());
+        for argument in arguments.iter().rev() {
+            self.expression(argument);
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        match &expression.kind {
+            ExpressionKind::Assign { target, value } => {
+                self.assignment(target, expression.line);
+                self.expression(value);
+            }
+            // Operands are evaluated from left to right, so they are visited from right to left
+            ExpressionKind::Binary { lhs, rhs, .. } => {
+                self.expression(rhs);
+                self.expression(lhs);
+            }
+            ExpressionKind::Negate(operand) => self.expression(operand),
+            ExpressionKind::Call { arguments, .. } => self.call(arguments),
+            ExpressionKind::Variable(name) => {
+                self.live.insert(name.clone());
+            }
+            _ => {}
+        }
+    }
+
+    fn assignment(&mut self, target: &str, line: usize) {
+        if !self.live.remove(target) {
+            self.warnings.push(Warning {
+                kind: WarningKind::DeadAssignment,
+                line,
+                message: format!("value assigned to '{}' is never read", target),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::warnings::{check, check_kind, WarningKind};
+    use crate::{C1Parser, Dialect, ParserOptions};
+
+    fn warnings(text: &str) -> Vec<(WarningKind, usize)> {
+        check(&C1Parser::parse_program(text).unwrap())
+            .into_iter()
+            .map(|warning| (warning.kind, warning.line))
+            .collect()
+    }
+
+    #[test]
+    fn example_program_has_no_warnings() {
+        let program = C1Parser::parse_program(include_str!("../tests/data/beispiel.c-1")).unwrap();
+        assert_eq!(check(&program), vec![]);
+    }
+
+    #[test]
+    fn kinds_are_checked_separately() {
+        use WarningKind::*;
+
+        let text = "void f() {}\nint main() {\n x = 1;\n return 0;\n printf(2);\n}";
+        let program = C1Parser::parse_program(text).unwrap();
+        let all = check(&program);
+        assert_eq!(all.len(), 3);
+        for kind in [UnreachableCode, UnusedFunction, DeadAssignment] {
+            let expected: Vec<_> = all
+                .iter()
+                .filter(|warning| warning.kind == kind)
+                .cloned()
+                .collect();
+            assert_eq!(check_kind(&program, kind), expected);
+        }
+    }
+
+    #[test]
+    fn statements_after_return() {
+        use WarningKind::*;
+
+        assert_eq!(
+            warnings("int f() {\n return 1;\n printf(2);\n printf(3);\n}"),
+            vec![(UnreachableCode, 3)]
+        );
+        assert_eq!(
+            warnings("int f() {\n {\n return 1;\n }\n printf(2);\n}"),
+            vec![(UnreachableCode, 5)]
+        );
+        assert_eq!(
+            warnings("void f() {\n if (x) {\n return;\n f();\n }\n f();\n}"),
+            vec![(UnreachableCode, 4)]
+        );
+        assert_eq!(warnings("void f() {\n if (x) return;\n f();\n}"), vec![]);
+    }
+
+    #[test]
+    fn functions_not_called_from_main() {
+        use WarningKind::*;
+
+        assert_eq!(
+            warnings(
+                "void a() { b(); }\nvoid b() { a(); }\nvoid c() { c(); }\nvoid main() { b(); }"
+            ),
+            vec![(UnusedFunction, 3)]
+        );
+        // The call graph also contains calls in unreachable statements
+        assert_eq!(
+            warnings("void a() {}\nvoid main() { return; a(); }"),
+            vec![(UnreachableCode, 2)]
+        );
+        // Without main there is no entry point to check against
+        assert_eq!(warnings("void a() {}"), vec![]);
+    }
+
+    #[test]
+    fn assignments_never_read() {
+        use WarningKind::*;
+
+        assert_eq!(
+            warnings("int f() {\n x = 1;\n x = 2;\n return x;\n}"),
+            vec![(DeadAssignment, 2)]
+        );
+        assert_eq!(
+            warnings("int f() {\n x = 1;\n if (c) x = 2;\n return x;\n}"),
+            vec![]
+        );
+        assert_eq!(
+            warnings("int f() {\n x = 1;\n if (c) {\n x = 2;\n return 0;\n }\n return 1;\n}"),
+            vec![(DeadAssignment, 2), (DeadAssignment, 4)]
+        );
+        assert_eq!(
+            warnings("void f() {\n printf(y = (x = 1) + x);\n}"),
+            vec![(DeadAssignment, 2)]
+        );
+        assert_eq!(
+            warnings("void f() {\n x = x + 1;\n}"),
+            vec![(DeadAssignment, 2)]
+        );
+    }
+
+    #[test]
+    fn c1_loops_and_globals() {
+        use WarningKind::*;
+
+        let warnings = |text| {
+            let options = ParserOptions {
+                dialect: Dialect::C1,
+                ..ParserOptions::default()
+            };
+            check(&C1Parser::with_options(text, options).program().unwrap())
+                .into_iter()
+                .map(|warning| (warning.kind, warning.line))
+                .collect::<Vec<_>>()
+        };
+        // Read in the next iteration
+        assert_eq!(
+            warnings("int f() {\n int x = 0;\n while (x < 9) x = x + 1;\n return 0;\n}"),
+            vec![]
+        );
+        assert_eq!(
+            warnings("void f() {\n int x = 0;\n for (int i = 0; i < 9; i = i + 1)\n x = i;\n}"),
+            vec![(DeadAssignment, 2), (DeadAssignment, 4)]
+        );
+        assert_eq!(
+            warnings("void f() {\n int x;\n do {\n x = 1;\n printf(x);\n x = 2;\n } while (1);\n}"),
+            vec![(DeadAssignment, 6)]
+        );
+        // Global variables may be read after the function or by the functions it calls
+        assert_eq!(
+            warnings("int x;\nvoid f() {\n x = 1;\n g();\n x = 2;\n}"),
+            vec![]
+        );
+        assert_eq!(
+            warnings("int x;\nvoid f() {\n x = 1;\n x = 2;\n}"),
+            vec![(DeadAssignment, 3)]
+        );
+        assert_eq!(
+            warnings("int f() {\n if (x) return 1; else return 2;\n x = 3;\n}"),
+            vec![(UnreachableCode, 3)]
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// Run the c1 binary with the text on stdin
+fn c1(command: &str, text: &str) -> Output {
+    let mut process = Command::new(env!("CARGO_BIN_EXE_c1"))
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    process
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(text.as_bytes())
+        .unwrap();
+    process.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> &str {
+    std::str::from_utf8(&output.stdout).unwrap()
+}
+
+fn stderr(output: &Output) -> &str {
+    std::str::from_utf8(&output.stderr).unwrap()
+}
+
+#[test]
+fn check_reports_syntax_errors() {
+    let output = Command::new(env!("CARGO_BIN_EXE_c1"))
+        .args(["check", "tests/data/beispiel.c-1"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "");
+
+    let output = c1("check", "void main() {\n  printf(1)\n}");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stderr(&output),
+        "error: unexpected token\n --> <stdin>:3:1\n  |\n3 | }\n  | ^\n"
+    );
+}
+
+#[test]
+fn programs_spanning_several_files() {
+    let output = Command::new(env!("CARGO_BIN_EXE_c1"))
+        .args(["sexp", "tests/data/beispiel.c-1", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .and_then(|mut process| {
+            process.stdin.take().unwrap().write_all(b"void g() {}\n")?;
+            process.wait_with_output()
+        })
+        .unwrap();
+    assert!(output.status.success());
+    assert!(stdout(&output).ends_with(" (fn void g))\n"));
+
+    // Errors are located in the file they occur in
+    let output = Command::new(env!("CARGO_BIN_EXE_c1"))
+        .args(["check", "tests/data/beispiel.c-1", "tests/data/beispiel.c1"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stderr(&output),
+        "\
+error: global variables are not part of C(-1)
+ --> tests/data/beispiel.c1:1:11
+  |
+1 | int calls = 0;
+  |           ^
+"
+    );
+}
+
+#[test]
+fn sexp_prints_the_syntax_tree() {
+    let output = c1("sexp", "int blub() { blub1 = 23; return blub1; }");
+    assert!(output.status.success());
+    assert_eq!(
+        stdout(&output),
+        "(program (fn int blub (assign blub1 23) (return blub1)))\n"
+    );
+}
+
+#[test]
+fn c1_dialect() {
+    let text = "void main() { int x = 1; while (x) x = 0; }";
+    let output = c1("sexp", text);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stderr(&output),
+        "\
+error: declarations are not part of C(-1)
+ --> <stdin>:1:15
+  |
+1 | void main() { int x = 1; while (x) x = 0; }
+  |               ^^^
+"
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_c1"))
+        .args(["--c1", "check", "tests/data/beispiel.c1"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let output = Command::new(env!("CARGO_BIN_EXE_c1"))
+        .args(["sexp", "--c1", "tests/data/beispiel.c1"])
+        .output()
+        .unwrap();
+    assert!(stdout(&output).starts_with("(program (var int calls 0) (fn int fib (param int n)"));
+}
+
+#[test]
+fn coloured_errors() {
+    let mut process = Command::new(env!("CARGO_BIN_EXE_c1"))
+        .args(["check", "--color"])
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    process.stdin.take().unwrap().write_all(b"void f(").unwrap();
+    let output = process.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).starts_with("\x1b[1;31merror\x1b[0m\x1b[1m: "));
+    assert!(stderr(&output).contains("\x1b[1;34m- this `(` was never closed\x1b[0m"));
+}
+
+#[test]
+fn graphviz_output() {
+    // The derivation tree is drawn up to the syntax error
+    let output = c1("dot-parse", "void main() { x }");
+    assert!(output.status.success());
+    assert!(stdout(&output).starts_with("digraph parse_tree {\n"));
+    assert!(stdout(&output).contains("[label=\"error\", color=red];"));
+
+    let output = c1("dot-calls", "void main() { f(); }");
+    assert!(output.status.success());
+    assert!(stdout(&output).contains("    n0 -> n1;\n"));
+
+    assert_eq!(c1("dot-ast", "void main() { x }").status.code(), Some(1));
+}
+
+#[test]
+fn json_output() {
+    let output = c1("json", "void main() {}");
+    assert!(output.status.success());
+    let program: serde_json::Value = serde_json::from_str(stdout(&output)).unwrap();
+    assert_eq!(program["functions"][0]["name"], "main");
+
+    let output = c1("tokens", "void\n$ x #");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stderr(&output),
+        "\
+error: unrecognized text
+ --> <stdin>:2:1
+  |
+2 | $ x #
+  | ^
+
+error: unrecognized text
+ --> <stdin>:2:5
+  |
+2 | $ x #
+  |     ^
+"
+    );
+}
+
+#[test]
+fn trace_output() {
+    let output = c1("trace", "void main() {}");
+    assert!(output.status.success());
+    let trace: Vec<&str> = stdout(&output).lines().collect();
+    assert_eq!(trace.len(), 8);
+    assert_eq!(trace[2], "    enter type at line 1 'void'");
+    assert_eq!(trace[7], "accept program at EOF");
+}
+
+#[test]
+fn ll1_tables() {
+    let output = Command::new(env!("CARGO_BIN_EXE_c1"))
+        .arg("ll1")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let tables = stdout(&output);
+    assert!(tables.contains(
+        "\n    statementlist       <ID> <KW_IF> <KW_PRINTF> <KW_RETURN> \"{\" (nullable)\n"
+    ));
+    assert!(tables.ends_with(
+        "LL(1) conflicts
+    statement: 'statassignment \";\"' and 'functioncall \";\"' both start with <ID>
+    assignment: '<ID> \"=\" assignment' and 'expr' both start with <ID>
+    factor: 'functioncall' and '<ID>' both start with <ID>
+"
+    ));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_c1"))
+        .args(["ll1", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .and_then(|mut process| {
+            process
+                .stdin
+                .take()
+                .unwrap()
+                .write_all(b"list ::= ( <ID> )? <ID>")?;
+            process.wait_with_output()
+        })
+        .unwrap();
+    assert_eq!(
+        stdout(&output),
+        "FIRST
+    list  <ID>
+FOLLOW
+    list  <EOF>
+LL(1) conflicts
+    list: '( <ID> )?' may start with and be followed by <ID>
+"
+    );
+}
+
+#[test]
+fn lalr_item_sets() {
+    let output = Command::new(env!("CARGO_BIN_EXE_c1"))
+        .arg("lalr")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let automaton = stdout(&output);
+    assert!(automaton.starts_with("state 0\n    program' ::= . program  [<EOF>]\n"));
+    assert!(automaton.contains("\nstate 88\n"));
+    assert!(automaton.ends_with("LALR(1) conflicts\n    none\n"));
+}
+
+#[test]
+fn usage_errors() {
+    let output = c1("compile", "");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).starts_with("usage: c1 <command> [file]"));
+}
@@ -0,0 +1,76 @@
+//! Snapshot tests of rendered diagnostics. Run with UPDATE_SNAPSHOTS=1 to write the snapshots in
+//! tests/snapshots after changing the rendering on purpose.
+use cb_3::diagnostic::Diagnostic;
+use cb_3::source::SourceMap;
+use cb_3::{Dialect, ParserOptions};
+use std::{env, fs};
+
+fn assert_snapshot(name: &str, rendered: &str) {
+    let path = format!("tests/snapshots/{}.txt", name);
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::write(&path, rendered).unwrap();
+    }
+    let snapshot = fs::read_to_string(&path).unwrap();
+    assert!(
+        snapshot == rendered,
+        "{} differs from the rendered diagnostic:\n{}",
+        path,
+        rendered
+    );
+}
+
+/// Parse one file as C1 and render its syntax error
+fn render_error(text: &str, colour: bool) -> String {
+    let mut sources = SourceMap::new();
+    sources.add("main.c1", text);
+    let options = ParserOptions {
+        dialect: Dialect::C1,
+        ..ParserOptions::default()
+    };
+    let error = sources.parse_program(options).unwrap_err();
+    Diagnostic::from_parse_error(&error, &sources).render(&sources, colour)
+}
+
+#[test]
+fn unexpected_token() {
+    let text = "void main() {\n\tprintf(\"äöü\" 1);\n}\n";
+    assert_snapshot("unexpected_token", &render_error(text, false));
+}
+
+#[test]
+fn unclosed_brace() {
+    let text = "int f(int n) {\n    if (n < 2) {\n        return n;\n    }\n    return f(n - 1);\n";
+    assert_snapshot("unclosed_brace", &render_error(text, false));
+}
+
+#[test]
+fn mismatched_bracket() {
+    let text = "void main() {\n    printf(f(1, 2});\n}\n";
+    assert_snapshot("mismatched_bracket", &render_error(text, false));
+}
+
+#[test]
+fn labels_in_several_files() {
+    let mut sources = SourceMap::new();
+    sources.add(
+        "main.c1",
+        format!("{}void main() {{\n    f(1);\n}}\n", "\n".repeat(9)),
+    );
+    sources.add("f.c1", "void f() {}\n");
+    let call = sources.files().next().unwrap().text().find("f(1)").unwrap();
+    let definition = sources.files().nth(1).unwrap().span().start + 5;
+    let diagnostic = Diagnostic::warning("'f' takes 0 arguments but got 1", call..call + 4)
+        .with_label("called with 1 argument")
+        .with_secondary(definition..definition + 1, "defined here")
+        .with_secondary(9..13, "");
+    assert_snapshot(
+        "labels_in_several_files",
+        &diagnostic.render(&sources, false),
+    );
+}
+
+#[test]
+fn colour() {
+    let text = "void main() {\n    printf(1);\n";
+    assert_snapshot("colour", &render_error(text, true));
+}
@@ -0,0 +1,130 @@
+//! The table-driven LlParser and LrParser, computed from c-1-syntax.ebnf, and the hand-written
+//! C1Parser accept the same texts and fail at the same token.
+use cb_3::grammar::{Generator, GeneratorOptions, Grammar, LlParser, LrParser};
+use cb_3::{tokenize, C1Parser};
+
+const HAND_WRITTEN: &[&str] = &[
+    "",
+    "// only a comment",
+    "  bool  ",
+    "x = 0;",
+    "void foo() {}",
+    "void foo()) {}",
+    "const bar() {return 0;}",
+    "int bar() {return 0;}",
+    "void f() { return; }",
+    "void f() { { { } } {} }",
+    "void f() { if (x) if (y) z = 1; else; }",
+    "void f() { if (x = y = 1 < 2) { printf(-x * 2 && y || z); } }",
+    "void f() { x = -(-1); }",
+    "void f() { x = - -1; }",
+    "void f() { x = 1 < 2 < 3; }",
+    "void f() { x = (1 < 2) < 3; }",
+    "void f() { x = f() + f; }",
+    "void f() { x == 1; }",
+    "void f() { x(); y }",
+    "void f() { x = ; }",
+    "void f() { printf(); }",
+    "void f() { return x = 1 }",
+    "void f() { 1 = x; }",
+    "void f() { x = 1.5e3 / .5 - true; }",
+    "void f() { x = 99999999999999999999; }",
+    "void f() { x = y = z; }",
+    "void f() { x = (y = z); }",
+    "void f() { while (x) {} }",
+    "void f() { x = $; }",
+    "float f() {} int g() {} bool h() {}",
+    "void f() {",
+    "void f() { x = (1;",
+];
+
+struct Engines {
+    ll: LlParser,
+    lr: LrParser,
+}
+
+impl Engines {
+    fn new(grammar: &Grammar) -> Engines {
+        Engines {
+            ll: LlParser::new(grammar).unwrap(),
+            lr: LrParser::new(grammar).unwrap(),
+        }
+    }
+}
+
+fn check(engines: &Engines, text: &str) {
+    let expected = C1Parser::parse(text);
+    for (name, actual) in [
+        ("LlParser", engines.ll.parse(text)),
+        ("LrParser", engines.lr.parse(text)),
+    ] {
+        match (&expected, &actual) {
+            (Ok(()), Ok(())) => {}
+            (Err(expected), Err(actual))
+                if (expected.line, &expected.text, &expected.span)
+                    == (actual.line, &actual.text, &actual.span) => {}
+            _ => panic!(
+                "{:?}\nC1Parser: {:?}\n{}: {:?}",
+                text, expected, name, actual
+            ),
+        }
+    }
+}
+
+#[test]
+fn generated_programs() {
+    let grammar = Grammar::c1();
+    let engines = Engines::new(&grammar);
+    for seed in 1..=100 {
+        let options = GeneratorOptions {
+            seed,
+            ..GeneratorOptions::default()
+        };
+        let mut generator = Generator::new(&grammar, options);
+        for _ in 0..10 {
+            check(&engines, &generator.program());
+            check(&engines, &generator.invalid_program());
+        }
+    }
+}
+
+#[test]
+fn hand_written_programs() {
+    let engines = Engines::new(&Grammar::c1());
+    for text in HAND_WRITTEN {
+        check(&engines, text);
+    }
+
+    // The example, every prefix of it and the example without one of its tokens
+    let text = include_str!("data/beispiel.c-1");
+    for (end, _) in text.char_indices() {
+        check(&engines, &text[..end]);
+    }
+    for token in tokenize(text).unwrap() {
+        let mut text = text.to_string();
+        text.replace_range(token.span, "");
+        check(&engines, &text);
+    }
+}
+
+#[test]
+fn short_expressions() {
+    // Every expression of up to five of these tokens, against the grammar the operator table
+    // of C1Parser replaces
+    const TOKENS: &[&str] = &["a", "1", "-", "+", "*", "<", "==", "(", ")"];
+    let engines = Engines::new(&Grammar::c1());
+    let mut expressions = vec![String::new()];
+    for _ in 0..5 {
+        expressions = expressions
+            .iter()
+            .flat_map(|expression| {
+                TOKENS
+                    .iter()
+                    .map(move |token| expression.clone() + " " + token)
+            })
+            .collect();
+        for expression in &expressions {
+            check(&engines, &format!("void f() {{ x ={}; }}", expression));
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! The JSON field names are read by external scripts and must stay stable
+#![cfg(feature = "serde")]
+use cb_3::{tokenize, C1Parser};
+use serde_json::json;
+
+#[test]
+fn tokens_as_json() {
+    let tokens = tokenize("int x;\n  x = 1.5;").unwrap();
+    assert_eq!(
+        serde_json::to_value(&tokens[..2]).unwrap(),
+        json!([
+            {"kind": "kw_int", "text": "int", "line": 1, "column": 1, "span": {"start": 0, "end": 3}},
+            {"kind": "identifier", "text": "x", "line": 1, "column": 5, "span": {"start": 4, "end": 5}},
+        ])
+    );
+    assert_eq!(
+        serde_json::to_value(&tokens[5]).unwrap(),
+        json!({"kind": "const_float", "text": "1.5", "line": 2, "column": 7, "span": {"start": 13, "end": 16}})
+    );
+}
+
+#[test]
+fn syntax_tree_as_json() {
+    let program = C1Parser::parse_program(
+        "bool f() {\n  if (x != -1) { return g(); }\n  printf(y = true);\n}",
+    )
+    .unwrap();
+    let int = |value: i64| json!({"kind": {"int": value}, "line": 2});
+    assert_eq!(
+        serde_json::to_value(&program).unwrap(),
+        json!({"functions": [{
+            "return_type": "boolean",
+            "name": "f",
+            "line": 1,
+            "body": [
+                {"kind": {"if": {
+                    "condition": {"kind": {"binary": {
+                        "operator": "not_equal",
+                        "lhs": {"kind": {"variable": "x"}, "line": 2},
+                        "rhs": {"kind": {"negate": int(1)}, "line": 2},
+                    }}, "line": 2},
+                    "body": {"kind": {"block": [
+                        {"kind": {"return": {"kind": {"call": "g"}, "line": 2}}, "line": 2},
+                    ]}, "line": 2},
+                }}, "line": 2},
+                {"kind": {"printf": {"kind": {"assign": {
+                    "target": "y",
+                    "value": {"kind": {"bool": true}, "line": 3},
+                }}, "line": 3}}, "line": 3},
+            ],
+        }]})
+    );
+}
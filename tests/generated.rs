@@ -0,0 +1,44 @@
+use cb_3::grammar::{Generator, GeneratorOptions, Grammar};
+use cb_3::C1Parser;
+
+#[test]
+fn parser_accepts_generated_programs() {
+    let grammar = Grammar::c1();
+    for seed in 1..=50 {
+        for max_depth in [4, 8, 16, 24] {
+            let options = GeneratorOptions {
+                max_depth,
+                max_repeat: 3,
+                seed,
+            };
+            let mut generator = Generator::new(&grammar, options);
+            for _ in 0..10 {
+                let program = generator.program();
+                if let Err(error) = C1Parser::parse(&program) {
+                    panic!("{:?} rejected {}: {}", options, program, error);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn parser_rejects_near_misses() {
+    let grammar = Grammar::c1();
+    for seed in 1..=50 {
+        let options = GeneratorOptions {
+            seed,
+            ..GeneratorOptions::default()
+        };
+        let mut generator = Generator::new(&grammar, options);
+        for _ in 0..20 {
+            let program = generator.invalid_program();
+            assert!(
+                C1Parser::parse(&program).is_err(),
+                "{:?} accepted {}",
+                options,
+                program
+            );
+        }
+    }
+}
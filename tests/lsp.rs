@@ -0,0 +1,212 @@
+use cb_3::lsp::{read_message, write_message};
+use serde_json::{json, Value};
+use std::io::{BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+const URI: &str = "file:///example.c-1";
+
+/// A c1-lsp process driven through its stdin and stdout
+struct Client {
+    process: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: i64,
+}
+
+impl Client {
+    fn start() -> Client {
+        let mut process = Command::new(env!("CARGO_BIN_EXE_c1-lsp"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdin = process.stdin.take().unwrap();
+        let stdout = BufReader::new(process.stdout.take().unwrap());
+        let mut client = Client {
+            process,
+            stdin,
+            stdout,
+            next_id: 1,
+        };
+        let capabilities = client.request("initialize", json!({"capabilities": {}}));
+        assert_eq!(capabilities["capabilities"]["textDocumentSync"], 1);
+        client.notify("initialized", json!({}));
+        client
+    }
+
+    fn send(&mut self, message: Value) {
+        write_message(&mut self.stdin, &message).unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let body = read_message(&mut self.stdout).unwrap().unwrap();
+        serde_json::from_str(&body).unwrap()
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({"jsonrpc": "2.0", "method": method, "params": params}));
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params}));
+        let response = self.receive();
+        assert_eq!(response["id"], id, "{}", response);
+        response["result"].clone()
+    }
+
+    /// Open the document and return the published diagnostics
+    fn open(&mut self, text: &str) -> Value {
+        self.notify(
+            "textDocument/didOpen",
+            json!({"textDocument": {"uri": URI, "languageId": "c-1", "version": 1, "text": text}}),
+        );
+        self.diagnostics()
+    }
+
+    fn diagnostics(&mut self) -> Value {
+        let notification = self.receive();
+        assert_eq!(notification["method"], "textDocument/publishDiagnostics");
+        assert_eq!(notification["params"]["uri"], URI);
+        notification["params"]["diagnostics"].clone()
+    }
+
+    fn at(&mut self, method: &str, line: u64, character: u64) -> Value {
+        self.request(
+            method,
+            json!({
+                "textDocument": {"uri": URI},
+                "position": {"line": line, "character": character},
+                "context": {"includeDeclaration": true},
+            }),
+        )
+    }
+
+    fn shutdown(mut self) -> i32 {
+        assert_eq!(self.request("shutdown", Value::Null), Value::Null);
+        self.notify("exit", Value::Null);
+        self.stdin.flush().unwrap();
+        self.process.wait().unwrap().code().unwrap()
+    }
+}
+
+fn range(start: (u64, u64), end: (u64, u64)) -> Value {
+    json!({
+        "start": {"line": start.0, "character": start.1},
+        "end": {"line": end.0, "character": end.1},
+    })
+}
+
+#[test]
+fn publishes_diagnostics_on_open_and_change() {
+    let mut client = Client::start();
+    assert_eq!(
+        client.open("void main() {\n  printf(1)\n}"),
+        json!([{
+            "range": range((2, 0), (2, 1)),
+            "severity": 1,
+            "source": "c1",
+            "message": "unexpected token at line 3 with text: '}'",
+        }])
+    );
+
+    client.notify(
+        "textDocument/didChange",
+        json!({
+            "textDocument": {"uri": URI, "version": 2},
+            "contentChanges": [{"text": "void main() {\n  x = 1;\n}"}],
+        }),
+    );
+    let diagnostics = client.diagnostics();
+    assert_eq!(diagnostics[0]["severity"], 2);
+    assert_eq!(diagnostics[0]["code"], "dead-assignment");
+    assert_eq!(diagnostics[0]["range"], range((1, 0), (1, 8)));
+
+    client.notify(
+        "textDocument/didChange",
+        json!({
+            "textDocument": {"uri": URI, "version": 3},
+            "contentChanges": [{"text": "void main() {}"}],
+        }),
+    );
+    assert_eq!(client.diagnostics(), json!([]));
+
+    client.notify(
+        "textDocument/didClose",
+        json!({"textDocument": {"uri": URI}}),
+    );
+    assert_eq!(client.diagnostics(), json!([]));
+    assert_eq!(client.shutdown(), 0);
+}
+
+#[test]
+fn navigates_function_identifiers() {
+    let mut client = Client::start();
+    let text = std::fs::read_to_string("tests/data/beispiel.c-1").unwrap();
+    assert_eq!(client.open(&text), json!([]));
+
+    // "blub" in "printf(blub());" in main
+    let definition = client.at("textDocument/definition", 27, 10);
+    assert_eq!(
+        definition,
+        json!({"uri": URI, "range": range((0, 4), (0, 8))})
+    );
+
+    let references = client.at("textDocument/references", 0, 5);
+    let lines: Vec<&Value> = references
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|location| &location["range"]["start"]["line"])
+        .collect();
+    assert_eq!(lines, vec![0, 12, 13, 14, 14, 27]);
+
+    let hover = client.at("textDocument/hover", 28, 9);
+    assert_eq!(hover["contents"]["value"], "```c\nfloat blah()\n```");
+    assert_eq!(hover["range"], range((28, 8), (28, 12)));
+
+    // Variables are no function identifiers
+    assert_eq!(client.at("textDocument/definition", 1, 2), Value::Null);
+    assert_eq!(client.at("textDocument/hover", 1, 2), Value::Null);
+
+    let symbols = client.request(
+        "textDocument/documentSymbol",
+        json!({"textDocument": {"uri": URI}}),
+    );
+    assert_eq!(symbols[0]["name"], "blub");
+    assert_eq!(symbols[0]["detail"], "int");
+    assert_eq!(symbols[0]["kind"], 12);
+    assert_eq!(symbols[0]["range"], range((0, 0), (7, 1)));
+    assert_eq!(symbols[0]["selectionRange"], range((0, 4), (0, 8)));
+    let names: Vec<&Value> = symbols
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|symbol| &symbol["name"])
+        .collect();
+    assert_eq!(names, vec!["blub", "blah", "main"]);
+
+    assert_eq!(client.shutdown(), 0);
+}
+
+#[test]
+fn reports_protocol_errors() {
+    let mut client = Client::start();
+    client.send(json!({"jsonrpc": "2.0", "id": 99, "method": "unknown/method"}));
+    let response = client.receive();
+    assert_eq!(response["id"], 99);
+    assert_eq!(response["error"]["code"], -32601);
+
+    client.send(json!({
+        "jsonrpc": "2.0",
+        "id": 100,
+        "method": "textDocument/hover",
+        "params": {"textDocument": {"uri": "file:///closed.c-1"}, "position": {"line": 0, "character": 0}},
+    }));
+    assert_eq!(client.receive()["error"]["code"], -32602);
+
+    // Exiting without shutdown request signals an error
+    client.notify("exit", Value::Null);
+    assert_eq!(client.process.wait().unwrap().code(), Some(1));
+}
@@ -0,0 +1,117 @@
+//! Every input produces Ok or Err, never a panic or a stack overflow. The fuzz targets in fuzz/
+//! check the same properties on inputs found by coverage guidance.
+use cb_3::cst::Cst;
+use cb_3::incremental::IncrementalParse;
+use cb_3::operators::{Associativity, Operator};
+use cb_3::{dot, tokenize, C1Lexer, C1Parser, C1Token, ParserOptions};
+
+/// Pieces of C1 and of things that are no C1, so that random texts hit many paths
+const PIECES: &[&str] = &[
+    "void",
+    "int",
+    "if",
+    "while",
+    "return",
+    "printf",
+    "x",
+    "f",
+    "1",
+    "2.5",
+    ".5e3",
+    "1e",
+    "true",
+    "(",
+    ")",
+    "{",
+    "}",
+    ";",
+    "=",
+    "==",
+    "<",
+    "-",
+    "+",
+    "*",
+    "/",
+    "&&",
+    "||",
+    " ",
+    "\n",
+    "\r\n",
+    "/*",
+    "*/",
+    "//",
+    "\"",
+    "$",
+    "ä",
+    "\t",
+    "9999999999999999999999",
+];
+
+fn check(text: &str) {
+    let restored: String = C1Lexer::with_trivia(text).map(|token| token.text).collect();
+    assert_eq!(restored, text);
+    let _ = tokenize(text);
+    let result = C1Parser::parse_program(text);
+    let cst = Cst::parse(text);
+    assert_eq!(cst.to_string(), text);
+    assert_eq!(cst.error().is_some(), result.is_err(), "{:?}", text);
+    let _ = dot::parse_tree(text);
+    assert_eq!(IncrementalParse::new(text).result().is_ok(), result.is_ok());
+}
+
+#[test]
+fn random_texts() {
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut below = |bound: usize| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % bound as u64) as usize
+    };
+    for _ in 0..2000 {
+        let length = below(60);
+        let text: String = (0..length).map(|_| PIECES[below(PIECES.len())]).collect();
+        check(&text);
+    }
+}
+
+#[test]
+fn deeply_nested_texts() {
+    for (open, close) in [("(", ")"), ("{", "}"), ("x = ", ""), ("if (x) ", "")] {
+        for prefix in ["", "void f() ", "void f() { ", "int f() { return "] {
+            let text = format!(
+                "{}{}1;{}",
+                prefix,
+                open.repeat(20_000),
+                close.repeat(20_000)
+            );
+            check(&text);
+        }
+    }
+}
+
+#[test]
+fn custom_operator_tables() {
+    // Levels beyond those of C(-1), and tokens that have no node of the syntax tree
+    const OPERATORS: &[Operator] = &[
+        Operator::infix(C1Token::Plus, 1, Associativity::Left),
+        Operator::infix(C1Token::Assign, 2, Associativity::Left),
+        Operator::prefix(C1Token::Asterisk, 3, Associativity::None),
+        Operator::prefix(C1Token::Minus, 4, Associativity::Right),
+    ];
+    let parse = |text: &str| {
+        let options = ParserOptions {
+            operators: OPERATORS,
+            ..ParserOptions::default()
+        };
+        C1Parser::with_options(text, options).program()
+    };
+    assert!(parse("void f() { x = - -y + 1; }").is_ok());
+    let error = parse("void f() { x = *y; }").unwrap_err();
+    assert_eq!(error.reason, "not a prefix operator");
+    let error = parse("void f() { x = (1 = 2); }").unwrap_err();
+    assert_eq!(error.reason, "not a binary operator");
+    let text = format!("void f() {{ x = {}1; }}", "-".repeat(200_000));
+    let error = parse(&text).unwrap_err();
+    assert_eq!(error.reason, "nesting too deep");
+}